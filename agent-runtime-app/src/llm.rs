@@ -2,6 +2,7 @@ use serde_json::{json, Value};
 
 #[derive(Debug, Clone)]
 pub struct LlmConfig {
+    #[allow(dead_code)]
     pub enabled: bool,
     pub provider: String,
     pub base_url: String,
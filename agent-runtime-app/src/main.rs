@@ -1,8 +1,15 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use agent_runtime::runtime::InMemoryRuntime;
-use agent_runtime::server::router;
+use agent_runtime::auth::AuthConfig;
+use agent_runtime::grpc;
+use agent_runtime::runtime::{
+    ConcurrencyLimits, InMemoryRuntime, RetentionPolicy, RetryPolicy, TenantQuota,
+    WorkflowOptions,
+};
+use agent_runtime::store::SqliteRunStore;
+use agent_runtime::server::{shutdown_signal, RouterBuilder};
 use serde_json::json;
 use sqlx::MySqlPool;
 
@@ -13,7 +20,26 @@ use workflows::{ConversationWorkflow, DailyBriefingWorkflow, EchoWorkflow, Meeti
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
-    let runtime = Arc::new(InMemoryRuntime::new());
+    let runtime = match std::env::var("RUN_STORE_PATH") {
+        Ok(path) if !path.trim().is_empty() => {
+            let store = SqliteRunStore::open(&path).expect("open run store");
+            InMemoryRuntime::with_store(Arc::new(store))
+        }
+        _ => InMemoryRuntime::new(),
+    };
+    let runtime = match std::env::var("EVENT_BUFFER") {
+        Ok(capacity) => {
+            let capacity = capacity.trim().parse().expect("valid EVENT_BUFFER");
+            runtime.with_event_buffer(capacity)
+        }
+        Err(_) => runtime,
+    };
+    let runtime = Arc::new(
+        runtime
+            .with_retention(retention_from_env())
+            .with_concurrency_limits(concurrency_from_env()),
+    );
+    runtime.spawn_retention_sweeper();
     let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let db = MySqlPool::connect(&db_url).await.expect("connect db");
     runtime
@@ -36,7 +62,7 @@ async fn main() {
         )
         .await;
     runtime
-        .register_workflow_with_schemas(
+        .register_workflow_with_options(
             Arc::new(MeetingTodoWorkflow),
             Some(json!({
                 "type": "object",
@@ -63,10 +89,15 @@ async fn main() {
                 },
                 "required": ["todos"]
             })),
+            WorkflowOptions {
+                timeout: Some(Duration::from_secs(120)),
+                retry: RetryPolicy::exponential(3),
+                ..WorkflowOptions::default()
+            },
         )
         .await;
     runtime
-        .register_workflow_with_schemas(
+        .register_workflow_with_options(
             Arc::new(ConversationWorkflow),
             Some(json!({
                 "type": "object",
@@ -105,10 +136,15 @@ async fn main() {
                 },
                 "required": ["conversation_id", "reply", "messages"]
             })),
+            WorkflowOptions {
+                timeout: Some(Duration::from_secs(120)),
+                retry: RetryPolicy::exponential(3),
+                ..WorkflowOptions::default()
+            },
         )
         .await;
     runtime
-        .register_workflow_with_schemas(
+        .register_workflow_with_options(
             Arc::new(DailyBriefingWorkflow::new(db.clone())),
             Some(json!({
                 "type": "object",
@@ -132,15 +168,99 @@ async fn main() {
                 },
                 "required": ["date", "category", "facts_recap", "report_path"]
             })),
+            WorkflowOptions {
+                timeout: Some(Duration::from_secs(300)),
+                retry: RetryPolicy::exponential(3),
+                ..WorkflowOptions::default()
+            },
         )
         .await;
 
-    let app = router(runtime);
+    let recovery = runtime.recover_runs().await.expect("recover runs");
+    println!(
+        "recovered runs: {} requeued, {} restarted, {} failed",
+        recovery.requeued.len(),
+        recovery.restarted.len(),
+        recovery.failed.len()
+    );
+
+    let auth = match std::env::var("API_KEYS_FILE") {
+        Ok(path) if !path.trim().is_empty() => {
+            Some(AuthConfig::load(path.trim()).expect("load API keys"))
+        }
+        _ if env_flag("ALLOW_UNAUTHENTICATED") => {
+            println!("warning: API_KEYS_FILE not set; serving without authentication");
+            None
+        }
+        _ => panic!("set API_KEYS_FILE, or ALLOW_UNAUTHENTICATED=true for local development"),
+    };
+    let app = RouterBuilder::new(runtime.clone());
+    let app = match auth {
+        Some(auth) => app.auth(auth),
+        None => app,
+    };
+    let grpc = std::env::var("GRPC_ADDR")
+        .ok()
+        .filter(|addr| !addr.trim().is_empty())
+        .map(|addr| {
+            let addr: SocketAddr = addr.trim().parse().expect("valid GRPC_ADDR");
+            let service = app.grpc_service();
+            println!("agent runtime gRPC listening on {}", addr);
+            tokio::spawn(grpc::serve(addr, service, shutdown_signal()))
+        });
+    let app = app.build();
     let addr: SocketAddr = "127.0.0.1:9000".parse().expect("valid addr");
     println!("agent runtime listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr)
         .await
         .expect("bind");
-    axum::serve(listener, app).await.expect("serve");
+    let drain = Duration::from_secs(env_number("SHUTDOWN_DRAIN_SECS").unwrap_or(30));
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            println!("shutting down; draining runs for up to {:?}", drain);
+            let report = runtime.shutdown(drain).await;
+            println!(
+                "shutdown: {} runs requeued, {} failed",
+                report.requeued.len(),
+                report.failed.len()
+            );
+        })
+        .await
+        .expect("serve");
+    if let Some(grpc) = grpc {
+        grpc.await.expect("gRPC task").expect("serve gRPC");
+    }
+}
+
+fn env_flag(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|value| matches!(value.trim(), "1" | "true"))
+}
+
+fn env_number(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().parse().unwrap_or_else(|_| panic!("valid {name}")))
+}
+
+fn retention_from_env() -> RetentionPolicy {
+    RetentionPolicy {
+        max_events_per_run: env_number("MAX_EVENTS_PER_RUN").map(|max| max as usize),
+        run_ttl: env_number("RUN_TTL_SECS").map(Duration::from_secs),
+        artifact_ttl: env_number("ARTIFACT_TTL_SECS").map(Duration::from_secs),
+        ..RetentionPolicy::default()
+    }
+}
+
+fn concurrency_from_env() -> ConcurrencyLimits {
+    ConcurrencyLimits {
+        max_running: env_number("MAX_CONCURRENT_RUNS").map(|max| max as usize),
+        max_queued: env_number("MAX_QUEUED_RUNS").map(|max| max as usize),
+        per_tenant: TenantQuota {
+            max_running: env_number("TENANT_MAX_CONCURRENT_RUNS").map(|max| max as usize),
+            max_queued: env_number("TENANT_MAX_QUEUED_RUNS").map(|max| max as usize),
+        },
+        ..ConcurrencyLimits::default()
+    }
 }
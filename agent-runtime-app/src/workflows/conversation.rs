@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use agent_runtime::runtime::{AgentError, WorkflowOutput, WorkflowRunner};
+use agent_runtime::runtime::{AgentError, RunContext, WorkflowOutput, WorkflowRunner};
 use agent_runtime::types::{Artifact, ArtifactType};
 use chrono::Utc;
 use once_cell::sync::Lazy;
@@ -38,7 +38,7 @@ impl WorkflowRunner for ConversationWorkflow {
         Some("0.1.0")
     }
 
-    async fn run(&self, input: Value) -> Result<WorkflowOutput, AgentError> {
+    async fn run(&self, input: Value, _ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
         let parsed: ConversationInput =
             serde_json::from_value(input).map_err(|err| AgentError::fatal(err.to_string()))?;
 
@@ -92,6 +92,8 @@ impl WorkflowRunner for ConversationWorkflow {
             mime_type: Some("application/json".to_string()),
             data: Some(output.clone()),
             file: None,
+            tenant_id: None,
+            run_id: None,
         };
 
         Ok(WorkflowOutput {
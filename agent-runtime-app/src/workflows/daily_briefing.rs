@@ -1,4 +1,4 @@
-use agent_runtime::runtime::{AgentError, WorkflowOutput, WorkflowRunner};
+use agent_runtime::runtime::{AgentError, RunContext, WorkflowOutput, WorkflowRunner};
 use agent_runtime::types::{Artifact, ArtifactType};
 use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
 use rust_decimal::prelude::ToPrimitive;
@@ -38,7 +38,7 @@ impl WorkflowRunner for DailyBriefingWorkflow {
         Some("0.1.0")
     }
 
-    async fn run(&self, input: Value) -> Result<WorkflowOutput, AgentError> {
+    async fn run(&self, input: Value, _ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
         let parsed: DailyBriefingInput =
             serde_json::from_value(input).map_err(|err| AgentError::fatal(err.to_string()))?;
 
@@ -240,6 +240,8 @@ impl WorkflowRunner for DailyBriefingWorkflow {
             mime_type: Some("text/markdown".to_string()),
             data: Some(json!({ "path": report_path })),
             file: None,
+            tenant_id: None,
+            run_id: None,
         };
 
         Ok(WorkflowOutput {
@@ -260,6 +262,7 @@ fn parse_date(input: &str) -> Option<NaiveDate> {
         .or_else(|| NaiveDate::parse_from_str(input, "%Y%m%d").ok())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_report(
     date: NaiveDate,
     operation_count: i64,
@@ -316,50 +319,7 @@ fn render_report(
     lines.join("\n")
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[tokio::test]
-    #[ignore = "requires LLM and network access"]
-    async fn llm_briefing_sections() {
-        dotenvy::dotenv().ok();
-        let Some(config) = LlmConfig::from_env() else {
-            eprintln!("LLM not enabled; set LLM_ENABLED=1 to run this test");
-            return;
-        };
-        let client = LlmClient::new(config);
-        let tomorrow_list = vec![json!({
-            "customer_name": "张三",
-            "time": "10:00",
-            "doctor": "李医生",
-            "consultant": "王顾问",
-        })];
-        let risks = vec!["今日预约为 0，需排查获客/预约渠道".to_string()];
-        let checklist = vec!["核对明日预约客户名单并逐一确认到诊".to_string()];
-
-        let content = build_llm_report(
-            &client,
-            NaiveDate::from_ymd_opt(2025, 1, 6).unwrap(),
-            12,
-            3456.78,
-            0,
-            3,
-            5,
-            &tomorrow_list,
-            &risks,
-            &checklist,
-        )
-        .await
-        .expect("llm report");
-
-        assert!(content.contains("Facts Recap"));
-        assert!(content.contains("明日客户清单"));
-        assert!(content.contains("风险提示"));
-        assert!(content.contains("执行 checklist"));
-    }
-}
-
+#[allow(clippy::too_many_arguments)]
 async fn build_llm_report(
     client: &LlmClient,
     date: NaiveDate,
@@ -415,3 +375,47 @@ Checklist JSON: {}\n",
         .await
         .map_err(|err| AgentError::retryable(format!("llm error: {}", err)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "requires LLM and network access"]
+    async fn llm_briefing_sections() {
+        dotenvy::dotenv().ok();
+        let Some(config) = LlmConfig::from_env() else {
+            eprintln!("LLM not enabled; set LLM_ENABLED=1 to run this test");
+            return;
+        };
+        let client = LlmClient::new(config);
+        let tomorrow_list = vec![json!({
+            "customer_name": "张三",
+            "time": "10:00",
+            "doctor": "李医生",
+            "consultant": "王顾问",
+        })];
+        let risks = vec!["今日预约为 0，需排查获客/预约渠道".to_string()];
+        let checklist = vec!["核对明日预约客户名单并逐一确认到诊".to_string()];
+
+        let content = build_llm_report(
+            &client,
+            NaiveDate::from_ymd_opt(2025, 1, 6).unwrap(),
+            12,
+            3456.78,
+            0,
+            3,
+            5,
+            &tomorrow_list,
+            &risks,
+            &checklist,
+        )
+        .await
+        .expect("llm report");
+
+        assert!(content.contains("Facts Recap"));
+        assert!(content.contains("明日客户清单"));
+        assert!(content.contains("风险提示"));
+        assert!(content.contains("执行 checklist"));
+    }
+}
@@ -1,4 +1,4 @@
-use agent_runtime::runtime::{AgentError, WorkflowOutput, WorkflowRunner};
+use agent_runtime::runtime::{AgentError, RunContext, WorkflowOutput, WorkflowRunner};
 use agent_runtime::types::{Artifact, ArtifactType};
 use chrono::Utc;
 use serde_json::{json, Value};
@@ -16,7 +16,7 @@ impl WorkflowRunner for EchoWorkflow {
         Some("0.1.0")
     }
 
-    async fn run(&self, input: Value) -> Result<WorkflowOutput, AgentError> {
+    async fn run(&self, input: Value, _ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
         let artifact = Artifact {
             artifact_id: format!("art_{}", Uuid::new_v4()),
             r#type: ArtifactType::Record,
@@ -25,6 +25,8 @@ impl WorkflowRunner for EchoWorkflow {
             mime_type: Some("application/json".to_string()),
             data: Some(json!({ "echo": input })),
             file: None,
+            tenant_id: None,
+            run_id: None,
         };
         Ok(WorkflowOutput {
             output: json!({ "echo": input }),
@@ -1,4 +1,4 @@
-use agent_runtime::runtime::{AgentError, WorkflowOutput, WorkflowRunner};
+use agent_runtime::runtime::{AgentError, RunContext, WorkflowOutput, WorkflowRunner};
 use agent_runtime::types::{Artifact, ArtifactType};
 use chrono::Utc;
 use serde_json::{json, Value};
@@ -18,7 +18,7 @@ impl WorkflowRunner for MeetingTodoWorkflow {
         Some("0.1.0")
     }
 
-    async fn run(&self, input: Value) -> Result<WorkflowOutput, AgentError> {
+    async fn run(&self, input: Value, _ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
         // 读取会议纪要文本，空值则走空字符串
         let summary_text = input
             .get("summary")
@@ -44,6 +44,8 @@ impl WorkflowRunner for MeetingTodoWorkflow {
             mime_type: Some("application/json".to_string()),
             data: Some(output.clone()),
             file: None,
+            tenant_id: None,
+            run_id: None,
         };
         // 产出结构化 todos 结果
         Ok(WorkflowOutput {
@@ -0,0 +1,31 @@
+use agent_runtime::types::{RunCreateRequest, WorkflowRef};
+use agent_sdk::client::Client;
+use futures_util::StreamExt;
+use serde_json::json;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let base_url = std::env::var("AGENT_BASE_URL")
+        .unwrap_or_else(|_| "http://127.0.0.1:9000".to_string());
+    let client = Client::new(base_url).with_bearer_auth("dev-token");
+    let request = RunCreateRequest {
+        workflow: WorkflowRef {
+            name: "echo".to_string(),
+            version: Some("0.1.0".to_string()),
+        },
+        input: json!({ "hello": "ws" }),
+        context: None,
+        metadata: None,
+        labels: None,
+    };
+
+    let created = client.create_run(request).await?;
+    let mut socket = client.event_socket().await?;
+    socket.subscribe(&created.run.run_id, None).await?;
+
+    println!("streaming messages (Ctrl+C to stop)...");
+    while let Some(message) = socket.next().await {
+        println!("{}", serde_json::to_string(&message?)?);
+    }
+    Ok(())
+}
@@ -1,7 +1,17 @@
-use agent_runtime::types::{ErrorResponse, EventListResponse, Run, RunCreateRequest, RunCreateResponse};
-use futures_util::StreamExt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use agent_runtime::types::{
+    CheckpointDecisionRequest, CheckpointInputRequest, ErrorResponse, Event, EventListQuery,
+    EventListResponse, HumanCheckpoint, Run, RunCreateRequest, RunCreateResponse, RunListQuery,
+    RunListResponse, WsClientMessage, WsServerMessage,
+};
+use futures_util::{SinkExt, Stream, StreamExt, TryStreamExt};
 use reqwest::StatusCode;
+use tokio::net::TcpStream;
 use tokio::time::{timeout, Duration};
+use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ClientError {
@@ -15,6 +25,10 @@ pub enum ClientError {
     Timeout,
     #[error("event stream ended before completion")]
     StreamEnded,
+    #[error("websocket error: {0}")]
+    WebSocket(#[from] tungstenite::Error),
+    #[error("invalid websocket message: {0}")]
+    InvalidMessage(#[from] serde_json::Error),
 }
 
 #[derive(Clone)]
@@ -86,7 +100,78 @@ impl Client {
         self.handle_response(response, StatusCode::OK).await
     }
 
+    /// Fetches one page of runs; pass the returned `next_cursor` back in
+    /// `query.cursor` to get the next page.
+    pub async fn list_runs(&self, query: &RunListQuery) -> Result<RunListResponse, ClientError> {
+        let url = format!("{}/v1/runs", self.base_url.trim_end_matches('/'));
+        let response = self
+            .http
+            .get(url)
+            .headers(self.default_headers.clone())
+            .query(query)
+            .send()
+            .await?;
+        self.handle_response(response, StatusCode::OK).await
+    }
+
+    pub async fn cancel_run(&self, run_id: &str) -> Result<Run, ClientError> {
+        let url = format!(
+            "{}/v1/runs/{}/cancel",
+            self.base_url.trim_end_matches('/'),
+            run_id
+        );
+        let response = self
+            .http
+            .post(url)
+            .headers(self.default_headers.clone())
+            .send()
+            .await?;
+        self.handle_response(response, StatusCode::ACCEPTED).await
+    }
+
+    pub async fn approve_checkpoint(
+        &self,
+        run_id: &str,
+        checkpoint_id: &str,
+        request: CheckpointDecisionRequest,
+    ) -> Result<HumanCheckpoint, ClientError> {
+        self.post_checkpoint(run_id, checkpoint_id, "approve", &request)
+            .await
+    }
+
+    pub async fn reject_checkpoint(
+        &self,
+        run_id: &str,
+        checkpoint_id: &str,
+        request: CheckpointDecisionRequest,
+    ) -> Result<HumanCheckpoint, ClientError> {
+        self.post_checkpoint(run_id, checkpoint_id, "reject", &request)
+            .await
+    }
+
+    pub async fn provide_checkpoint_input(
+        &self,
+        run_id: &str,
+        checkpoint_id: &str,
+        request: CheckpointInputRequest,
+    ) -> Result<HumanCheckpoint, ClientError> {
+        self.post_checkpoint(run_id, checkpoint_id, "provide_input", &request)
+            .await
+    }
+
+    /// Fetches the first page of a run's events.
     pub async fn list_events(&self, run_id: &str) -> Result<EventListResponse, ClientError> {
+        self.list_events_page(run_id, &EventListQuery::default())
+            .await
+    }
+
+    /// Fetches one page of a run's events; pass the returned `next_cursor`
+    /// back in `query.cursor` to get the next page.
+    pub async fn list_events_page(
+        &self,
+        run_id: &str,
+        query: &EventListQuery,
+    ) -> Result<EventListResponse, ClientError> {
         let url = format!(
             "{}/v1/runs/{}/events",
             self.base_url.trim_end_matches('/'),
@@ -96,11 +181,42 @@ impl Client {
             .http
             .get(url)
             .headers(self.default_headers.clone())
+            .query(query)
             .send()
             .await?;
         self.handle_response(response, StatusCode::OK).await
     }
 
+    /// Yields every page of a run's events matching `query`, starting at
+    /// `query.cursor`.
+    pub fn list_events_pages<'a>(
+        &'a self,
+        run_id: &'a str,
+        query: EventListQuery,
+    ) -> impl Stream<Item = Result<EventListResponse, ClientError>> + 'a {
+        futures_util::stream::try_unfold(Some(query), move |query| async move {
+            let Some(mut query) = query else {
+                return Ok(None);
+            };
+            let page = self.list_events_page(run_id, &query).await?;
+            query.cursor = page.next_cursor.clone();
+            let next = query.cursor.is_some().then_some(query);
+            Ok(Some((page, next)))
+        })
+    }
+
+    /// Collects all of a run's events matching `query` across pages.
+    pub async fn list_all_events(
+        &self,
+        run_id: &str,
+        query: EventListQuery,
+    ) -> Result<Vec<Event>, ClientError> {
+        self.list_events_pages(run_id, query)
+            .map_ok(|page| page.data)
+            .try_concat()
+            .await
+    }
+
     pub async fn wait_for_completion(
         &self,
         run_id: &str,
@@ -120,10 +236,11 @@ impl Client {
             .await?;
         let status = response.status();
         if status != StatusCode::OK {
-            if let Ok(api_error) = response.json::<ApiErrorEnvelope>().await {
-                return Err(ClientError::Api(api_error.error));
-            }
-            return Err(ClientError::UnexpectedStatus(status));
+            let body = response.bytes().await?;
+            return Err(match api_error(&body) {
+                Some(api_error) => ClientError::Api(api_error),
+                None => ClientError::UnexpectedStatus(status),
+            });
         }
 
         let mut stream = response.bytes_stream();
@@ -137,10 +254,10 @@ impl Client {
                 while let Some(pos) = buffer.find("\n\n") {
                     let event_block = buffer[..pos].to_string();
                     buffer = buffer[pos + 2..].to_string();
-                    if let Some(event_type) = sse_event_type(&event_block) {
-                        if matches!(event_type, "run.completed" | "run.failed") {
-                            return Ok(());
-                        }
+                    if let Some(event_type) = sse_event_type(&event_block)
+                        && matches!(event_type, "run.completed" | "run.failed" | "run.canceled")
+                    {
+                        return Ok(());
                     }
                 }
             }
@@ -154,6 +271,48 @@ impl Client {
         }
     }
 
+    /// Opens `GET /v1/runs/{run_id}/ws`, which follows one run from the start,
+    /// or after the `after` event id, and closes once the run has ended.
+    pub async fn run_event_socket(
+        &self,
+        run_id: &str,
+        after: Option<&str>,
+    ) -> Result<EventSocket, ClientError> {
+        let mut path = format!("/v1/runs/{}/ws", run_id);
+        if let Some(after) = after {
+            path.push_str(&format!("?after={}", after));
+        }
+        self.connect_socket(&path).await
+    }
+
+    /// Opens `GET /v1/ws`, which follows the runs passed to
+    /// [`EventSocket::subscribe`].
+    pub async fn event_socket(&self) -> Result<EventSocket, ClientError> {
+        self.connect_socket("/v1/ws").await
+    }
+
+    async fn connect_socket(&self, path: &str) -> Result<EventSocket, ClientError> {
+        let base_url = self.base_url.trim_end_matches('/');
+        let base_url = match base_url.split_once("://") {
+            Some(("https", rest)) => format!("wss://{}", rest),
+            Some(("http", rest)) => format!("ws://{}", rest),
+            _ => base_url.to_string(),
+        };
+        let mut request = format!("{}{}", base_url, path).into_client_request()?;
+        request.headers_mut().extend(self.default_headers.clone());
+        match tokio_tungstenite::connect_async(request).await {
+            Ok((socket, _)) => Ok(EventSocket { socket }),
+            Err(tungstenite::Error::Http(response)) => {
+                let api_error = response.body().as_deref().and_then(api_error);
+                match api_error {
+                    Some(api_error) => Err(ClientError::Api(api_error)),
+                    None => Err(ClientError::UnexpectedStatus(response.status())),
+                }
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
     async fn handle_response<T: serde::de::DeserializeOwned>(
         &self,
         response: reqwest::Response,
@@ -163,10 +322,11 @@ impl Client {
         if status == expected {
             return Ok(response.json::<T>().await?);
         }
-        if let Ok(api_error) = response.json::<ApiErrorEnvelope>().await {
-            return Err(ClientError::Api(api_error.error));
+        let body = response.bytes().await?;
+        match api_error(&body) {
+            Some(api_error) => Err(ClientError::Api(api_error)),
+            None => Err(ClientError::UnexpectedStatus(status)),
         }
-        Err(ClientError::UnexpectedStatus(status))
     }
 
     async fn create_run_inner(
@@ -186,6 +346,115 @@ impl Client {
         let response = req.send().await?;
         self.handle_response(response, StatusCode::CREATED).await
     }
+
+    async fn post_checkpoint<B: serde::Serialize>(
+        &self,
+        run_id: &str,
+        checkpoint_id: &str,
+        action: &str,
+        body: &B,
+    ) -> Result<HumanCheckpoint, ClientError> {
+        let url = format!(
+            "{}/v1/runs/{}/human/{}/{}",
+            self.base_url.trim_end_matches('/'),
+            run_id,
+            checkpoint_id,
+            action
+        );
+        let response = self
+            .http
+            .post(url)
+            .headers(self.default_headers.clone())
+            .json(body)
+            .send()
+            .await?;
+        self.handle_response(response, StatusCode::OK).await
+    }
+}
+
+/// A WebSocket carrying run events, opened with [`Client::event_socket`] or
+/// [`Client::run_event_socket`]. As a stream it yields every message the
+/// server sends and ends when the socket closes.
+pub struct EventSocket {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl EventSocket {
+    /// Follows `run_id` from the start, or after the `after` event id. The
+    /// server answers with `subscribed` or an `error` naming the run.
+    pub async fn subscribe(
+        &mut self,
+        run_id: &str,
+        after: Option<&str>,
+    ) -> Result<(), ClientError> {
+        self.send(WsClientMessage::Subscribe {
+            run_id: run_id.to_string(),
+            after: after.map(str::to_string),
+        })
+        .await
+    }
+
+    pub async fn unsubscribe(&mut self, run_id: &str) -> Result<(), ClientError> {
+        self.send(WsClientMessage::Unsubscribe {
+            run_id: run_id.to_string(),
+        })
+        .await
+    }
+
+    /// Asks for a `pong`; WebSocket-level pings are answered automatically.
+    pub async fn ping(&mut self) -> Result<(), ClientError> {
+        self.send(WsClientMessage::Ping).await
+    }
+
+    pub async fn close(mut self) -> Result<(), ClientError> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+
+    /// Only the events, ending when the socket closes. An `error` message
+    /// becomes [`ClientError::Api`].
+    pub fn events(self) -> impl Stream<Item = Result<Event, ClientError>> {
+        self.filter_map(|message| async move {
+            match message {
+                Ok(WsServerMessage::Event { event }) => Some(Ok(event)),
+                Ok(WsServerMessage::Error { error, .. }) => Some(Err(ClientError::Api(error))),
+                Ok(_) => None,
+                Err(err) => Some(Err(err)),
+            }
+        })
+    }
+
+    async fn send(&mut self, message: WsClientMessage) -> Result<(), ClientError> {
+        let text = serde_json::to_string(&message)?;
+        self.socket.send(Message::Text(text)).await?;
+        Ok(())
+    }
+}
+
+impl Stream for EventSocket {
+    type Item = Result<WsServerMessage, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let message = match self.socket.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(message))) => message,
+                Poll::Ready(Some(Err(tungstenite::Error::ConnectionClosed))) => {
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(Some(Err(err))) => return Poll::Ready(Some(Err(err.into()))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            match message {
+                Message::Text(text) => {
+                    let parsed = serde_json::from_str(&text).map_err(ClientError::from);
+                    return Poll::Ready(Some(parsed));
+                }
+                Message::Close(_) => return Poll::Ready(None),
+                _ => continue,
+            }
+        }
+    }
 }
 
 fn sse_event_type(event_block: &str) -> Option<&str> {
@@ -197,7 +466,17 @@ fn sse_event_type(event_block: &str) -> Option<&str> {
     None
 }
 
+/// Error body of servers from before errors were sent flat.
 #[derive(Debug, serde::Deserialize)]
 struct ApiErrorEnvelope {
     error: ErrorResponse,
 }
+
+/// Parses an error body, flat or wrapped in the legacy `{ "error": ... }`.
+fn api_error(body: &[u8]) -> Option<ErrorResponse> {
+    if let Ok(error) = serde_json::from_slice::<ErrorResponse>(body) {
+        return Some(error);
+    }
+    let envelope: ApiErrorEnvelope = serde_json::from_slice(body).ok()?;
+    Some(envelope.error)
+}
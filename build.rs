@@ -0,0 +1,12 @@
+fn main() {
+    // Use the vendored protoc so building does not need one on the PATH.
+    if std::env::var_os("PROTOC").is_none() {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc");
+        // SAFETY: build scripts are single-threaded.
+        unsafe { std::env::set_var("PROTOC", protoc) };
+    }
+    tonic_build::configure()
+        .build_client(true)
+        .compile_protos(&["proto/agent_runtime.proto"], &["proto"])
+        .expect("compile proto/agent_runtime.proto");
+}
@@ -1,4 +1,3 @@
-pub mod server;
 pub mod llm;
 pub mod tools;
 pub mod workflows;
@@ -52,6 +52,13 @@ pub struct LlmMessage {
     pub content: String,
 }
 
+/// A parsed JSON reply plus the provider's `usage` object, when it sent one.
+#[derive(Debug, Clone)]
+pub struct LlmReply {
+    pub json: Value,
+    pub usage: Option<Value>,
+}
+
 impl LlmClient {
     pub fn new(config: LlmConfig) -> Self {
         Self {
@@ -60,7 +67,11 @@ impl LlmClient {
         }
     }
 
-    pub async fn chat_json(&self, messages: &[LlmMessage]) -> Result<Value, String> {
+    pub fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    pub async fn chat_json(&self, messages: &[LlmMessage]) -> Result<LlmReply, String> {
         if self.config.api_key.is_empty() {
             return Err("LLM_API_KEY is required when LLM_ENABLED=1".to_string());
         }
@@ -71,7 +82,7 @@ impl LlmClient {
         }
     }
 
-    async fn call_openai_chat_json(&self, messages: &[LlmMessage]) -> Result<Value, String> {
+    async fn call_openai_chat_json(&self, messages: &[LlmMessage]) -> Result<LlmReply, String> {
         let url = format!("{}/chat/completions", self.config.base_url.trim_end_matches('/'));
         let body = json!({
             "model": self.config.model,
@@ -107,10 +118,13 @@ impl LlmClient {
             .ok_or_else(|| "missing content in LLM response".to_string())?;
         let json_str = extract_json_content(content);
         let parsed = serde_json::from_str::<Value>(&json_str).map_err(|err| err.to_string())?;
-        Ok(parsed)
+        Ok(LlmReply {
+            json: parsed,
+            usage: value.get("usage").cloned(),
+        })
     }
 
-    async fn call_claude_chat_json(&self, messages: &[LlmMessage]) -> Result<Value, String> {
+    async fn call_claude_chat_json(&self, messages: &[LlmMessage]) -> Result<LlmReply, String> {
         let url = format!("{}/v1/messages", self.config.base_url.trim_end_matches('/'));
         let (system, claude_messages) = split_system_messages(messages);
         let body = json!({
@@ -148,7 +162,10 @@ impl LlmClient {
             .ok_or_else(|| "missing content in Claude response".to_string())?;
         let json_str = extract_json_content(content);
         let parsed = serde_json::from_str::<Value>(&json_str).map_err(|err| err.to_string())?;
-        Ok(parsed)
+        Ok(LlmReply {
+            json: parsed,
+            usage: value.get("usage").cloned(),
+        })
     }
 }
 
@@ -1,7 +1,15 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use agent_runtime::runtime::InMemoryRuntime;
+use agent_runtime::auth::AuthConfig;
+use agent_runtime::runtime::{
+    ConcurrencyLimits, InMemoryRuntime, RetentionPolicy, RetryPolicy, TenantQuota,
+    WorkflowOptions,
+};
+use agent_runtime::grpc;
+use agent_runtime::server::{shutdown_signal, RouterBuilder};
+use agent_runtime::store::SqliteRunStore;
 use std::path::Path;
 
 use serde_json::Value;
@@ -12,18 +20,33 @@ use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, Env
 
 use loreal_agent_app::tools::ToolManager;
 use loreal_agent_app::workflows::{
-    load_latest_active_spec_path, MeetingPrebriefDaily1_1Runner, WorkflowSpec,
+    load_spec_paths, MeetingPrebriefDaily1_1Runner, WorkflowSpec,
 };
 
 #[tokio::main]
 async fn main() {
     dotenvy::dotenv().ok();
     let _guards = init_tracing();
-    let runtime = Arc::new(InMemoryRuntime::new());
-    let workflow_spec_path = load_latest_active_spec_path().expect("discover active workflow spec");
-    let workflow_spec = WorkflowSpec::load(&workflow_spec_path).expect("valid workflow spec");
-    let input_schema = read_json_schema(&workflow_spec.input_schema_path());
-    let output_schema = read_json_schema(&workflow_spec.output_schema_path());
+    let runtime = match std::env::var("RUN_STORE_PATH") {
+        Ok(path) if !path.trim().is_empty() => {
+            let store = SqliteRunStore::open(&path).expect("open run store");
+            InMemoryRuntime::with_store(Arc::new(store))
+        }
+        _ => InMemoryRuntime::new(),
+    };
+    let runtime = match std::env::var("EVENT_BUFFER") {
+        Ok(capacity) => {
+            let capacity = capacity.trim().parse().expect("valid EVENT_BUFFER");
+            runtime.with_event_buffer(capacity)
+        }
+        Err(_) => runtime,
+    };
+    let runtime = Arc::new(
+        runtime
+            .with_retention(retention_from_env())
+            .with_concurrency_limits(concurrency_from_env()),
+    );
+    runtime.spawn_retention_sweeper();
     let mysql = match std::env::var("DATABASE_URL") {
         Ok(url) if !url.trim().is_empty() => match MySqlPool::connect(&url).await {
             Ok(pool) => Some(pool),
@@ -35,21 +58,126 @@ async fn main() {
         _ => None,
     };
     let tools = std::sync::Arc::new(ToolManager::new(mysql));
-    let workflow =
-        MeetingPrebriefDaily1_1Runner::from_spec(&workflow_spec, tools).expect("load workflow");
+    let spec_paths = load_spec_paths().expect("discover workflow specs");
+    for spec_path in spec_paths {
+        let workflow_spec = WorkflowSpec::load(&spec_path).expect("valid workflow spec");
+        let input_schema = read_json_schema(&workflow_spec.input_schema_path());
+        let output_schema = read_json_schema(&workflow_spec.output_schema_path());
+        let workflow = MeetingPrebriefDaily1_1Runner::from_spec(&workflow_spec, tools.clone())
+            .expect("load workflow");
+        info!(
+            version = %workflow_spec.version,
+            active = workflow_spec.is_active(),
+            "registering workflow version"
+        );
+        runtime
+            .register_workflow_with_options(
+                Arc::new(workflow),
+                Some(input_schema),
+                Some(output_schema),
+                WorkflowOptions {
+                    // MySQL assembly plus five LLM summaries; anything slower is hung.
+                    timeout: Some(Duration::from_secs(300)),
+                    retry: RetryPolicy::exponential(3),
+                    active: workflow_spec.is_active(),
+                    // Only reads store data and rewrites its own report, so a run
+                    // cut off by a crash can start over.
+                    idempotent: true,
+                    ..WorkflowOptions::default()
+                },
+            )
+            .await;
+    }
 
-    runtime
-        .register_workflow_with_schemas(Arc::new(workflow), Some(input_schema), Some(output_schema))
-        .await;
+    let recovery = runtime.recover_runs().await.expect("recover runs");
+    info!(
+        requeued = recovery.requeued.len(),
+        restarted = recovery.restarted.len(),
+        failed = recovery.failed.len(),
+        "recovered unfinished runs"
+    );
 
-    let app = loreal_agent_app::server::router(runtime);
+    let auth = match std::env::var("API_KEYS_FILE") {
+        Ok(path) if !path.trim().is_empty() => {
+            let auth = AuthConfig::load(path.trim()).expect("load API keys");
+            info!(keys = auth.keys.len(), "API key authentication enabled");
+            Some(auth)
+        }
+        _ if env_flag("ALLOW_UNAUTHENTICATED") => {
+            warn!("API_KEYS_FILE not set; serving without authentication");
+            None
+        }
+        _ => panic!("set API_KEYS_FILE, or ALLOW_UNAUTHENTICATED=true for local development"),
+    };
+    let app = RouterBuilder::new(runtime.clone());
+    let app = match auth {
+        Some(auth) => app.auth(auth),
+        None => app,
+    };
+    let grpc = std::env::var("GRPC_ADDR")
+        .ok()
+        .filter(|addr| !addr.trim().is_empty())
+        .map(|addr| {
+            let addr: SocketAddr = addr.trim().parse().expect("valid GRPC_ADDR");
+            let service = app.grpc_service();
+            info!(%addr, "loreal agent app gRPC listening");
+            tokio::spawn(grpc::serve(addr, service, shutdown_signal()))
+        });
+    let app = app.build();
     let addr: SocketAddr = "127.0.0.1:9010".parse().expect("valid addr");
     info!(%addr, "loreal agent app listening");
 
     let listener = tokio::net::TcpListener::bind(addr)
         .await
         .expect("bind");
-    axum::serve(listener, app).await.expect("serve");
+    let drain = Duration::from_secs(env_number("SHUTDOWN_DRAIN_SECS").unwrap_or(30));
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            info!(?drain, "shutting down; draining runs");
+            let report = runtime.shutdown(drain).await;
+            info!(
+                requeued = report.requeued.len(),
+                failed = report.failed.len(),
+                "shutdown complete"
+            );
+        })
+        .await
+        .expect("serve");
+    if let Some(grpc) = grpc {
+        grpc.await.expect("gRPC task").expect("serve gRPC");
+    }
+}
+
+fn env_flag(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|value| matches!(value.trim(), "1" | "true"))
+}
+
+fn env_number(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().parse().unwrap_or_else(|_| panic!("valid {name}")))
+}
+
+fn retention_from_env() -> RetentionPolicy {
+    RetentionPolicy {
+        max_events_per_run: env_number("MAX_EVENTS_PER_RUN").map(|max| max as usize),
+        run_ttl: env_number("RUN_TTL_SECS").map(Duration::from_secs),
+        artifact_ttl: env_number("ARTIFACT_TTL_SECS").map(Duration::from_secs),
+        ..RetentionPolicy::default()
+    }
+}
+
+fn concurrency_from_env() -> ConcurrencyLimits {
+    ConcurrencyLimits {
+        max_running: env_number("MAX_CONCURRENT_RUNS").map(|max| max as usize),
+        max_queued: env_number("MAX_QUEUED_RUNS").map(|max| max as usize),
+        per_tenant: TenantQuota {
+            max_running: env_number("TENANT_MAX_CONCURRENT_RUNS").map(|max| max as usize),
+            max_queued: env_number("TENANT_MAX_QUEUED_RUNS").map(|max| max as usize),
+        },
+        ..ConcurrencyLimits::default()
+    }
 }
 
 fn read_json_schema(path: &Path) -> Value {
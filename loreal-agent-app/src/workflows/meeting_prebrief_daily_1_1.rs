@@ -1,7 +1,7 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use agent_runtime::runtime::{AgentError, WorkflowOutput, WorkflowRunner};
+use agent_runtime::runtime::{AgentError, RunContext, WorkflowOutput, WorkflowRunner};
 use jsonschema::JSONSchema;
 use serde::Deserialize;
 use serde_json::{json, Value};
@@ -144,6 +144,7 @@ pub struct MeetingPrebriefDaily1_1Runner {
     rules: WorkflowRules,
     tools: SharedTools,
     output_schema: JSONSchema,
+    reports_dir: Option<PathBuf>,
 }
 
 struct ExecutionPlan {
@@ -181,8 +182,15 @@ impl MeetingPrebriefDaily1_1Runner {
             rules,
             tools,
             output_schema,
+            reports_dir: None,
         })
     }
+
+    /// Writes reports under `dir` instead of `REPORTS_DIR` (default `reports`).
+    pub fn with_reports_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.reports_dir = Some(dir.into());
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -195,41 +203,87 @@ impl WorkflowRunner for MeetingPrebriefDaily1_1Runner {
         Some(Box::leak(self.version.clone().into_boxed_str()))
     }
 
-    async fn run(&self, input: Value) -> Result<WorkflowOutput, AgentError> {
-        info!(workflow = "meeting_prebrief_daily", stage = "start", "run started");
+    async fn run(&self, input: Value, ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
+        info!(
+            workflow = "meeting_prebrief_daily",
+            stage = "start",
+            run_id = ctx.run_id(),
+            "run started"
+        );
         let plan = build_execution_plan();
-        let input = normalize_input(input, &plan, &self.tools).await?;
-        validate_input_completeness(&input)?;
+        let input = ctx
+            .step("assemble_mysql", normalize_input(ctx, input, &plan, &self.tools))
+            .await?;
+        ctx.step("validate_input", async { validate_input_completeness(&input) })
+            .await?;
         let mut output = execute_workflow(&input, &self.rules, &self.thresholds);
-        if let Some(summary) = maybe_generate_llm_summary(&input, &output).await {
+        // `step` refuses to start once the run is canceled, so a canceled run
+        // stops spending model calls.
+        let summary = ctx
+            .step("llm_summary", async {
+                Ok(maybe_generate_llm_summary(ctx, &input, &output).await)
+            })
+            .await?;
+        if let Some(summary) = summary {
             output = attach_agent_summary(output, summary);
         }
-        if let Some(risk_summary) = maybe_generate_llm_risk_summary(&input, &output).await {
+        let risk_summary = ctx
+            .step("llm_risk_summary", async {
+                Ok(maybe_generate_llm_risk_summary(ctx, &input, &output).await)
+            })
+            .await?;
+        if let Some(risk_summary) = risk_summary {
             output = attach_agent_risk_summary(output, risk_summary);
         }
-        if let Some(staff_summary) = maybe_generate_llm_staff_summary(&input, &output).await {
+        let staff_summary = ctx
+            .step("llm_staff_summary", async {
+                Ok(maybe_generate_llm_staff_summary(ctx, &input, &output).await)
+            })
+            .await?;
+        if let Some(staff_summary) = staff_summary {
             output = attach_agent_staff_summary(output, staff_summary);
         }
-        if let Some(customer_summary) = maybe_generate_llm_customer_summary(&input, &output).await {
+        let customer_summary = ctx
+            .step("llm_customer_summary", async {
+                Ok(maybe_generate_llm_customer_summary(ctx, &input, &output).await)
+            })
+            .await?;
+        if let Some(customer_summary) = customer_summary {
             output = attach_agent_customer_summary(output, customer_summary);
         }
-        if let Some(key_items_summary) = maybe_generate_llm_key_items_summary(&input, &output).await {
+        let key_items_summary = ctx
+            .step("llm_key_items_summary", async {
+                Ok(maybe_generate_llm_key_items_summary(ctx, &input, &output).await)
+            })
+            .await?;
+        if let Some(key_items_summary) = key_items_summary {
             output = attach_agent_key_items_summary(output, key_items_summary);
         }
-        let report_md = render_report_md(&input, &output);
-        let output = attach_report_md(output, report_md);
-        validate_output_schema(&output, &self.output_schema)?;
-        let biz_date = output
-            .get("biz_date")
-            .and_then(|v| v.as_str())
-            .unwrap_or("1970-01-01");
-        let report_md = output
-            .get("report_md")
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
-        persist_report_md(report_md, biz_date)
-            .await
-            .map_err(AgentError::fatal)?;
+        let output = ctx
+            .step("render_report", async {
+                let report_md = render_report_md(&input, &output);
+                let output = attach_report_md(output, report_md);
+                validate_output_schema(&output, &self.output_schema)?;
+                Ok(output)
+            })
+            .await?;
+        ctx.step("persist_report", async {
+            let biz_date = output
+                .get("biz_date")
+                .and_then(|v| v.as_str())
+                .unwrap_or("1970-01-01");
+            let report_md = output
+                .get("report_md")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let report_dir = self.reports_dir.clone().unwrap_or_else(|| {
+                std::env::var("REPORTS_DIR").unwrap_or_else(|_| "reports".to_string()).into()
+            });
+            persist_report_md(&report_dir, report_md, biz_date)
+                .await
+                .map_err(AgentError::fatal)
+        })
+        .await?;
 
         Ok(WorkflowOutput {
             output,
@@ -238,6 +292,27 @@ impl WorkflowRunner for MeetingPrebriefDaily1_1Runner {
     }
 }
 
+/// `LlmClient::chat_json` wrapped in `llm.request`/`llm.response` events.
+async fn chat_json_traced(
+    ctx: &RunContext,
+    step_id: &str,
+    client: &LlmClient,
+    messages: &[LlmMessage],
+) -> Result<Value, String> {
+    ctx.llm_request(
+        step_id,
+        json!({ "model": client.model(), "messages": messages.len() }),
+    )
+    .await;
+    let result = client.chat_json(messages).await;
+    let payload = match &result {
+        Ok(reply) => json!({ "ok": true, "usage": reply.usage }),
+        Err(err) => json!({ "ok": false, "error": err }),
+    };
+    ctx.llm_response(step_id, payload).await;
+    result.map(|reply| reply.json)
+}
+
 fn build_execution_plan() -> ExecutionPlan {
     ExecutionPlan {
         use_mysql_assembly: true,
@@ -253,11 +328,7 @@ fn validate_input_completeness(input: &Value) -> Result<(), AgentError> {
     if input.get("biz_date").and_then(|v| v.as_str()).is_none() {
         missing.push("biz_date".to_string());
     }
-    let his = input.get("his").and_then(|v| v.as_object());
-    if his.is_none() {
-        missing.push("his".to_string());
-    } else {
-        let his = his.unwrap();
+    if let Some(his) = input.get("his").and_then(|v| v.as_object()) {
         for key in [
             "visits",
             "gmv",
@@ -270,6 +341,8 @@ fn validate_input_completeness(input: &Value) -> Result<(), AgentError> {
                 missing.push(format!("his.{}", key));
             }
         }
+    } else {
+        missing.push("his".to_string());
     }
 
     if missing.is_empty() {
@@ -312,6 +385,7 @@ fn validate_output_schema(output: &Value, schema: &JSONSchema) -> Result<(), Age
 }
 
 async fn normalize_input(
+    ctx: &RunContext,
     mut input: Value,
     plan: &ExecutionPlan,
     tools: &SharedTools,
@@ -323,20 +397,28 @@ async fn normalize_input(
                 "mysql not configured (DATABASE_URL missing or connection failed)",
             ));
         };
-        let assembled = assemble_meeting_prebrief_daily_1_1_mysql(pool, &input)
-            .await
-            .map_err(|err| match err {
-                MysqlAssembleError::InvalidInput(message) => AgentError::fatal(message),
-                MysqlAssembleError::Db(message) => AgentError::retryable(message),
-            })?;
+        let tool_name = "mysql.assemble_meeting_prebrief_daily_1_1";
+        ctx.tool_called(
+            "assemble_mysql",
+            tool_name,
+            json!({ "store_id": input.get("store_id"), "biz_date": input.get("biz_date") }),
+        )
+        .await;
+        let assembled = assemble_meeting_prebrief_daily_1_1_mysql(pool, &input).await;
+        let payload = match &assembled {
+            Ok(_) => json!({ "ok": true }),
+            Err(err) => json!({ "ok": false, "error": err.to_string() }),
+        };
+        ctx.tool_result("assemble_mysql", tool_name, payload).await;
+        let assembled = assembled.map_err(|err| match err {
+            MysqlAssembleError::InvalidInput(message) => AgentError::fatal(message),
+            MysqlAssembleError::Db(message) => AgentError::retryable(message),
+        })?;
         let mut merged = assembled;
         merge_json(&mut merged, &input);
         input = merged;
         info!(stage = "assemble_mysql", "mysql assembly completed");
     }
-    if let Value::Object(map) = &mut input {
-        map.remove("__context");
-    }
     Ok(input)
 }
 
@@ -388,10 +470,12 @@ fn execute_workflow(input: &Value, rules: &WorkflowRules, thresholds: &Threshold
     })
 }
 
-async fn maybe_generate_llm_summary(input: &Value, output: &Value) -> Option<Vec<String>> {
-    let Some(config) = LlmConfig::from_env() else {
-        return None;
-    };
+async fn maybe_generate_llm_summary(
+    ctx: &RunContext,
+    input: &Value,
+    output: &Value,
+) -> Option<Vec<String>> {
+    let config = LlmConfig::from_env()?;
     let client = LlmClient::new(config);
     let payload = json!({
         "facts_recap": output.get("facts_recap"),
@@ -422,7 +506,7 @@ async fn maybe_generate_llm_summary(input: &Value, output: &Value) -> Option<Vec
             content: prompt,
         },
     ];
-    let response = client.chat_json(&messages).await.ok()?;
+    let response = chat_json_traced(ctx, "llm_summary", &client, &messages).await.ok()?;
     let summary = response.get("summary").and_then(|v| v.as_array())?;
     let mut items = Vec::new();
     for item in summary.iter().take(6) {
@@ -440,10 +524,12 @@ async fn maybe_generate_llm_summary(input: &Value, output: &Value) -> Option<Vec
     }
 }
 
-async fn maybe_generate_llm_risk_summary(input: &Value, output: &Value) -> Option<Vec<String>> {
-    let Some(config) = LlmConfig::from_env() else {
-        return None;
-    };
+async fn maybe_generate_llm_risk_summary(
+    ctx: &RunContext,
+    input: &Value,
+    output: &Value,
+) -> Option<Vec<String>> {
+    let config = LlmConfig::from_env()?;
     let client = LlmClient::new(config);
     let payload = json!({
         "facts_recap": output.get("facts_recap"),
@@ -474,7 +560,7 @@ async fn maybe_generate_llm_risk_summary(input: &Value, output: &Value) -> Optio
             content: prompt,
         },
     ];
-    let response = match client.chat_json(&messages).await {
+    let response = match chat_json_traced(ctx, "llm_risk_summary", &client, &messages).await {
         Ok(value) => value,
         Err(err) => {
             warn!(stage = "llm_risk_summary", error = %err, "llm risk summary failed");
@@ -508,10 +594,12 @@ async fn maybe_generate_llm_risk_summary(input: &Value, output: &Value) -> Optio
     }
 }
 
-async fn maybe_generate_llm_staff_summary(input: &Value, output: &Value) -> Option<Vec<String>> {
-    let Some(config) = LlmConfig::from_env() else {
-        return None;
-    };
+async fn maybe_generate_llm_staff_summary(
+    ctx: &RunContext,
+    input: &Value,
+    output: &Value,
+) -> Option<Vec<String>> {
+    let config = LlmConfig::from_env()?;
     let client = LlmClient::new(config);
     let payload = json!({
         "staff_stats": output.get("facts_recap").and_then(|v| v.get("staff_stats")),
@@ -539,7 +627,7 @@ async fn maybe_generate_llm_staff_summary(input: &Value, output: &Value) -> Opti
             content: prompt,
         },
     ];
-    let response = match client.chat_json(&messages).await {
+    let response = match chat_json_traced(ctx, "llm_staff_summary", &client, &messages).await {
         Ok(value) => value,
         Err(err) => {
             warn!(stage = "llm_staff_summary", error = %err, "llm staff summary failed");
@@ -573,10 +661,12 @@ async fn maybe_generate_llm_staff_summary(input: &Value, output: &Value) -> Opti
     }
 }
 
-async fn maybe_generate_llm_customer_summary(input: &Value, output: &Value) -> Option<Vec<String>> {
-    let Some(config) = LlmConfig::from_env() else {
-        return None;
-    };
+async fn maybe_generate_llm_customer_summary(
+    ctx: &RunContext,
+    input: &Value,
+    output: &Value,
+) -> Option<Vec<String>> {
+    let config = LlmConfig::from_env()?;
     let client = LlmClient::new(config);
     let payload = json!({
         "customer_summary": output.get("facts_recap").and_then(|v| v.get("customer_summary")),
@@ -604,7 +694,7 @@ async fn maybe_generate_llm_customer_summary(input: &Value, output: &Value) -> O
             content: prompt,
         },
     ];
-    let response = match client.chat_json(&messages).await {
+    let response = match chat_json_traced(ctx, "llm_customer_summary", &client, &messages).await {
         Ok(value) => value,
         Err(err) => {
             warn!(stage = "llm_customer_summary", error = %err, "llm customer summary failed");
@@ -638,10 +728,12 @@ async fn maybe_generate_llm_customer_summary(input: &Value, output: &Value) -> O
     }
 }
 
-async fn maybe_generate_llm_key_items_summary(input: &Value, output: &Value) -> Option<Vec<String>> {
-    let Some(config) = LlmConfig::from_env() else {
-        return None;
-    };
+async fn maybe_generate_llm_key_items_summary(
+    ctx: &RunContext,
+    input: &Value,
+    output: &Value,
+) -> Option<Vec<String>> {
+    let config = LlmConfig::from_env()?;
     let client = LlmClient::new(config);
     let payload = json!({
         "key_items_mtd": output.get("facts_recap").and_then(|v| v.get("key_items_mtd")),
@@ -669,7 +761,7 @@ async fn maybe_generate_llm_key_items_summary(input: &Value, output: &Value) ->
             content: prompt,
         },
     ];
-    let response = match client.chat_json(&messages).await {
+    let response = match chat_json_traced(ctx, "llm_key_items_summary", &client, &messages).await {
         Ok(value) => value,
         Err(err) => {
             warn!(stage = "llm_key_items_summary", error = %err, "llm key items summary failed");
@@ -861,15 +953,18 @@ fn attach_agent_key_items_summary(mut output: Value, summary: Vec<String>) -> Va
     output
 }
 
-async fn persist_report_md(report_md: &str, biz_date: &str) -> Result<(), String> {
+async fn persist_report_md(
+    report_dir: &Path,
+    report_md: &str,
+    biz_date: &str,
+) -> Result<(), String> {
     let file_suffix = chrono::NaiveDate::parse_from_str(biz_date, "%Y-%m-%d")
         .map(|d| d.format("%Y%m%d").to_string())
         .unwrap_or_else(|_| biz_date.replace('-', ""));
-    let report_dir = std::env::var("REPORTS_DIR").unwrap_or_else(|_| "reports".to_string());
-    tokio::fs::create_dir_all(&report_dir)
+    tokio::fs::create_dir_all(report_dir)
         .await
         .map_err(|err| format!("create reports dir failed: {}", err))?;
-    let report_path = format!("{}/briefing_{}.md", report_dir, file_suffix);
+    let report_path = report_dir.join(format!("briefing_{}.md", file_suffix));
     tokio::fs::write(&report_path, report_md.as_bytes())
         .await
         .map_err(|err| format!("write report failed: {}", err))?;
@@ -1031,23 +1126,23 @@ fn build_checklist(
         }
     }
 
-    if appointments_count > 0 {
-        if let Some(template) = find_tomorrow_template(templates) {
-            let replacements = checklist_replacements(biz_date, None, appointments_count);
-            let action = render_template(&template.action_template, &replacements);
-            let due = render_template(&template.due_template, &replacements);
-            let evidence_ref = template
-                .evidence_ref
-                .clone()
-                .unwrap_or_else(|| vec!["tomorrow_list:appointments".to_string()]);
-            checklist.push(json!({
-                "item_id": format!("item_{}", Uuid::new_v4()),
-                "owner_role": template.owner_role,
-                "action": action,
-                "due": due,
-                "evidence_ref": evidence_ref
-            }));
-        }
+    if appointments_count > 0
+        && let Some(template) = find_tomorrow_template(templates)
+    {
+        let replacements = checklist_replacements(biz_date, None, appointments_count);
+        let action = render_template(&template.action_template, &replacements);
+        let due = render_template(&template.due_template, &replacements);
+        let evidence_ref = template
+            .evidence_ref
+            .clone()
+            .unwrap_or_else(|| vec!["tomorrow_list:appointments".to_string()]);
+        checklist.push(json!({
+            "item_id": format!("item_{}", Uuid::new_v4()),
+            "owner_role": template.owner_role,
+            "action": action,
+            "due": due,
+            "evidence_ref": evidence_ref
+        }));
     }
 
     while checklist.len() < MIN_CHECKLIST_ITEMS {
@@ -1098,11 +1193,11 @@ fn find_risk_template<'a>(
     })
 }
 
-fn find_tomorrow_template<'a>(templates: &'a [ChecklistTemplate]) -> Option<&'a ChecklistTemplate> {
+fn find_tomorrow_template(templates: &[ChecklistTemplate]) -> Option<&ChecklistTemplate> {
     templates.iter().find(|template| template.when_tomorrow_list.unwrap_or(false))
 }
 
-fn find_fallback_template<'a>(templates: &'a [ChecklistTemplate]) -> Option<&'a ChecklistTemplate> {
+fn find_fallback_template(templates: &[ChecklistTemplate]) -> Option<&ChecklistTemplate> {
     templates.iter().find(|template| template.fallback.unwrap_or(false))
 }
 
@@ -1278,20 +1373,6 @@ fn format_pct_delta(value: f64) -> String {
     }
 }
 
-fn risk_label(risk_type: &str) -> std::borrow::Cow<'static, str> {
-    match risk_type {
-        "gmv_drop" => std::borrow::Cow::Borrowed("开单下滑"),
-        "consumption_drop" => std::borrow::Cow::Borrowed("消耗下滑"),
-        "visits_drop" => std::borrow::Cow::Borrowed("到店下滑"),
-        "avg_ticket_drop" => std::borrow::Cow::Borrowed("客单下滑"),
-        "gmv_target_gap" => std::borrow::Cow::Borrowed("开单进度落后"),
-        "consumption_target_gap" => std::borrow::Cow::Borrowed("消耗进度落后"),
-        "touch_gap" => std::borrow::Cow::Borrowed("触达未回积压"),
-        "tomorrow_load" => std::borrow::Cow::Borrowed("明日承接压力"),
-        _ => std::borrow::Cow::Owned(risk_type.to_string()),
-    }
-}
-
 fn render_report_md(input: &Value, output: &Value) -> String {
     let biz_date = output
         .get("biz_date")
@@ -1667,7 +1748,13 @@ fn render_report_md(input: &Value, output: &Value) -> String {
     lines.join("\n")
 }
 
+const WORKFLOW_ROOT: &str = "loreal-agent-app/workflows/1-1_meeting_prebrief_daily";
+
 pub fn load_latest_active_spec_path() -> Result<std::path::PathBuf, String> {
-    let workflow_root = Path::new("loreal-agent-app/workflows/1-1_meeting_prebrief_daily");
-    super::spec::discover_latest_active_version(workflow_root)
+    super::spec::discover_latest_active_version(Path::new(WORKFLOW_ROOT))
+}
+
+/// Every versioned spec of the workflow, oldest first.
+pub fn load_spec_paths() -> Result<Vec<std::path::PathBuf>, String> {
+    super::spec::discover_versions(Path::new(WORKFLOW_ROOT))
 }
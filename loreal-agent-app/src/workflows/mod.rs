@@ -1,5 +1,7 @@
 mod meeting_prebrief_daily_1_1;
 mod spec;
 
-pub use meeting_prebrief_daily_1_1::{load_latest_active_spec_path, MeetingPrebriefDaily1_1Runner};
-pub use spec::WorkflowSpec;
+pub use meeting_prebrief_daily_1_1::{
+    load_latest_active_spec_path, load_spec_paths, MeetingPrebriefDaily1_1Runner,
+};
+pub use spec::{discover_latest_active_version, WorkflowSpec};
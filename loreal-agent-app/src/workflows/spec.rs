@@ -78,10 +78,19 @@ impl SemVer {
     }
 }
 
-pub fn discover_latest_active_version(workflow_root: &Path) -> Result<PathBuf, String> {
+impl WorkflowSpec {
+    pub fn is_active(&self) -> bool {
+        self.status
+            .as_deref()
+            .is_some_and(|status| status.eq_ignore_ascii_case("active"))
+    }
+}
+
+/// Spec paths of every `v<semver>/workflow.yml` under `workflow_root`, oldest first.
+pub fn discover_versions(workflow_root: &Path) -> Result<Vec<PathBuf>, String> {
     let dir = std::fs::read_dir(workflow_root)
         .map_err(|err| format!("read workflow root failed: {}", err))?;
-    let mut best: Option<(SemVer, PathBuf)> = None;
+    let mut versions: Vec<(SemVer, PathBuf)> = Vec::new();
     for entry in dir {
         let entry = entry.map_err(|err| format!("read workflow root entry failed: {}", err))?;
         let path = entry.path();
@@ -97,21 +106,20 @@ pub fn discover_latest_active_version(workflow_root: &Path) -> Result<PathBuf, S
             continue;
         };
         let spec_path = path.join("workflow.yml");
-        if !spec_path.exists() {
-            continue;
-        }
-        let spec = WorkflowSpec::load(&spec_path)?;
-        if spec
-            .status
-            .as_deref()
-            .is_some_and(|status| status.eq_ignore_ascii_case("active"))
-        {
-            if best.as_ref().map(|(v, _)| version > *v).unwrap_or(true) {
-                best = Some((version, spec_path));
-            }
+        if spec_path.exists() {
+            versions.push((version, spec_path));
         }
     }
-    best.map(|(_, path)| path)
-        .ok_or_else(|| format!("no active workflow.yml found under {}", workflow_root.display()))
+    versions.sort();
+    Ok(versions.into_iter().map(|(_, path)| path).collect())
 }
 
+pub fn discover_latest_active_version(workflow_root: &Path) -> Result<PathBuf, String> {
+    let mut best = None;
+    for spec_path in discover_versions(workflow_root)? {
+        if WorkflowSpec::load(&spec_path)?.is_active() {
+            best = Some(spec_path);
+        }
+    }
+    best.ok_or_else(|| format!("no active workflow.yml found under {}", workflow_root.display()))
+}
@@ -1,22 +1,30 @@
+use std::path::Path;
 use std::sync::Arc;
 
-use agent_runtime::runtime::WorkflowRunner;
+use agent_runtime::runtime::{RunContext, WorkflowRunner};
 use serde_json::json;
+use sqlx::MySqlPool;
 use uuid::Uuid;
 
 use loreal_agent_app::tools::ToolManager;
-use loreal_agent_app::workflows::{load_latest_active_spec_path, MeetingPrebriefDaily1_1Runner, WorkflowSpec};
+use loreal_agent_app::workflows::{discover_latest_active_version, MeetingPrebriefDaily1_1Runner, WorkflowSpec};
+
+const WORKFLOW_ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/workflows/1-1_meeting_prebrief_daily");
 
 #[tokio::test]
+#[ignore = "requires DATABASE_URL"]
 async fn persists_report_md_to_reports_dir() {
     let tmp_dir = format!("target/tmp/reports_test_{}", Uuid::new_v4());
     std::fs::create_dir_all(&tmp_dir).expect("create tmp reports dir");
-    std::env::set_var("REPORTS_DIR", &tmp_dir);
 
-    let spec_path = load_latest_active_spec_path().expect("discover active spec");
+    let spec_path = discover_latest_active_version(Path::new(WORKFLOW_ROOT)).expect("discover active spec");
     let spec = WorkflowSpec::load(&spec_path).expect("load spec");
-    let tools = Arc::new(ToolManager::new(None));
-    let runner = MeetingPrebriefDaily1_1Runner::from_spec(&spec, tools).expect("runner");
+    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL");
+    let pool = MySqlPool::connect(&database_url).await.expect("connect mysql");
+    let tools = Arc::new(ToolManager::new(Some(pool)));
+    let runner = MeetingPrebriefDaily1_1Runner::from_spec(&spec, tools)
+        .expect("runner")
+        .with_reports_dir(&tmp_dir);
 
     let input = json!({
         "store_id": "test_store",
@@ -27,7 +35,10 @@ async fn persists_report_md_to_reports_dir() {
         "mtd": { "gmv": 986000, "consumption": 865000, "time_progress": 0.58, "gmv_target": 2200000, "consumption_target": 2000000 }
     });
 
-    runner.run(input).await.expect("run workflow");
+    runner
+        .run(input, &RunContext::detached())
+        .await
+        .expect("run workflow");
 
     let expected = format!("{}/briefing_20251230.md", tmp_dir);
     let content = std::fs::read_to_string(&expected).expect("report exists");
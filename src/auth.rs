@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::types::{ErrorResponse, RunCreateRequest, RunListQuery};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Scope {
+    #[serde(rename = "runs:create")]
+    RunsCreate,
+    /// Runs, their events and their artifacts.
+    #[serde(rename = "runs:read")]
+    RunsRead,
+    #[serde(rename = "runs:cancel")]
+    RunsCancel,
+    #[serde(rename = "workflows:read")]
+    WorkflowsRead,
+    /// Approve, reject or provide input to human checkpoints.
+    #[serde(rename = "hitl:decide")]
+    HitlDecide,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RunsCreate => "runs:create",
+            Self::RunsRead => "runs:read",
+            Self::RunsCancel => "runs:cancel",
+            Self::WorkflowsRead => "workflows:read",
+            Self::HitlDecide => "hitl:decide",
+        }
+    }
+}
+
+/// One configured API key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    /// Recorded as `created_by` on the runs the key creates.
+    pub id: String,
+    /// Lowercase hex SHA-256 of the bearer token.
+    pub token_sha256: String,
+    pub scopes: Vec<Scope>,
+    /// Workflows the key may see and run. `None` allows every workflow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workflows: Option<Vec<String>>,
+    /// Tenant the key acts for. Its runs are created under this tenant and it
+    /// can only see that tenant's runs and artifacts. `None` is an operator
+    /// key that sees every tenant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+}
+
+impl ApiKey {
+    /// A key for `token`, which is hashed right away.
+    pub fn new(id: impl Into<String>, token: &str, scopes: impl Into<Vec<Scope>>) -> Self {
+        Self {
+            id: id.into(),
+            token_sha256: token_digest(token),
+            scopes: scopes.into(),
+            workflows: None,
+            tenant_id: None,
+        }
+    }
+
+    pub fn with_workflows<I, S>(mut self, workflows: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.workflows = Some(workflows.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant_id: impl Into<String>) -> Self {
+        self.tenant_id = Some(tenant_id.into());
+        self
+    }
+}
+
+/// The authenticated caller, available to handlers as a request extension.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub key_id: String,
+    pub scopes: Vec<Scope>,
+    pub workflows: Option<Vec<String>>,
+    pub tenant_id: Option<String>,
+}
+
+impl Principal {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    pub fn allows_workflow(&self, name: &str) -> bool {
+        self.workflows
+            .as_ref()
+            .is_none_or(|workflows| workflows.iter().any(|workflow| workflow == name))
+    }
+
+    /// 403 unless the key may use workflow `name`.
+    pub fn check_workflow(&self, name: &str) -> Result<(), ErrorResponse> {
+        if self.allows_workflow(name) {
+            return Ok(());
+        }
+        Err(ErrorResponse {
+            code: "forbidden".to_string(),
+            message: format!("API key {} may not use workflow {}", self.key_id, name),
+            retryable: false,
+            details: Some(json!({ "workflow": name })),
+        })
+    }
+
+    /// Whether the caller may see records of `tenant_id`. Tenant keys only
+    /// see their own tenant; operator keys see everything.
+    pub fn allows_tenant(&self, tenant_id: Option<&str>) -> bool {
+        self.tenant_id
+            .as_deref()
+            .is_none_or(|own| tenant_id == Some(own))
+    }
+
+    /// 403 when a tenant key names another tenant.
+    pub fn check_tenant(&self, tenant_id: Option<&str>) -> Result<(), ErrorResponse> {
+        match tenant_id {
+            Some(tenant_id) if !self.allows_tenant(Some(tenant_id)) => Err(ErrorResponse {
+                code: "forbidden".to_string(),
+                message: format!("API key {} may not act for tenant {}", self.key_id, tenant_id),
+                retryable: false,
+                details: Some(json!({ "tenant_id": tenant_id })),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks a create request against the key's workflows and tenant, and
+    /// puts a tenant key's runs under its tenant.
+    pub fn scope_create_request(&self, req: &mut RunCreateRequest) -> Result<(), ErrorResponse> {
+        self.check_workflow(&req.workflow.name)?;
+        if let Some(tenant_id) = &self.tenant_id {
+            let metadata = req.metadata.get_or_insert_with(Default::default);
+            self.check_tenant(metadata.get("tenant_id").and_then(Value::as_str))?;
+            metadata.insert("tenant_id".to_string(), tenant_id.clone().into());
+        }
+        Ok(())
+    }
+
+    /// Limits a tenant key's run listing to its own tenant, and a restricted
+    /// key's listing to its workflows.
+    pub fn scope_run_query(&self, query: &mut RunListQuery) -> Result<(), ErrorResponse> {
+        if self.tenant_id.is_some() {
+            self.check_tenant(query.tenant_id.as_deref())?;
+            query.tenant_id = self.tenant_id.clone();
+        }
+        if let Some(workflows) = &self.workflows {
+            match &query.workflow_name {
+                Some(name) => self.check_workflow(name)?,
+                None => query.workflow_names = Some(workflows.clone()),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// API keys accepted as bearer tokens. Keys are configured by the SHA-256 of
+/// their token, so the config file holds nothing a caller could replay.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthConfig {
+    pub keys: Vec<ApiKey>,
+}
+
+impl AuthConfig {
+    /// Reads `{"keys": [ApiKey, ...]}` from a JSON file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("read {} failed: {}", path.display(), err))?;
+        serde_json::from_str(&text)
+            .map_err(|err| format!("parse {} failed: {}", path.display(), err))
+    }
+}
+
+/// Configured keys indexed by token digest.
+#[derive(Debug, Clone)]
+pub struct Authenticator {
+    keys: Arc<HashMap<String, ApiKey>>,
+    // Prefix the routes are mounted under; scopes are keyed by the path below it.
+    prefix: String,
+}
+
+impl Authenticator {
+    pub fn new(config: AuthConfig) -> Self {
+        let keys = config
+            .keys
+            .into_iter()
+            .map(|key| (key.token_sha256.to_ascii_lowercase(), key))
+            .collect();
+        Self {
+            keys: Arc::new(keys),
+            prefix: String::new(),
+        }
+    }
+
+    /// For routes nested under `prefix`, e.g. by [`crate::server::RouterBuilder::prefix`].
+    pub fn mounted_at(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    pub fn authenticate(&self, token: &str) -> Option<Principal> {
+        let key = self.keys.get(&token_digest(token))?;
+        Some(Principal {
+            key_id: key.id.clone(),
+            scopes: key.scopes.clone(),
+            workflows: key.workflows.clone(),
+            tenant_id: key.tenant_id.clone(),
+        })
+    }
+}
+
+fn token_digest(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Scope needed for a route, keyed by method and route pattern. Routes not
+/// listed only need a valid key.
+fn required_scope(method: &Method, path: &str) -> Option<Scope> {
+    let scope = match (method.as_str(), path) {
+        ("POST", "/v1/runs") => Scope::RunsCreate,
+        ("DELETE", "/v1/runs/:run_id") | ("POST", "/v1/runs/:run_id/cancel") => Scope::RunsCancel,
+        (_, path) if path.starts_with("/v1/runs/:run_id/human/") => Scope::HitlDecide,
+        (_, path)
+            if path.starts_with("/v1/runs")
+                || path.starts_with("/v1/artifacts")
+                || path == "/v1/ws" =>
+        {
+            Scope::RunsRead
+        }
+        (_, path) if path.starts_with("/v1/workflows") => Scope::WorkflowsRead,
+        _ => return None,
+    };
+    Some(scope)
+}
+
+/// Middleware for `Router::route_layer`: rejects requests without a valid
+/// bearer token (401) or without the route's scope (403), and stores the
+/// [`Principal`] in the request extensions.
+pub async fn require_api_key(
+    State(authenticator): State<Authenticator>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    let Some(principal) = token.and_then(|token| authenticator.authenticate(token)) else {
+        let message = if token.is_some() {
+            "invalid API key"
+        } else {
+            "missing bearer token"
+        };
+        let error = ErrorResponse {
+            code: "unauthorized".to_string(),
+            message: message.to_string(),
+            retryable: false,
+            details: None,
+        };
+        let mut response = (StatusCode::UNAUTHORIZED, Json(error)).into_response();
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        return response;
+    };
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let path = path.strip_prefix(authenticator.prefix.as_str()).unwrap_or(&path);
+    if let Some(scope) = required_scope(request.method(), path)
+        && !principal.has_scope(scope)
+    {
+        let error = ErrorResponse {
+            code: "forbidden".to_string(),
+            message: format!("API key {} lacks scope {}", principal.key_id, scope.as_str()),
+            retryable: false,
+            details: Some(json!({ "required_scope": scope })),
+        };
+        return (StatusCode::FORBIDDEN, Json(error)).into_response();
+    }
+    request.extensions_mut().insert(principal);
+    next.run(request).await
+}
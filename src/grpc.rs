@@ -0,0 +1,820 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use prost::Message;
+use serde_json::{Map, Number, Value};
+use tonic::metadata::{MetadataMap, MetadataValue};
+use tonic::transport::Server;
+use tonic::{Code, Request, Response, Status};
+
+use crate::auth::{AuthConfig, Authenticator, Principal, Scope};
+use crate::runtime::InMemoryRuntime;
+use crate::server::CreateRunHook;
+use crate::types::{
+    Artifact, ArtifactFile, ArtifactRef, ArtifactType, CheckpointDecision, Cost, ErrorResponse,
+    Event, EventListQuery, EventType, HumanCheckpoint, HumanCheckpointStatus, Run,
+    RunCreateRequest, RunListQuery, RunStatus, SchemaBundle, Timing, Workflow, WorkflowRef,
+    WorkflowSummary, WorkflowVersion,
+};
+
+/// Messages and service stubs generated from `proto/agent_runtime.proto`.
+pub mod proto {
+    tonic::include_proto!("agent.runtime.v1");
+}
+
+use proto::agent_runtime_server::{AgentRuntime, AgentRuntimeServer};
+
+/// Implements the `AgentRuntime` gRPC service on top of an [`InMemoryRuntime`],
+/// with the same semantics as the HTTP routes. Failed calls carry the
+/// encoded `Error` message in the status details.
+#[derive(Clone)]
+pub struct GrpcService {
+    runtime: Arc<InMemoryRuntime>,
+    authenticator: Option<Authenticator>,
+    create_run_hooks: Arc<Vec<CreateRunHook>>,
+}
+
+/// Service without authentication, for tests and trusted networks.
+pub fn grpc_service(runtime: Arc<InMemoryRuntime>) -> AgentRuntimeServer<GrpcService> {
+    AgentRuntimeServer::new(GrpcService {
+        runtime,
+        authenticator: None,
+        create_run_hooks: Arc::default(),
+    })
+}
+
+/// Service where every call needs a bearer token from `auth` in the
+/// `authorization` metadata, with the scope the matching HTTP route needs.
+pub fn grpc_service_with_auth(
+    runtime: Arc<InMemoryRuntime>,
+    auth: AuthConfig,
+) -> AgentRuntimeServer<GrpcService> {
+    AgentRuntimeServer::new(GrpcService {
+        runtime,
+        authenticator: Some(Authenticator::new(auth)),
+        create_run_hooks: Arc::default(),
+    })
+}
+
+/// Service built by [`crate::server::RouterBuilder::grpc_service`], sharing
+/// the router's keys and create-run hooks.
+pub(crate) fn grpc_service_with_hooks(
+    runtime: Arc<InMemoryRuntime>,
+    auth: Option<AuthConfig>,
+    create_run_hooks: Arc<Vec<CreateRunHook>>,
+) -> AgentRuntimeServer<GrpcService> {
+    AgentRuntimeServer::new(GrpcService {
+        runtime,
+        authenticator: auth.map(Authenticator::new),
+        create_run_hooks,
+    })
+}
+
+/// Serves `service` on `addr` until `signal` resolves, then waits for open
+/// calls to finish.
+pub async fn serve(
+    addr: SocketAddr,
+    service: AgentRuntimeServer<GrpcService>,
+    signal: impl Future<Output = ()>,
+) -> Result<(), tonic::transport::Error> {
+    Server::builder()
+        .add_service(service)
+        .serve_with_shutdown(addr, signal)
+        .await
+}
+
+impl GrpcService {
+    /// The caller's principal, or `None` when the service is unauthenticated.
+    fn authorize(&self, metadata: &MetadataMap, scope: Scope) -> Result<Option<Principal>, Status> {
+        let Some(authenticator) = &self.authenticator else {
+            return Ok(None);
+        };
+        let token = metadata
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+        let Some(principal) = token.and_then(|token| authenticator.authenticate(token)) else {
+            let message = if token.is_some() {
+                "invalid API key"
+            } else {
+                "missing bearer token"
+            };
+            return Err(status(error("unauthorized", message.to_string(), None)));
+        };
+        if !principal.has_scope(scope) {
+            let message = format!("API key {} lacks scope {}", principal.key_id, scope.as_str());
+            let details = serde_json::json!({ "required_scope": scope });
+            return Err(status(error("forbidden", message, Some(details))));
+        }
+        Ok(Some(principal))
+    }
+
+    /// Loads a run the caller may see; runs of other tenants are not found and
+    /// runs of workflows outside the key's list are forbidden.
+    async fn visible_run(
+        &self,
+        principal: &Option<Principal>,
+        run_id: &str,
+    ) -> Result<Run, Status> {
+        match self.runtime.get_run(run_id).await {
+            Some(run) if allows_tenant(principal, run.tenant_id.as_deref()) => {
+                if let Some(principal) = principal {
+                    principal.check_workflow(&run.workflow.name).map_err(status)?;
+                }
+                Ok(run)
+            }
+            _ => Err(status(error("not_found", "run not found".to_string(), None))),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl AgentRuntime for GrpcService {
+    type StreamEventsStream = BoxStream<'static, Result<proto::Event, Status>>;
+
+    async fn create_run(
+        &self,
+        request: Request<proto::CreateRunRequest>,
+    ) -> Result<Response<proto::RunResponse>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::RunsCreate)?;
+        let request = request.into_inner();
+        let workflow = request
+            .workflow
+            .ok_or_else(|| status(error("invalid_request", "workflow is required".into(), None)))?;
+        let mut req = RunCreateRequest {
+            workflow: WorkflowRef {
+                name: workflow.name,
+                version: non_empty(workflow.version),
+            },
+            input: request.input.map(Value::from).unwrap_or(Value::Null),
+            context: request.context.map(Value::from),
+            metadata: (!request.metadata.is_empty()).then(|| json_map(request.metadata)),
+            labels: (!request.labels.is_empty()).then_some(request.labels),
+        };
+        for hook in self.create_run_hooks.iter() {
+            hook(&mut req, principal.as_ref()).map_err(status)?;
+        }
+        if let Some(principal) = &principal {
+            principal.scope_create_request(&mut req).map_err(status)?;
+        }
+        let created_by = principal.as_ref().map(|principal| principal.key_id.as_str());
+        let (run, replayed) = match non_empty(request.idempotency_key) {
+            Some(key) => {
+                self.runtime
+                    .create_run_with_idempotency_as(&key, req, created_by)
+                    .await
+            }
+            None => self
+                .runtime
+                .create_run_as(req, created_by)
+                .await
+                .map(|run| (run, false)),
+        }
+        .map_err(status)?;
+        let mut response = Response::new(proto::RunResponse {
+            run: Some(run.into()),
+        });
+        if replayed {
+            response
+                .metadata_mut()
+                .insert("idempotent-replayed", MetadataValue::from_static("true"));
+        }
+        Ok(response)
+    }
+
+    async fn get_run(
+        &self,
+        request: Request<proto::GetRunRequest>,
+    ) -> Result<Response<proto::RunResponse>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::RunsRead)?;
+        let run = self.visible_run(&principal, &request.get_ref().run_id).await?;
+        Ok(Response::new(proto::RunResponse {
+            run: Some(run.into()),
+        }))
+    }
+
+    async fn list_runs(
+        &self,
+        request: Request<proto::ListRunsRequest>,
+    ) -> Result<Response<proto::ListRunsResponse>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::RunsRead)?;
+        let request = request.into_inner();
+        let status_filter = match request.status() {
+            proto::RunStatus::Unspecified => None,
+            other => Some(RunStatus::from(other)),
+        };
+        let mut query = RunListQuery {
+            limit: page_size(request.limit),
+            cursor: non_empty(request.cursor),
+            status: status_filter,
+            workflow_name: non_empty(request.workflow_name),
+            label_selector: non_empty(request.label_selector),
+            created_after: parse_query_time("created_after", request.created_after)?,
+            created_before: parse_query_time("created_before", request.created_before)?,
+            tenant_id: non_empty(request.tenant_id),
+            workflow_names: None,
+        };
+        if let Some(principal) = &principal {
+            principal.scope_run_query(&mut query).map_err(status)?;
+        }
+        let page = self.runtime.list_runs(query).await.map_err(status)?;
+        Ok(Response::new(proto::ListRunsResponse {
+            data: page.data.into_iter().map(Into::into).collect(),
+            next_cursor: page.next_cursor.unwrap_or_default(),
+        }))
+    }
+
+    async fn cancel_run(
+        &self,
+        request: Request<proto::CancelRunRequest>,
+    ) -> Result<Response<proto::RunResponse>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::RunsCancel)?;
+        let run_id = &request.get_ref().run_id;
+        self.visible_run(&principal, run_id).await?;
+        let run = self.runtime.cancel_run(run_id).await.map_err(status)?;
+        Ok(Response::new(proto::RunResponse {
+            run: Some(run.into()),
+        }))
+    }
+
+    async fn list_events(
+        &self,
+        request: Request<proto::ListEventsRequest>,
+    ) -> Result<Response<proto::ListEventsResponse>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::RunsRead)?;
+        let request = request.into_inner();
+        self.visible_run(&principal, &request.run_id).await?;
+        let query = EventListQuery {
+            limit: page_size(request.limit),
+            cursor: non_empty(request.cursor),
+            types: (!request.types.is_empty()).then(|| request.types.join(",")),
+            step_id: non_empty(request.step_id),
+            after: non_empty(request.after),
+        };
+        let page = self
+            .runtime
+            .list_events_page(&request.run_id, query)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(proto::ListEventsResponse {
+            data: page.data.into_iter().map(Into::into).collect(),
+            next_cursor: page.next_cursor.unwrap_or_default(),
+        }))
+    }
+
+    async fn stream_events(
+        &self,
+        request: Request<proto::StreamEventsRequest>,
+    ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::RunsRead)?;
+        let request = request.into_inner();
+        self.visible_run(&principal, &request.run_id).await?;
+        let after = non_empty(request.after);
+        let events = self
+            .runtime
+            .event_stream(&request.run_id, after.as_deref())
+            .await
+            .ok_or_else(|| status(error("not_found", "run not found".to_string(), None)))?;
+        Ok(Response::new(
+            events.map(|event| Ok(proto::Event::from(event))).boxed(),
+        ))
+    }
+
+    async fn approve_checkpoint(
+        &self,
+        request: Request<proto::CheckpointDecisionRequest>,
+    ) -> Result<Response<proto::HumanCheckpoint>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::HitlDecide)?;
+        let request = request.into_inner();
+        self.visible_run(&principal, &request.run_id).await?;
+        let checkpoint = self
+            .runtime
+            .approve_checkpoint(&request.run_id, &request.checkpoint_id, non_empty(request.note))
+            .await
+            .map_err(status)?;
+        Ok(Response::new(checkpoint.into()))
+    }
+
+    async fn reject_checkpoint(
+        &self,
+        request: Request<proto::CheckpointDecisionRequest>,
+    ) -> Result<Response<proto::HumanCheckpoint>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::HitlDecide)?;
+        let request = request.into_inner();
+        self.visible_run(&principal, &request.run_id).await?;
+        let checkpoint = self
+            .runtime
+            .reject_checkpoint(&request.run_id, &request.checkpoint_id, non_empty(request.note))
+            .await
+            .map_err(status)?;
+        Ok(Response::new(checkpoint.into()))
+    }
+
+    async fn provide_checkpoint_input(
+        &self,
+        request: Request<proto::CheckpointInputRequest>,
+    ) -> Result<Response<proto::HumanCheckpoint>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::HitlDecide)?;
+        let request = request.into_inner();
+        self.visible_run(&principal, &request.run_id).await?;
+        let input = request.input.map(Value::from).unwrap_or(Value::Null);
+        let checkpoint = self
+            .runtime
+            .provide_checkpoint_input(
+                &request.run_id,
+                &request.checkpoint_id,
+                input,
+                non_empty(request.note),
+            )
+            .await
+            .map_err(status)?;
+        Ok(Response::new(checkpoint.into()))
+    }
+
+    async fn list_workflows(
+        &self,
+        request: Request<proto::ListWorkflowsRequest>,
+    ) -> Result<Response<proto::ListWorkflowsResponse>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::WorkflowsRead)?;
+        let tag = non_empty(request.into_inner().tag);
+        let tenant_id = principal.as_ref().and_then(|principal| principal.tenant_id.as_deref());
+        let mut data = self.runtime.list_workflows_for_tenant(tenant_id).await;
+        if let Some(principal) = &principal {
+            data.retain(|workflow| principal.allows_workflow(&workflow.name));
+        }
+        if let Some(tag) = tag {
+            data.retain(|workflow| workflow.tags.contains(&tag));
+        }
+        Ok(Response::new(proto::ListWorkflowsResponse {
+            data: data.into_iter().map(Into::into).collect(),
+            next_cursor: String::new(),
+        }))
+    }
+
+    async fn get_workflow(
+        &self,
+        request: Request<proto::GetWorkflowRequest>,
+    ) -> Result<Response<proto::Workflow>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::WorkflowsRead)?;
+        let request = request.into_inner();
+        if let Some(principal) = &principal {
+            principal.check_workflow(&request.name).map_err(status)?;
+        }
+        let tenant_id = principal.and_then(|principal| principal.tenant_id);
+        let version = non_empty(request.version);
+        self.runtime
+            .get_workflow_for_tenant(&request.name, version.as_deref(), tenant_id.as_deref())
+            .await
+            .map(|workflow| Response::new(workflow.into()))
+            .ok_or_else(|| status(error("not_found", "workflow not found".to_string(), None)))
+    }
+
+    async fn get_workflow_schemas(
+        &self,
+        request: Request<proto::GetWorkflowSchemasRequest>,
+    ) -> Result<Response<proto::SchemaBundle>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::WorkflowsRead)?;
+        let request = request.into_inner();
+        if let Some(principal) = &principal {
+            principal.check_workflow(&request.name).map_err(status)?;
+        }
+        let tenant_id = principal.and_then(|principal| principal.tenant_id);
+        let version = non_empty(request.version);
+        self.runtime
+            .get_workflow_schemas_for_tenant(
+                &request.name,
+                version.as_deref(),
+                tenant_id.as_deref(),
+            )
+            .await
+            .map(|bundle| Response::new(bundle.into()))
+            .ok_or_else(|| status(error("not_found", "workflow not found".to_string(), None)))
+    }
+
+    async fn get_artifact(
+        &self,
+        request: Request<proto::GetArtifactRequest>,
+    ) -> Result<Response<proto::Artifact>, Status> {
+        let principal = self.authorize(request.metadata(), Scope::RunsRead)?;
+        let not_found = || status(error("not_found", "artifact not found".to_string(), None));
+        let artifact = match self.runtime.get_artifact(&request.get_ref().artifact_id).await {
+            Some(artifact) if allows_tenant(&principal, artifact.tenant_id.as_deref()) => artifact,
+            _ => return Err(not_found()),
+        };
+        if principal.as_ref().is_some_and(|principal| principal.workflows.is_some()) {
+            // The allow-list applies to the workflow of the run that made it.
+            let run_id = artifact.run_id.as_deref().ok_or_else(not_found)?;
+            self.visible_run(&principal, run_id).await?;
+        }
+        Ok(Response::new(artifact.into()))
+    }
+}
+
+fn allows_tenant(principal: &Option<Principal>, tenant_id: Option<&str>) -> bool {
+    principal
+        .as_ref()
+        .is_none_or(|principal| principal.allows_tenant(tenant_id))
+}
+
+fn error(code: &str, message: String, details: Option<Value>) -> ErrorResponse {
+    ErrorResponse {
+        code: code.to_string(),
+        message,
+        retryable: false,
+        details,
+    }
+}
+
+/// Maps a runtime error onto the gRPC code matching its HTTP status, with the
+/// full error encoded as a `proto::Error` in the details.
+pub fn status(err: ErrorResponse) -> Status {
+    let code = match err.code.as_str() {
+        "unauthorized" => Code::Unauthenticated,
+        "forbidden" => Code::PermissionDenied,
+        "not_found" => Code::NotFound,
+        "run_not_cancelable"
+        | "idempotency_conflict"
+        | "checkpoint_already_resolved"
+        | "checkpoint_not_pending" => Code::FailedPrecondition,
+        "queue_full" | "tenant_quota_exceeded" => Code::ResourceExhausted,
+        "shutting_down" => Code::Unavailable,
+        "storage_error" => Code::Internal,
+        _ => Code::InvalidArgument,
+    };
+    let message = err.message.clone();
+    let details = proto::Error::from(err).encode_to_vec();
+    Status::with_details(code, message, details.into())
+}
+
+/// Decodes the `proto::Error` a failed call carries, if any.
+pub fn error_details(status: &Status) -> Option<ErrorResponse> {
+    proto::Error::decode(status.details()).ok().map(Into::into)
+}
+
+fn non_empty(text: String) -> Option<String> {
+    (!text.is_empty()).then_some(text)
+}
+
+fn page_size(limit: i32) -> Option<usize> {
+    (limit > 0).then_some(limit as usize)
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn optional_timestamp(time: Option<DateTime<Utc>>) -> String {
+    time.map(timestamp).unwrap_or_default()
+}
+
+fn parse_query_time(field: &str, text: String) -> Result<Option<DateTime<Utc>>, Status> {
+    let Some(text) = non_empty(text) else {
+        return Ok(None);
+    };
+    DateTime::parse_from_rfc3339(&text)
+        .map(|time| Some(time.with_timezone(&Utc)))
+        .map_err(|err| {
+            let message = format!("{} is not an RFC 3339 timestamp: {}", field, err);
+            status(error("invalid_query", message, None))
+        })
+}
+
+fn json_map(fields: HashMap<String, proto::JsonValue>) -> Map<String, Value> {
+    fields.into_iter().map(|(key, value)| (key, value.into())).collect()
+}
+
+fn proto_map(fields: Map<String, Value>) -> HashMap<String, proto::JsonValue> {
+    fields.into_iter().map(|(key, value)| (key, value.into())).collect()
+}
+
+impl From<Value> for proto::JsonValue {
+    fn from(value: Value) -> Self {
+        use proto::json_value::Kind;
+        let kind = match value {
+            Value::Null => Kind::NullValue(proto::JsonNull {}),
+            Value::Bool(value) => Kind::BoolValue(value),
+            Value::Number(number) => match number.as_i64() {
+                Some(value) => Kind::IntValue(value),
+                None => Kind::DoubleValue(number.as_f64().unwrap_or_default()),
+            },
+            Value::String(value) => Kind::StringValue(value),
+            Value::Array(values) => Kind::ListValue(proto::JsonList {
+                values: values.into_iter().map(Into::into).collect(),
+            }),
+            Value::Object(fields) => Kind::StructValue(proto::JsonStruct {
+                fields: proto_map(fields),
+            }),
+        };
+        Self { kind: Some(kind) }
+    }
+}
+
+impl From<proto::JsonValue> for Value {
+    fn from(value: proto::JsonValue) -> Self {
+        use proto::json_value::Kind;
+        match value.kind {
+            None | Some(Kind::NullValue(_)) => Value::Null,
+            Some(Kind::BoolValue(value)) => Value::Bool(value),
+            Some(Kind::IntValue(value)) => Value::Number(value.into()),
+            // JSON has no NaN or infinities.
+            Some(Kind::DoubleValue(value)) => {
+                Number::from_f64(value).map_or(Value::Null, Value::Number)
+            }
+            Some(Kind::StringValue(value)) => Value::String(value),
+            Some(Kind::ListValue(list)) => {
+                Value::Array(list.values.into_iter().map(Into::into).collect())
+            }
+            Some(Kind::StructValue(fields)) => Value::Object(json_map(fields.fields)),
+        }
+    }
+}
+
+impl From<ErrorResponse> for proto::Error {
+    fn from(err: ErrorResponse) -> Self {
+        Self {
+            code: err.code,
+            message: err.message,
+            retryable: err.retryable,
+            details: err.details.map(Into::into),
+        }
+    }
+}
+
+impl From<proto::Error> for ErrorResponse {
+    fn from(err: proto::Error) -> Self {
+        Self {
+            code: err.code,
+            message: err.message,
+            retryable: err.retryable,
+            details: err.details.map(Into::into),
+        }
+    }
+}
+
+impl From<RunStatus> for proto::RunStatus {
+    fn from(status: RunStatus) -> Self {
+        match status {
+            RunStatus::Queued => Self::Queued,
+            RunStatus::Running => Self::Running,
+            RunStatus::WaitingHuman => Self::WaitingHuman,
+            RunStatus::Succeeded => Self::Succeeded,
+            RunStatus::Failed => Self::Failed,
+            RunStatus::Canceled => Self::Canceled,
+            RunStatus::TimedOut => Self::TimedOut,
+        }
+    }
+}
+
+impl From<proto::RunStatus> for RunStatus {
+    fn from(status: proto::RunStatus) -> Self {
+        match status {
+            proto::RunStatus::Unspecified | proto::RunStatus::Queued => Self::Queued,
+            proto::RunStatus::Running => Self::Running,
+            proto::RunStatus::WaitingHuman => Self::WaitingHuman,
+            proto::RunStatus::Succeeded => Self::Succeeded,
+            proto::RunStatus::Failed => Self::Failed,
+            proto::RunStatus::Canceled => Self::Canceled,
+            proto::RunStatus::TimedOut => Self::TimedOut,
+        }
+    }
+}
+
+impl From<EventType> for proto::EventType {
+    fn from(event_type: EventType) -> Self {
+        match event_type {
+            EventType::RunStarted => Self::RunStarted,
+            EventType::RunCompleted => Self::RunCompleted,
+            EventType::RunFailed => Self::RunFailed,
+            EventType::RunCanceled => Self::RunCanceled,
+            EventType::StepStarted => Self::StepStarted,
+            EventType::StepCompleted => Self::StepCompleted,
+            EventType::StepFailed => Self::StepFailed,
+            EventType::ToolCalled => Self::ToolCalled,
+            EventType::ToolResult => Self::ToolResult,
+            EventType::LlmRequest => Self::LlmRequest,
+            EventType::LlmResponse => Self::LlmResponse,
+            EventType::HitlRequired => Self::HitlRequired,
+            EventType::HitlResolved => Self::HitlResolved,
+            EventType::ArtifactCreated => Self::ArtifactCreated,
+            EventType::EventsTruncated => Self::EventsTruncated,
+            EventType::RuntimeShutdown => Self::RuntimeShutdown,
+            EventType::RunRecovered => Self::RunRecovered,
+        }
+    }
+}
+
+impl From<ArtifactType> for proto::ArtifactType {
+    fn from(artifact_type: ArtifactType) -> Self {
+        match artifact_type {
+            ArtifactType::Message => Self::Message,
+            ArtifactType::Record => Self::Record,
+            ArtifactType::File => Self::File,
+        }
+    }
+}
+
+impl From<HumanCheckpointStatus> for proto::HumanCheckpointStatus {
+    fn from(status: HumanCheckpointStatus) -> Self {
+        match status {
+            HumanCheckpointStatus::Required => Self::Required,
+            HumanCheckpointStatus::Approved => Self::Approved,
+            HumanCheckpointStatus::Rejected => Self::Rejected,
+            HumanCheckpointStatus::InputProvided => Self::InputProvided,
+            HumanCheckpointStatus::Resolved => Self::Resolved,
+            HumanCheckpointStatus::Expired => Self::Expired,
+            HumanCheckpointStatus::Canceled => Self::Canceled,
+        }
+    }
+}
+
+impl From<WorkflowRef> for proto::WorkflowRef {
+    fn from(workflow: WorkflowRef) -> Self {
+        Self {
+            name: workflow.name,
+            version: workflow.version.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<Timing> for proto::Timing {
+    fn from(timing: Timing) -> Self {
+        Self {
+            created_at: timestamp(timing.created_at),
+            started_at: optional_timestamp(timing.started_at),
+            finished_at: optional_timestamp(timing.finished_at),
+            wall_ms: timing.wall_ms.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<Cost> for proto::Cost {
+    fn from(cost: Cost) -> Self {
+        Self {
+            total_tokens: cost.total_tokens as i64,
+            prompt_tokens: cost.prompt_tokens as i64,
+            completion_tokens: cost.completion_tokens as i64,
+            total_usd: cost.total_usd,
+        }
+    }
+}
+
+impl From<Run> for proto::Run {
+    fn from(run: Run) -> Self {
+        Self {
+            run_id: run.run_id,
+            workflow: Some(run.workflow.into()),
+            status: proto::RunStatus::from(run.status).into(),
+            trace_id: run.trace_id.unwrap_or_default(),
+            tenant_id: run.tenant_id.unwrap_or_default(),
+            timing: Some(run.timing.into()),
+            input: run.input.map(Into::into),
+            context: run.context.map(Into::into),
+            output: run.output.map(Into::into),
+            error: run.error.map(Into::into),
+            cost: run.cost.map(Into::into),
+            artifacts: run.artifacts.into_iter().map(Into::into).collect(),
+            checkpoints: run.checkpoints.into_iter().map(Into::into).collect(),
+            metadata: run.metadata.map(proto_map).unwrap_or_default(),
+            labels: run.labels,
+            queue_position: run.queue_position.unwrap_or_default() as u32,
+            created_by: run.created_by.unwrap_or_default(),
+            attempts: run.attempts,
+        }
+    }
+}
+
+impl From<Event> for proto::Event {
+    fn from(event: Event) -> Self {
+        Self {
+            event_id: event.event_id,
+            ts: timestamp(event.ts),
+            r#type: proto::EventType::from(event.event_type).into(),
+            run_id: event.run_id,
+            step_id: event.step_id.unwrap_or_default(),
+            tool_name: event.tool_name.unwrap_or_default(),
+            payload: Some(event.payload.into()),
+            trace: None,
+            tenant_id: event.tenant_id.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<ArtifactRef> for proto::ArtifactRef {
+    fn from(artifact: ArtifactRef) -> Self {
+        Self {
+            artifact_id: artifact.artifact_id,
+            r#type: proto::ArtifactType::from(artifact.r#type).into(),
+            name: artifact.name.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<ArtifactFile> for proto::ArtifactFile {
+    fn from(file: ArtifactFile) -> Self {
+        Self {
+            download_url: file.download_url,
+            expires_at: optional_timestamp(file.expires_at),
+            size_bytes: file.size_bytes.unwrap_or_default(),
+            sha256: file.sha256.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<Artifact> for proto::Artifact {
+    fn from(artifact: Artifact) -> Self {
+        Self {
+            artifact_id: artifact.artifact_id,
+            r#type: proto::ArtifactType::from(artifact.r#type).into(),
+            name: artifact.name.unwrap_or_default(),
+            created_at: timestamp(artifact.created_at),
+            mime_type: artifact.mime_type.unwrap_or_default(),
+            data: artifact.data.map(Into::into),
+            file: artifact.file.map(Into::into),
+            tenant_id: artifact.tenant_id.unwrap_or_default(),
+            run_id: artifact.run_id.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<CheckpointDecision> for proto::CheckpointDecision {
+    fn from(decision: CheckpointDecision) -> Self {
+        Self {
+            approved: decision.approved,
+            note: decision.note.unwrap_or_default(),
+            decided_at: timestamp(decision.decided_at),
+        }
+    }
+}
+
+impl From<HumanCheckpoint> for proto::HumanCheckpoint {
+    fn from(checkpoint: HumanCheckpoint) -> Self {
+        Self {
+            checkpoint_id: checkpoint.checkpoint_id,
+            run_id: checkpoint.run_id,
+            status: proto::HumanCheckpointStatus::from(checkpoint.status).into(),
+            created_at: timestamp(checkpoint.created_at),
+            expires_at: optional_timestamp(checkpoint.expires_at),
+            title: checkpoint.title.unwrap_or_default(),
+            instructions: checkpoint.instructions.unwrap_or_default(),
+            required_schema: checkpoint.required_schema.map(Into::into),
+            provided_input: checkpoint.provided_input.map(Into::into),
+            decision: checkpoint.decision.map(Into::into),
+        }
+    }
+}
+
+impl From<WorkflowVersion> for proto::WorkflowVersion {
+    fn from(version: WorkflowVersion) -> Self {
+        Self {
+            version: version.version.unwrap_or_default(),
+            active: version.active,
+        }
+    }
+}
+
+impl From<Workflow> for proto::Workflow {
+    fn from(workflow: Workflow) -> Self {
+        Self {
+            name: workflow.name,
+            version: workflow.version.unwrap_or_default(),
+            description: workflow.description.unwrap_or_default(),
+            tags: workflow.tags,
+            default_timeout_ms: workflow.default_timeout_ms.unwrap_or_default(),
+            input_schema_ref: workflow.input_schema_ref.unwrap_or_default(),
+            output_schema_ref: workflow.output_schema_ref.unwrap_or_default(),
+            definition: None,
+            versions: workflow.versions.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<WorkflowSummary> for proto::WorkflowSummary {
+    fn from(workflow: WorkflowSummary) -> Self {
+        Self {
+            name: workflow.name,
+            version: workflow.version.unwrap_or_default(),
+            description: workflow.description.unwrap_or_default(),
+            tags: workflow.tags,
+        }
+    }
+}
+
+impl From<SchemaBundle> for proto::SchemaBundle {
+    fn from(bundle: SchemaBundle) -> Self {
+        Self {
+            workflow: Some(bundle.workflow.into()),
+            schema_hash: bundle.schema_hash,
+            schemas: bundle
+                .schemas
+                .into_iter()
+                .map(|(kind, schema)| (kind, schema.into()))
+                .collect(),
+        }
+    }
+}
@@ -1,3 +1,8 @@
+pub mod auth;
+// The generated service trait returns the large `tonic::Status` as its error.
+#[allow(clippy::result_large_err)]
+pub mod grpc;
 pub mod runtime;
 pub mod server;
+pub mod store;
 pub mod types;
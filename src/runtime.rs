@@ -1,17 +1,44 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Duration;
 
-use chrono::Utc;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
 use serde_json::{json, Value};
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, oneshot, Mutex, Notify, RwLock};
+pub use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
+use crate::store::{
+    EventQuery, IdempotencyRecord, InMemoryRunStore, RunCursor, RunQuery, RunStore, RunUpdate,
+    StoreError, WorkflowRegistration,
+};
 use crate::types::{
-    Artifact, ArtifactRef, ErrorResponse, Event, EventType, Run, RunCreateRequest, RunStatus,
-    SchemaBundle, Timing, Workflow, WorkflowRef, WorkflowSummary,
+    Artifact, ArtifactRef, CheckpointDecision, Cost, ErrorResponse, Event, EventListQuery,
+    EventListResponse, EventType, HumanCheckpoint, HumanCheckpointStatus, Run, RunCreateRequest,
+    RunListQuery, RunListResponse, RunStatus, SchemaBundle, Timing, Workflow, WorkflowRef,
+    WorkflowSummary, WorkflowVersion,
 };
+use jsonschema::JSONSchema;
+use semver::{Version, VersionReq};
 use sha2::Digest;
 
+mod context;
+mod queue;
+mod recovery;
+mod retention;
+mod shutdown;
+
+pub use context::{CheckpointRequest, RunContext};
+pub use queue::{ConcurrencyLimits, TenantQuota};
+pub use recovery::RecoveryReport;
+pub use retention::{RetentionMetrics, RetentionPolicy};
+pub use shutdown::ShutdownReport;
+
 #[derive(Debug, thiserror::Error)]
 pub enum AgentError {
     #[error("retryable: {message}")]
@@ -70,6 +97,8 @@ impl AgentError {
 #[derive(Debug, Clone)]
 pub struct WorkflowOutput {
     pub output: Value,
+    /// Registered when the run succeeds; use `RunContext::add_artifact` to
+    /// publish artifacts earlier.
     pub artifacts: Vec<Artifact>,
 }
 
@@ -79,38 +108,270 @@ pub trait WorkflowRunner: Send + Sync {
     fn version(&self) -> Option<&'static str> {
         None
     }
-    async fn run(&self, input: Value) -> Result<WorkflowOutput, AgentError>;
+    /// Executes one attempt of a run. `ctx` carries the run's identity and
+    /// context and is used to report progress on the run's event stream.
+    async fn run(&self, input: Value, ctx: &RunContext) -> Result<WorkflowOutput, AgentError>;
+}
+
+/// How a human resolved a checkpoint.
+#[derive(Debug, Clone)]
+enum CheckpointResolution {
+    Approve { note: Option<String> },
+    Reject { note: Option<String> },
+    ProvideInput { input: Value, note: Option<String> },
+}
+
+struct PendingCheckpoint {
+    run_id: String,
+    resolved: oneshot::Sender<HumanCheckpoint>,
+}
+
+const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_EVENT_BUFFER: usize = 100;
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 200;
+
+/// Per-workflow execution settings supplied at registration.
+#[derive(Debug, Clone)]
+pub struct WorkflowOptions {
+    /// Wall-clock limit for a single run. Callers can override it per run with
+    /// `metadata.timeout_ms`; runs without any limit may run forever.
+    pub timeout: Option<Duration>,
+    /// How often the runner is re-invoked after `AgentError::Retryable` failures.
+    pub retry: RetryPolicy,
+    /// Inactive versions only run when a caller pins their exact version, so a
+    /// canary can be registered next to the live version.
+    pub active: bool,
+    /// Reject runs whose input does not match the registered input schema.
+    /// Turn off for legacy callers that rely on the runner's own coercion.
+    pub validate_input: bool,
+    /// Runs of this workflow (across versions) executing at once; further
+    /// runs stay queued.
+    pub max_concurrency: Option<usize>,
+    /// Safe to run again from the start. Runs interrupted by a crash are then
+    /// restarted on recovery instead of failed.
+    pub idempotent: bool,
+    /// Tenants whose runs may use this version. Other tenants, and runs
+    /// without a tenant, do not see it, so a tenant can be given its own
+    /// variant of a shared workflow. `None` shares it with everyone.
+    pub tenants: Option<Vec<String>>,
+}
+
+impl Default for WorkflowOptions {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            retry: RetryPolicy::default(),
+            active: true,
+            validate_input: true,
+            max_concurrency: None,
+            idempotent: false,
+            tenants: None,
+        }
+    }
+}
+
+/// Exponential backoff with jitter. The default policy makes a single attempt.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn exponential(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the attempt following `attempt` (1-based). Uses "equal
+    /// jitter": half of the capped exponential delay is fixed, half is random.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let capped = base.min(self.max_backoff.as_secs_f64()).max(0.0);
+        let jitter = rand::random::<f64>() * capped / 2.0;
+        Duration::from_secs_f64(capped / 2.0 + jitter)
+    }
+}
+
+enum RunOutcome {
+    Finished(Result<WorkflowOutput, AgentError>),
+    Canceled,
+    TimedOut,
 }
 
 #[derive(Clone)]
 struct WorkflowEntry {
     runner: Arc<dyn WorkflowRunner>,
+    version: Option<String>,
+    semver: Option<Version>,
     input_schema: Option<Value>,
     output_schema: Option<Value>,
+    // Compiled `input_schema`, present when input validation is enabled.
+    input_validator: Option<Arc<JSONSchema>>,
+    output_validator: Option<Arc<JSONSchema>>,
+    options: WorkflowOptions,
+}
+
+impl WorkflowEntry {
+    /// Whether runs of `tenant_id` may use this version.
+    fn usable_by(&self, tenant_id: Option<&str>) -> bool {
+        match &self.options.tenants {
+            None => true,
+            Some(tenants) => {
+                tenant_id.is_some_and(|tenant_id| tenants.iter().any(|tenant| tenant == tenant_id))
+            }
+        }
+    }
+
+    /// Whether a caller scoped to `tenant_id` sees this version in the
+    /// registry. Unscoped callers see every version.
+    fn visible_to(&self, tenant_id: Option<&str>) -> bool {
+        tenant_id.is_none() || self.usable_by(tenant_id)
+    }
+}
+
+/// Cursor of [`InMemoryRuntime::event_stream`] over replayed and live events.
+struct EventStreamState {
+    runtime: InMemoryRuntime,
+    run_id: String,
+    receiver: broadcast::Receiver<Event>,
+    // Events to yield before reading the live channel again.
+    pending: VecDeque<Event>,
+    // Ids already yielded, or skipped because they precede the resume point.
+    seen: HashSet<String>,
+    done: bool,
 }
 
+impl EventStreamState {
+    async fn next(mut self) -> Option<(Event, Self)> {
+        // Stop right after the terminal event instead of waiting for another one.
+        if self.done {
+            return None;
+        }
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                self.seen.insert(event.event_id.clone());
+                self.done = event.event_type.is_terminal();
+                return Some((event, self));
+            }
+            match self.receiver.recv().await {
+                Ok(event) if self.seen.contains(&event.event_id) => {}
+                Ok(event) => self.pending.push_back(event),
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    // Every published event is stored before it is broadcast, so
+                    // the store holds whatever the channel dropped.
+                    let stored = self.runtime.list_events(&self.run_id).await?;
+                    self.pending.extend(
+                        stored
+                            .into_iter()
+                            .filter(|event| !self.seen.contains(&event.event_id)),
+                    );
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+// A run's stored event count, `None` until it has been read from the store.
+type EventCount = Arc<Mutex<Option<usize>>>;
+
 #[derive(Clone)]
 pub struct InMemoryRuntime {
-    workflows: Arc<RwLock<HashMap<String, WorkflowEntry>>>,
-    runs: Arc<RwLock<HashMap<String, RunRecord>>>,
-    artifacts: Arc<RwLock<HashMap<String, Artifact>>>,
+    // Versions of each workflow, sorted by ascending semver.
+    workflows: Arc<RwLock<HashMap<String, Vec<WorkflowEntry>>>>,
+    store: Arc<dyn RunStore>,
+    senders: Arc<RwLock<HashMap<String, broadcast::Sender<Event>>>>,
+    cancellations: Arc<RwLock<HashMap<String, CancellationToken>>>,
+    // Checkpoints a runner is currently waiting on, keyed by checkpoint id.
+    pending_checkpoints: Arc<RwLock<HashMap<String, PendingCheckpoint>>>,
+    // Serialises keyed creates so two concurrent retries cannot both miss the
+    // stored key and start duplicate runs.
+    idempotency_lock: Arc<Mutex<()>>,
+    idempotency_ttl: Duration,
+    // Capacity of each run's live event channel.
+    event_buffer: usize,
+    retention: RetentionPolicy,
+    retention_counters: Arc<retention::RetentionCounters>,
+    // Stored event count of each unfinished run under `max_events_per_run`,
+    // counted from the store on first use. Its lock is held while appending.
+    event_counts: Arc<Mutex<HashMap<String, EventCount>>>,
+    // Tenant of each unfinished run, so events don't reload the run.
+    run_tenants: Arc<RwLock<HashMap<String, Option<String>>>>,
+    limits: ConcurrencyLimits,
+    queue: Arc<Mutex<queue::RunQueue>>,
+    // Notified whenever the last running run releases its slot.
+    queue_idle: Arc<Notify>,
+    shutting_down: Arc<AtomicBool>,
 }
 
-struct RunRecord {
-    run: Run,
-    events: Vec<Event>,
-    sender: broadcast::Sender<Event>,
+impl Default for InMemoryRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl InMemoryRuntime {
     pub fn new() -> Self {
+        Self::with_store(Arc::new(InMemoryRunStore::new()))
+    }
+
+    pub fn with_store(store: Arc<dyn RunStore>) -> Self {
         Self {
             workflows: Arc::new(RwLock::new(HashMap::new())),
-            runs: Arc::new(RwLock::new(HashMap::new())),
-            artifacts: Arc::new(RwLock::new(HashMap::new())),
+            store,
+            senders: Arc::new(RwLock::new(HashMap::new())),
+            cancellations: Arc::new(RwLock::new(HashMap::new())),
+            pending_checkpoints: Arc::new(RwLock::new(HashMap::new())),
+            idempotency_lock: Arc::new(Mutex::new(())),
+            idempotency_ttl: DEFAULT_IDEMPOTENCY_TTL,
+            event_buffer: DEFAULT_EVENT_BUFFER,
+            retention: RetentionPolicy::default(),
+            retention_counters: Arc::default(),
+            event_counts: Arc::default(),
+            run_tenants: Arc::default(),
+            limits: ConcurrencyLimits::default(),
+            queue: Arc::default(),
+            queue_idle: Arc::default(),
+            shutting_down: Arc::default(),
         }
     }
 
+    /// How long an `Idempotency-Key` keeps pointing at the run it created.
+    pub fn with_idempotency_ttl(mut self, ttl: Duration) -> Self {
+        self.idempotency_ttl = ttl;
+        self
+    }
+
+    /// How many live events each run buffers for its slowest subscriber.
+    /// Subscribers that fall further behind catch up from the stored events.
+    pub fn with_event_buffer(mut self, capacity: usize) -> Self {
+        self.event_buffer = capacity.max(1);
+        self
+    }
+
+    pub fn store(&self) -> &Arc<dyn RunStore> {
+        &self.store
+    }
+
     pub async fn register_workflow(&self, workflow: Arc<dyn WorkflowRunner>) {
         self.register_workflow_with_schemas(workflow, None, None).await;
     }
@@ -121,76 +382,221 @@ impl InMemoryRuntime {
         input_schema: Option<Value>,
         output_schema: Option<Value>,
     ) {
+        self.register_workflow_with_options(
+            workflow,
+            input_schema,
+            output_schema,
+            WorkflowOptions::default(),
+        )
+        .await;
+    }
+
+    /// Outputs of every run are checked against `output_schema`.
+    ///
+    /// # Panics
+    ///
+    /// If `output_schema`, or `input_schema` with input validation enabled, is
+    /// not a valid JSON Schema.
+    pub async fn register_workflow_with_options(
+        &self,
+        workflow: Arc<dyn WorkflowRunner>,
+        input_schema: Option<Value>,
+        output_schema: Option<Value>,
+        options: WorkflowOptions,
+    ) {
+        let version = workflow.version().map(|v| v.to_string());
+        let input_validator = input_schema
+            .as_ref()
+            .filter(|_| options.validate_input)
+            .map(|schema| compile_schema(workflow.name(), "input", schema));
+        let output_validator = output_schema
+            .as_ref()
+            .map(|schema| compile_schema(workflow.name(), "output", schema));
+        let registration = WorkflowRegistration {
+            name: workflow.name().to_string(),
+            version: version.clone(),
+            input_schema: input_schema.clone(),
+            output_schema: output_schema.clone(),
+            timeout_ms: options.timeout.map(|timeout| timeout.as_millis() as u64),
+            max_attempts: Some(options.retry.max_attempts),
+            active: options.active,
+        };
+        let stored = self.store.save_workflow(registration).await;
+        log_store_error(stored, "workflow registration", workflow.name());
         let mut workflows = self.workflows.write().await;
-        workflows.insert(
-            workflow.name().to_string(),
-            WorkflowEntry {
-                runner: workflow,
-                input_schema,
-                output_schema,
-            },
-        );
+        let versions = workflows.entry(workflow.name().to_string()).or_default();
+        versions.retain(|entry| entry.version != version);
+        versions.push(WorkflowEntry {
+            semver: version.as_deref().and_then(parse_version),
+            version,
+            runner: workflow,
+            input_schema,
+            output_schema,
+            input_validator,
+            output_validator,
+            options,
+        });
+        versions.sort_by(|a, b| a.semver.cmp(&b.semver));
     }
 
+    /// Lists every registered version of every workflow.
     pub async fn list_workflows(&self) -> Vec<WorkflowSummary> {
+        self.list_workflows_for_tenant(None).await
+    }
+
+    /// Like [`Self::list_workflows`], leaving out versions registered for
+    /// other tenants when `tenant_id` is set.
+    pub async fn list_workflows_for_tenant(&self, tenant_id: Option<&str>) -> Vec<WorkflowSummary> {
         let workflows = self.workflows.read().await;
-        workflows
+        let mut data: Vec<WorkflowSummary> = workflows
             .values()
+            .flatten()
+            .filter(|entry| entry.visible_to(tenant_id))
             .map(|entry| WorkflowSummary {
                 name: entry.runner.name().to_string(),
-                version: entry.runner.version().map(|v| v.to_string()),
+                version: entry.version.clone(),
                 description: None,
                 tags: Vec::new(),
             })
-            .collect()
+            .collect();
+        data.sort_by(|a, b| a.name.cmp(&b.name));
+        data
     }
 
-    pub async fn get_workflow(&self, name: &str) -> Option<Workflow> {
+    /// Describes the version `version` resolves to (see [`Self::create_run`])
+    /// and lists all registered versions.
+    pub async fn get_workflow(&self, name: &str, version: Option<&str>) -> Option<Workflow> {
+        self.get_workflow_for_tenant(name, version, None).await
+    }
+
+    /// Like [`Self::get_workflow`], as seen by a caller scoped to `tenant_id`.
+    pub async fn get_workflow_for_tenant(
+        &self,
+        name: &str,
+        version: Option<&str>,
+        tenant_id: Option<&str>,
+    ) -> Option<Workflow> {
         let workflows = self.workflows.read().await;
-        workflows.get(name).map(|entry| Workflow {
+        let versions = visible_versions(workflows.get(name)?, tenant_id);
+        let entry = resolve_version(&versions, version)?;
+        Some(Workflow {
             name: entry.runner.name().to_string(),
-            version: entry.runner.version().map(|v| v.to_string()),
+            version: entry.version.clone(),
             description: None,
             tags: Vec::new(),
+            default_timeout_ms: entry
+                .options
+                .timeout
+                .map(|timeout| timeout.as_millis() as i64),
             input_schema_ref: None,
             output_schema_ref: None,
+            versions: versions
+                .iter()
+                .rev()
+                .map(|entry| WorkflowVersion {
+                    version: entry.version.clone(),
+                    active: entry.options.active,
+                })
+                .collect(),
         })
     }
 
-    pub async fn get_workflow_schemas(&self, name: &str) -> Option<SchemaBundle> {
+    pub async fn get_workflow_schemas(
+        &self,
+        name: &str,
+        version: Option<&str>,
+    ) -> Option<SchemaBundle> {
+        self.get_workflow_schemas_for_tenant(name, version, None).await
+    }
+
+    /// Like [`Self::get_workflow_schemas`], as seen by a caller scoped to
+    /// `tenant_id`.
+    pub async fn get_workflow_schemas_for_tenant(
+        &self,
+        name: &str,
+        version: Option<&str>,
+        tenant_id: Option<&str>,
+    ) -> Option<SchemaBundle> {
         let workflows = self.workflows.read().await;
-        workflows.get(name).map(|entry| {
-            let workflow_ref = WorkflowRef {
-                name: entry.runner.name().to_string(),
-                version: entry.runner.version().map(|v| v.to_string()),
-            };
-            let mut schemas = HashMap::new();
-            if let Some(schema) = &entry.input_schema {
-                schemas.insert("input".to_string(), schema.clone());
-            }
-            if let Some(schema) = &entry.output_schema {
-                schemas.insert("output".to_string(), schema.clone());
-            }
-            SchemaBundle {
-                workflow: workflow_ref,
-                schema_hash: hash_schemas(&schemas),
-                schemas,
-            }
+        let versions = visible_versions(workflows.get(name)?, tenant_id);
+        let entry = resolve_version(&versions, version)?;
+        let workflow_ref = WorkflowRef {
+            name: entry.runner.name().to_string(),
+            version: entry.version.clone(),
+        };
+        let mut schemas = HashMap::new();
+        if let Some(schema) = &entry.input_schema {
+            schemas.insert("input".to_string(), schema.clone());
+        }
+        if let Some(schema) = &entry.output_schema {
+            schemas.insert("output".to_string(), schema.clone());
+        }
+        Some(SchemaBundle {
+            workflow: workflow_ref,
+            schema_hash: hash_schemas(&schemas),
+            schemas,
         })
     }
 
     pub async fn create_run(&self, req: RunCreateRequest) -> Result<Run, ErrorResponse> {
+        self.create_run_as(req, None).await
+    }
+
+    /// Like [`Self::create_run`], recording the API key or other principal
+    /// that asked for the run as its `created_by`.
+    pub async fn create_run_as(
+        &self,
+        req: RunCreateRequest,
+        created_by: Option<&str>,
+    ) -> Result<Run, ErrorResponse> {
+        if self.is_shutting_down() {
+            return Err(ErrorResponse {
+                code: "shutting_down".to_string(),
+                message: "runtime is shutting down; retry against another instance".to_string(),
+                retryable: true,
+                details: None,
+            });
+        }
         let workflow_name = req.workflow.name.clone();
+        let tenant_id = requested_tenant_id(req.metadata.as_ref());
         let workflows = self.workflows.read().await;
-        let entry = workflows.get(&workflow_name).cloned().ok_or_else(|| {
-            ErrorResponse {
+        let versions = workflows
+            .get(&workflow_name)
+            .map(|versions| usable_versions(versions, tenant_id.as_deref()))
+            .filter(|versions| !versions.is_empty())
+            .ok_or_else(|| ErrorResponse {
                 code: "workflow_not_found".to_string(),
                 message: format!("workflow {} not registered", workflow_name),
                 retryable: false,
                 details: None,
-            }
-        })?;
+            })?;
         drop(workflows);
+        let requested = req.workflow.version.as_deref();
+        let entry = resolve_version(&versions, requested)
+            .cloned()
+            .ok_or_else(|| ErrorResponse {
+                code: "workflow_version_not_found".to_string(),
+                message: format!(
+                    "no registered version of {} matches {}",
+                    workflow_name,
+                    requested.unwrap_or("latest")
+                ),
+                retryable: false,
+                details: Some(json!({
+                    "requested": requested,
+                    "available": versions
+                        .iter()
+                        .map(|entry| json!({
+                            "version": entry.version,
+                            "active": entry.options.active,
+                        }))
+                        .collect::<Vec<_>>(),
+                })),
+            })?;
+        if let Some(validator) = &entry.input_validator {
+            validate_input(&workflow_name, validator, &req.input)?;
+        }
+        let timeout = requested_timeout(req.metadata.as_ref())?.or(entry.options.timeout);
 
         let run_id = format!("run_{}", Uuid::new_v4());
         let now = Utc::now();
@@ -204,61 +610,361 @@ impl InMemoryRuntime {
             run_id: run_id.clone(),
             workflow: WorkflowRef {
                 name: entry.runner.name().to_string(),
-                version: entry.runner.version().map(|v| v.to_string()),
+                version: entry.version.clone(),
             },
             status: RunStatus::Queued,
-            trace_id: None,
-            tenant_id: None,
+            trace_id: Some(requested_trace_id(req.metadata.as_ref())),
+            tenant_id,
             timing,
             input: Some(req.input.clone()),
             context: req.context.clone(),
             output: None,
             error: None,
+            cost: None,
             artifacts: Vec::new(),
+            attempts: 0,
+            checkpoints: Vec::new(),
+            labels: req.labels.clone().unwrap_or_default(),
+            metadata: req.metadata.clone(),
+            queue_position: None,
+            created_by: created_by.map(str::to_string),
         };
 
-        let (sender, _) = broadcast::channel(100);
-        let record = RunRecord {
+        // Held until the run is queued so concurrent creates cannot overfill it.
+        let mut queue = self.queue.lock().await;
+        queue.ensure_capacity(&self.limits, &run)?;
+        // Stored first so a failed insert leaves nothing behind in the maps below.
+        self.store
+            .insert_run(run.clone())
+            .await
+            .map_err(storage_error)?;
+        let (sender, _) = broadcast::channel(self.event_buffer);
+        self.senders.write().await.insert(run_id.clone(), sender);
+        self.run_tenants
+            .write()
+            .await
+            .insert(run_id.clone(), run.tenant_id.clone());
+        self.event_counts
+            .lock()
+            .await
+            .insert(run_id.clone(), Arc::new(Mutex::new(Some(0))));
+
+        let cancel = CancellationToken::new();
+        self.cancellations
+            .write()
+            .await
+            .insert(run_id.clone(), cancel.clone());
+
+        queue.push(queue::QueuedRun {
             run: run.clone(),
-            events: Vec::new(),
-            sender,
+            entry,
+            cancel,
+            timeout,
+        });
+        drop(queue);
+        self.dispatch_queued().await;
+        Ok(self.get_run(&run_id).await.unwrap_or(run))
+    }
+
+    /// Creates a run unless `idempotency_key` was already used for an identical
+    /// request within the TTL, in which case the original run is returned. The
+    /// returned flag is `true` for such replays.
+    pub async fn create_run_with_idempotency(
+        &self,
+        idempotency_key: &str,
+        req: RunCreateRequest,
+    ) -> Result<(Run, bool), ErrorResponse> {
+        self.create_run_with_idempotency_as(idempotency_key, req, None)
+            .await
+    }
+
+    /// Like [`Self::create_run_with_idempotency`], recording `created_by` on a
+    /// newly created run.
+    pub async fn create_run_with_idempotency_as(
+        &self,
+        idempotency_key: &str,
+        req: RunCreateRequest,
+        created_by: Option<&str>,
+    ) -> Result<(Run, bool), ErrorResponse> {
+        let request_hash = hash_request(&req);
+        // Keys are per tenant, so tenants cannot replay or probe each other's runs.
+        let idempotency_key = match requested_tenant_id(req.metadata.as_ref()) {
+            Some(tenant_id) => format!("{}/{}", tenant_id, idempotency_key),
+            None => idempotency_key.to_string(),
         };
-        self.runs.write().await.insert(run_id.clone(), record);
+        let _guard = self.idempotency_lock.lock().await;
+        let existing = self
+            .store
+            .get_idempotency_record(&idempotency_key)
+            .await
+            .map_err(storage_error)?;
+        if let Some(record) = existing
+            && record.expires_at > Utc::now()
+        {
+            if record.request_hash != request_hash {
+                return Err(ErrorResponse {
+                    code: "idempotency_conflict".to_string(),
+                    message: "Idempotency-Key was already used with a different request body"
+                        .to_string(),
+                    retryable: false,
+                    details: Some(json!({ "run_id": record.run_id })),
+                });
+            }
+            if let Some(run) = self.get_run(&record.run_id).await {
+                return Ok((run, true));
+            }
+        }
 
-        let runtime = self.clone();
-        tokio::spawn(async move {
-            runtime.execute_run(run_id, entry.runner, req.input).await;
-        });
+        let run = self.create_run_as(req, created_by).await?;
+        let ttl = chrono::Duration::from_std(self.idempotency_ttl)
+            .unwrap_or_else(|_| chrono::Duration::days(1));
+        self.store
+            .put_idempotency_record(IdempotencyRecord {
+                key: idempotency_key,
+                run_id: run.run_id.clone(),
+                request_hash,
+                expires_at: Utc::now() + ttl,
+            })
+            .await
+            .map_err(storage_error)?;
+        Ok((run, false))
+    }
+
+    pub async fn cancel_run(&self, run_id: &str) -> Result<Run, ErrorResponse> {
+        let run = self.get_run(run_id).await.ok_or_else(|| ErrorResponse {
+            code: "not_found".to_string(),
+            message: "run not found".to_string(),
+            retryable: false,
+            details: None,
+        })?;
+        if run.status.is_terminal() {
+            return Err(ErrorResponse {
+                code: "run_not_cancelable".to_string(),
+                message: format!("run {} already finished", run_id),
+                retryable: false,
+                details: Some(json!({ "status": run.status })),
+            });
+        }
 
-        Ok(run)
+        let queued = self.queue.lock().await.remove(run_id);
+        if queued.is_some() {
+            self.cancellations.write().await.remove(run_id);
+            self.finish_canceled(run_id, None).await.map_err(storage_error)?;
+            return Ok(self.get_run(run_id).await.unwrap_or(run));
+        }
+        let token = self.cancellations.read().await.get(run_id).cloned();
+        match token {
+            Some(token) => token.cancel(),
+            // No task owns the run (e.g. it was loaded from a persistent store), so
+            // finish it here.
+            None => self
+                .finish_canceled(run_id, run.timing.started_at)
+                .await
+                .map_err(storage_error)?,
+        }
+        Ok(self.get_run(run_id).await.unwrap_or(run))
+    }
+
+    pub async fn approve_checkpoint(
+        &self,
+        run_id: &str,
+        checkpoint_id: &str,
+        note: Option<String>,
+    ) -> Result<HumanCheckpoint, ErrorResponse> {
+        self.resolve_checkpoint(run_id, checkpoint_id, CheckpointResolution::Approve { note })
+            .await
+    }
+
+    pub async fn reject_checkpoint(
+        &self,
+        run_id: &str,
+        checkpoint_id: &str,
+        note: Option<String>,
+    ) -> Result<HumanCheckpoint, ErrorResponse> {
+        self.resolve_checkpoint(run_id, checkpoint_id, CheckpointResolution::Reject { note })
+            .await
+    }
+
+    pub async fn provide_checkpoint_input(
+        &self,
+        run_id: &str,
+        checkpoint_id: &str,
+        input: Value,
+        note: Option<String>,
+    ) -> Result<HumanCheckpoint, ErrorResponse> {
+        let resolution = CheckpointResolution::ProvideInput { input, note };
+        self.resolve_checkpoint(run_id, checkpoint_id, resolution).await
     }
 
     pub async fn get_run(&self, run_id: &str) -> Option<Run> {
-        let runs = self.runs.read().await;
-        runs.get(run_id).map(|record| record.run.clone())
+        let mut run = self.store.get_run(run_id).await.ok().flatten()?;
+        if run.status == RunStatus::Queued {
+            self.with_queue_positions(std::slice::from_mut(&mut run)).await;
+        }
+        Some(run)
+    }
+
+    /// Lists runs newest first. `next_cursor` is set when more runs may follow.
+    pub async fn list_runs(&self, query: RunListQuery) -> Result<RunListResponse, ErrorResponse> {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        let cursor = query.cursor.as_deref().map(decode_run_cursor).transpose()?;
+        let labels = query
+            .label_selector
+            .as_deref()
+            .map(parse_label_selector)
+            .transpose()?
+            .unwrap_or_default();
+        let mut data = self
+            .store
+            .list_runs(&RunQuery {
+                workflow_name: query.workflow_name,
+                workflow_names: query.workflow_names,
+                status: query.status,
+                labels,
+                created_after: query.created_after,
+                created_before: query.created_before,
+                tenant_id: query.tenant_id,
+                cursor,
+                limit: limit + 1,
+            })
+            .await
+            .map_err(storage_error)?;
+        let next_cursor = if data.len() > limit {
+            data.truncate(limit);
+            data.last().map(|run| encode_run_cursor(&RunCursor::of(run)))
+        } else {
+            None
+        };
+        self.with_queue_positions(&mut data).await;
+        Ok(RunListResponse { data, next_cursor })
     }
 
     pub async fn list_events(&self, run_id: &str) -> Option<Vec<Event>> {
-        let runs = self.runs.read().await;
-        runs.get(run_id).map(|record| record.events.clone())
+        self.store.list_events(run_id).await.ok().flatten()
+    }
+
+    /// One page of a run's events in emission order; pass `next_cursor` back as
+    /// `cursor` for the next page.
+    pub async fn list_events_page(
+        &self,
+        run_id: &str,
+        query: EventListQuery,
+    ) -> Result<EventListResponse, ErrorResponse> {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        let cursor = query.cursor.as_deref().map(decode_event_cursor).transpose()?;
+        let types = query
+            .types
+            .as_deref()
+            .map(parse_event_types)
+            .transpose()?
+            .unwrap_or_default();
+        let mut data = self
+            .store
+            .query_events(
+                run_id,
+                &EventQuery {
+                    types,
+                    step_id: query.step_id,
+                    after: cursor.or(query.after),
+                    limit: limit + 1,
+                },
+            )
+            .await
+            .map_err(|err| match err {
+                StoreError::UnknownEvent(event_id) => ErrorResponse {
+                    code: "invalid_cursor".to_string(),
+                    message: format!("event {} no longer exists", event_id),
+                    retryable: false,
+                    details: None,
+                },
+                err => storage_error(err),
+            })?
+            .ok_or_else(|| ErrorResponse {
+                code: "not_found".to_string(),
+                message: "run not found".to_string(),
+                retryable: false,
+                details: None,
+            })?;
+        let next_cursor = if data.len() > limit {
+            data.truncate(limit);
+            data.last().map(|event| URL_SAFE_NO_PAD.encode(&event.event_id))
+        } else {
+            None
+        };
+        Ok(EventListResponse { data, next_cursor })
+    }
+
+    /// Stored events of a run after `after` (all of them when `after` is
+    /// `None` or unknown), followed by live events. Each event is yielded once
+    /// and the stream ends after the run's terminal event. A subscriber that
+    /// lags behind the live buffer catches up from the stored events.
+    pub async fn event_stream(
+        &self,
+        run_id: &str,
+        after: Option<&str>,
+    ) -> Option<BoxStream<'static, Event>> {
+        // Subscribe before reading the store so nothing published in between is
+        // missed; events seen in both places are dropped from the live side.
+        let receiver = self.subscribe_events(run_id).await?;
+        let stored = self.list_events(run_id).await.unwrap_or_default();
+        let finished = stored.iter().any(|event| event.event_type.is_terminal());
+        let start = after
+            .and_then(|after| stored.iter().position(|event| event.event_id == after))
+            .map_or(0, |index| index + 1);
+        let seen: HashSet<String> = stored.iter().map(|event| event.event_id.clone()).collect();
+        let pending: VecDeque<Event> = stored.into_iter().skip(start).collect();
+        if finished {
+            return Some(stream::iter(pending).boxed());
+        }
+        let state = EventStreamState {
+            runtime: self.clone(),
+            run_id: run_id.to_string(),
+            receiver,
+            pending,
+            seen,
+            done: false,
+        };
+        Some(stream::unfold(state, EventStreamState::next).boxed())
     }
 
     pub async fn subscribe_events(&self, run_id: &str) -> Option<broadcast::Receiver<Event>> {
-        let runs = self.runs.read().await;
-        runs.get(run_id).map(|record| record.sender.subscribe())
+        if let Some(sender) = self.senders.read().await.get(run_id) {
+            return Some(sender.subscribe());
+        }
+        // Runs loaded from a persistent store have no live channel yet.
+        self.get_run(run_id).await?;
+        let mut senders = self.senders.write().await;
+        let sender = senders
+            .entry(run_id.to_string())
+            .or_insert_with(|| broadcast::channel(self.event_buffer).0);
+        Some(sender.subscribe())
     }
 
     pub async fn get_artifact(&self, artifact_id: &str) -> Option<Artifact> {
-        let artifacts = self.artifacts.read().await;
-        artifacts.get(artifact_id).cloned()
+        self.store.get_artifact(artifact_id).await.ok().flatten()
     }
 
     async fn execute_run(
         &self,
-        run_id: String,
-        workflow: Arc<dyn WorkflowRunner>,
-        input: Value,
+        run: Run,
+        entry: WorkflowEntry,
+        cancel: CancellationToken,
+        timeout: Option<Duration>,
     ) {
+        let workflow = entry.runner;
+        let retry = entry.options.retry;
+        let run_id = run.run_id.clone();
+        let input = run.input.clone().unwrap_or(Value::Null);
+        if cancel.is_cancelled() {
+            log_store_error(self.finish_canceled(&run_id, None).await, "canceled run", &run_id);
+            return;
+        }
         let started_at = Utc::now();
         self.update_run_status(&run_id, RunStatus::Running, Some(started_at), None, None)
             .await;
@@ -269,101 +975,626 @@ impl InMemoryRuntime {
             json!({ "workflow": { "name": workflow.name(), "version": workflow.version() } }),
         )
         .await;
-        self.emit_event(
-            &run_id,
-            EventType::StepStarted,
-            Some("workflow.run".to_string()),
-            json!({}),
-        )
-        .await;
+        let deadline = async {
+            match timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(deadline);
 
-        let result = workflow.run(input).await;
-        match result {
-            Ok(output) => {
-                let mut artifact_refs = Vec::new();
-                for artifact in output.artifacts {
-                    let artifact_id = artifact.artifact_id.clone();
-                    artifact_refs.push(ArtifactRef {
-                        artifact_id: artifact_id.clone(),
-                        r#type: artifact.r#type.clone(),
-                        name: artifact.name.clone(),
-                    });
-                    self.artifacts
-                        .write()
-                        .await
-                        .insert(artifact_id.clone(), artifact);
+        let mut attempt = 0;
+        // Whether the current attempt's step still needs a closing event.
+        let mut step_open = true;
+        let outcome = loop {
+            attempt += 1;
+            self.record_attempt(&run_id, attempt).await;
+            self.emit_event(
+                &run_id,
+                EventType::StepStarted,
+                Some(attempt_step_id(attempt)),
+                json!({ "attempt": attempt }),
+            )
+            .await;
+
+            let ctx = RunContext::new(self.clone(), &run, attempt, cancel.clone());
+            // Dropping the runner future stops it at its next await point even if
+            // the runner never checks the token itself.
+            let outcome = tokio::select! {
+                result = workflow.run(input.clone(), &ctx) => RunOutcome::Finished(result),
+                _ = cancel.cancelled() => RunOutcome::Canceled,
+                _ = &mut deadline => RunOutcome::TimedOut,
+            };
+            let err = match outcome {
+                RunOutcome::Finished(Err(err))
+                    if err.is_retryable()
+                        && attempt < retry.max_attempts
+                        && !cancel.is_cancelled() =>
+                {
+                    err
+                }
+                outcome => break outcome,
+            };
+
+            let delay = retry.backoff(attempt);
+            self.emit_event(
+                &run_id,
+                EventType::StepFailed,
+                Some(attempt_step_id(attempt)),
+                json!({
+                    "ok": false,
+                    "attempt": attempt,
+                    "retryable": true,
+                    "error": err.message(),
+                    "retry_in_ms": delay.as_millis() as u64,
+                }),
+            )
+            .await;
+            let interrupted = tokio::select! {
+                _ = tokio::time::sleep(delay) => None,
+                _ = cancel.cancelled() => Some(RunOutcome::Canceled),
+                _ = &mut deadline => Some(RunOutcome::TimedOut),
+            };
+            if let Some(outcome) = interrupted {
+                step_open = false;
+                break outcome;
+            }
+        };
+
+        let result = match outcome {
+            RunOutcome::Finished(result) if !cancel.is_cancelled() => result,
+            RunOutcome::TimedOut => {
+                cancel.cancel();
+                if step_open {
                     self.emit_event(
                         &run_id,
-                        EventType::ArtifactCreated,
-                        None,
-                        json!({ "artifact_id": artifact_id }),
+                        EventType::StepFailed,
+                        Some(attempt_step_id(attempt)),
+                        json!({ "ok": false, "attempt": attempt, "timed_out": true }),
                     )
                     .await;
                 }
-
-                let finished_at = Utc::now();
-                self.update_run_success(&run_id, output.output, artifact_refs, started_at, finished_at)
+                self.finish_timed_out(&run_id, started_at, timeout.unwrap_or_default())
+                    .await;
+                return;
+            }
+            _ => {
+                if step_open {
+                    self.emit_event(
+                        &run_id,
+                        EventType::StepFailed,
+                        Some(attempt_step_id(attempt)),
+                        json!({ "ok": false, "attempt": attempt, "canceled": true }),
+                    )
                     .await;
+                }
+                let stored = self.finish_canceled(&run_id, Some(started_at)).await;
+                log_store_error(stored, "canceled run", &run_id);
+                return;
+            }
+        };
+        match result {
+            Ok(output) => {
                 self.emit_event(
                     &run_id,
                     EventType::StepCompleted,
-                    Some("workflow.run".to_string()),
-                    json!({ "ok": true }),
-                )
-                .await;
-                self.emit_event(
-                    &run_id,
-                    EventType::RunCompleted,
-                    None,
-                    json!({ "status": "succeeded" }),
+                    Some(attempt_step_id(attempt)),
+                    json!({ "ok": true, "attempt": attempt }),
                 )
                 .await;
+                let validated = match &entry.output_validator {
+                    Some(validator) => {
+                        self.validate_output(&run_id, validator, &output.output).await
+                    }
+                    None => Ok(()),
+                };
+                if let Err(error) = validated {
+                    self.finish_failed(&run_id, error, started_at, Utc::now())
+                        .await;
+                    return;
+                }
+                for artifact in output.artifacts {
+                    self.register_artifact(&run_id, artifact).await;
+                }
+
+                let finished_at = Utc::now();
+                self.finish_succeeded(&run_id, output.output, started_at, finished_at)
+                    .await;
             }
             Err(err) => {
                 let finished_at = Utc::now();
+                let err_retryable = err.is_retryable();
+                let err_message = err.message().to_string();
                 let error = ErrorResponse {
                     code: "workflow_error".to_string(),
                     message: err.message().to_string(),
                     retryable: err.is_retryable(),
                     details: err.details().cloned(),
                 };
-                self.update_run_failure(&run_id, error, started_at, finished_at)
-                    .await;
                 self.emit_event(
                     &run_id,
                     EventType::StepFailed,
-                    Some("workflow.run".to_string()),
-                    json!({ "ok": false }),
+                    Some(attempt_step_id(attempt)),
+                    json!({
+                        "ok": false,
+                        "attempt": attempt,
+                        "retryable": err_retryable,
+                        "error": err_message,
+                    }),
                 )
                 .await;
-                self.emit_event(
-                    &run_id,
-                    EventType::RunFailed,
-                    None,
-                    json!({ "status": "failed" }),
-                )
+                self.finish_failed(&run_id, error, started_at, finished_at)
+                    .await;
+            }
+        }
+    }
+
+    /// Checks a runner's output against the registered output schema inside a
+    /// `workflow.validate_output` step.
+    async fn validate_output(
+        &self,
+        run_id: &str,
+        validator: &JSONSchema,
+        output: &Value,
+    ) -> Result<(), ErrorResponse> {
+        let step_id = Some("workflow.validate_output".to_string());
+        self.emit_event(run_id, EventType::StepStarted, step_id.clone(), json!({}))
+            .await;
+        let Some(errors) = schema_violations(validator, output) else {
+            self.emit_event(run_id, EventType::StepCompleted, step_id, json!({ "ok": true }))
                 .await;
+            return Ok(());
+        };
+        self.emit_event(
+            run_id,
+            EventType::StepFailed,
+            step_id,
+            json!({ "ok": false, "errors": errors }),
+        )
+        .await;
+        Err(ErrorResponse {
+            code: "output_validation_failed".to_string(),
+            message: "workflow output does not match its output schema".to_string(),
+            retryable: false,
+            details: Some(json!({ "errors": errors })),
+        })
+    }
+
+    async fn open_checkpoint(
+        &self,
+        run_id: &str,
+        request: CheckpointRequest,
+    ) -> Result<HumanCheckpoint, AgentError> {
+        let now = Utc::now();
+        let checkpoint = HumanCheckpoint {
+            checkpoint_id: format!("hcp_{}", Uuid::new_v4()),
+            run_id: run_id.to_string(),
+            status: HumanCheckpointStatus::Required,
+            created_at: now,
+            expires_at: request
+                .expires_in
+                .and_then(|expires_in| chrono::Duration::from_std(expires_in).ok())
+                .map(|expires_in| now + expires_in),
+            title: request.title,
+            instructions: request.instructions,
+            required_schema: request.required_schema,
+            provided_input: None,
+            decision: None,
+        };
+        let checkpoint_id = checkpoint.checkpoint_id.clone();
+        let (resolved, mut receiver) = oneshot::channel();
+        self.pending_checkpoints.write().await.insert(
+            checkpoint_id.clone(),
+            PendingCheckpoint {
+                run_id: run_id.to_string(),
+                resolved,
+            },
+        );
+        let stored = checkpoint.clone();
+        let updated = self
+            .update_run_with_event(
+                run_id,
+                Box::new(move |run| {
+                    run.status = RunStatus::WaitingHuman;
+                    run.checkpoints.push(stored);
+                }),
+                EventType::HitlRequired,
+                json!({
+                    "checkpoint_id": checkpoint_id,
+                    "title": checkpoint.title,
+                    "instructions": checkpoint.instructions,
+                    "required_schema": checkpoint.required_schema,
+                    "expires_at": checkpoint.expires_at,
+                }),
+            )
+            .await;
+        if !matches!(updated, Ok(Some(_))) {
+            self.pending_checkpoints.write().await.remove(&checkpoint_id);
+            return Err(AgentError::retryable("failed to record checkpoint"));
+        }
+
+        let expiry = async {
+            match request.expires_in {
+                Some(expires_in) => tokio::time::sleep(expires_in).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            resolved = &mut receiver => {
+                return resolved.map_err(|_| AgentError::fatal("checkpoint abandoned"));
             }
+            _ = expiry => {}
         }
+        // Whoever removes the pending entry owns the resolution; if a human got
+        // there first, take their answer instead of expiring.
+        if self
+            .pending_checkpoints
+            .write()
+            .await
+            .remove(&checkpoint_id)
+            .is_none()
+        {
+            return receiver
+                .await
+                .map_err(|_| AgentError::fatal("checkpoint abandoned"));
+        }
+        let closed = self
+            .close_checkpoint(run_id, &checkpoint_id, HumanCheckpointStatus::Expired, None, None)
+            .await;
+        let expired = matches!(
+            &closed,
+            Ok(Some(checkpoint)) if checkpoint.status == HumanCheckpointStatus::Expired
+        );
+        log_store_error(closed, "expired checkpoint", run_id);
+        if expired {
+            self.emit_event(
+                run_id,
+                EventType::HitlResolved,
+                None,
+                json!({
+                    "checkpoint_id": checkpoint_id,
+                    "status": HumanCheckpointStatus::Expired,
+                    "expired": true,
+                }),
+            )
+            .await;
+        }
+        Err(AgentError::fatal_with_details(
+            "checkpoint expired before a human resolved it",
+            json!({ "checkpoint_id": checkpoint_id }),
+        ))
+    }
+
+    async fn resolve_checkpoint(
+        &self,
+        run_id: &str,
+        checkpoint_id: &str,
+        resolution: CheckpointResolution,
+    ) -> Result<HumanCheckpoint, ErrorResponse> {
+        let not_found = |message: &str| ErrorResponse {
+            code: "not_found".to_string(),
+            message: message.to_string(),
+            retryable: false,
+            details: None,
+        };
+        let run = self
+            .get_run(run_id)
+            .await
+            .ok_or_else(|| not_found("run not found"))?;
+        let checkpoint = run
+            .checkpoints
+            .iter()
+            .find(|checkpoint| checkpoint.checkpoint_id == checkpoint_id)
+            .ok_or_else(|| not_found("checkpoint not found"))?;
+        if checkpoint.status != HumanCheckpointStatus::Required {
+            return Err(ErrorResponse {
+                code: "checkpoint_already_resolved".to_string(),
+                message: format!("checkpoint {} was already resolved", checkpoint_id),
+                retryable: false,
+                details: Some(json!({ "status": checkpoint.status })),
+            });
+        }
+        let pending = {
+            let mut pending_checkpoints = self.pending_checkpoints.write().await;
+            match pending_checkpoints.get(checkpoint_id) {
+                Some(pending) if pending.run_id == run_id => {
+                    pending_checkpoints.remove(checkpoint_id)
+                }
+                _ => None,
+            }
+        };
+        // The runner is gone (run finished, canceled or the process restarted).
+        let pending = pending.ok_or_else(|| ErrorResponse {
+            code: "checkpoint_not_pending".to_string(),
+            message: format!("no runner is waiting on checkpoint {}", checkpoint_id),
+            retryable: false,
+            details: Some(json!({ "run_status": run.status })),
+        })?;
+
+        let decided_at = Utc::now();
+        let (status, approved, note, provided_input) = match resolution {
+            CheckpointResolution::Approve { note } => {
+                (HumanCheckpointStatus::Approved, true, note, None)
+            }
+            CheckpointResolution::Reject { note } => {
+                (HumanCheckpointStatus::Rejected, false, note, None)
+            }
+            CheckpointResolution::ProvideInput { input, note } => {
+                (HumanCheckpointStatus::InputProvided, true, note, Some(input))
+            }
+        };
+        let decision = CheckpointDecision {
+            approved,
+            note: note.clone(),
+            decided_at,
+        };
+        let checkpoint = self
+            .close_checkpoint(run_id, checkpoint_id, status.clone(), Some(decision), provided_input)
+            .await
+            .map_err(storage_error)?
+            .ok_or_else(|| not_found("checkpoint not found"))?;
+        // The run ended between taking the pending entry and closing it.
+        if checkpoint.status != status {
+            return Err(ErrorResponse {
+                code: "checkpoint_not_pending".to_string(),
+                message: format!("no runner is waiting on checkpoint {}", checkpoint_id),
+                retryable: false,
+                details: Some(json!({ "status": checkpoint.status })),
+            });
+        }
+        self.emit_event(
+            run_id,
+            EventType::HitlResolved,
+            None,
+            json!({
+                "checkpoint_id": checkpoint_id,
+                "status": status,
+                "approved": approved,
+                "note": note,
+            }),
+        )
+        .await;
+        let _ = pending.resolved.send(checkpoint.clone());
+        Ok(checkpoint)
+    }
+
+    /// Records the outcome of a checkpoint and returns the run to `running`
+    /// once no other checkpoint is still open. A checkpoint that is no longer
+    /// open, or whose run already finished, is returned unchanged.
+    async fn close_checkpoint(
+        &self,
+        run_id: &str,
+        checkpoint_id: &str,
+        status: HumanCheckpointStatus,
+        decision: Option<CheckpointDecision>,
+        provided_input: Option<Value>,
+    ) -> Result<Option<HumanCheckpoint>, StoreError> {
+        let id = checkpoint_id.to_string();
+        let run = self
+            .store
+            .update_run(
+                run_id,
+                Box::new(move |run| {
+                    if run.status.is_terminal() {
+                        return;
+                    }
+                    if let Some(checkpoint) = run.checkpoints.iter_mut().find(|checkpoint| {
+                        checkpoint.checkpoint_id == id
+                            && checkpoint.status == HumanCheckpointStatus::Required
+                    }) {
+                        checkpoint.status = status;
+                        checkpoint.decision = decision;
+                        checkpoint.provided_input = provided_input;
+                    }
+                    let waiting = run
+                        .checkpoints
+                        .iter()
+                        .any(|checkpoint| checkpoint.status == HumanCheckpointStatus::Required);
+                    if run.status == RunStatus::WaitingHuman && !waiting {
+                        run.status = RunStatus::Running;
+                    }
+                }),
+            )
+            .await?;
+        Ok(run.and_then(|run| {
+            run.checkpoints
+                .into_iter()
+                .find(|checkpoint| checkpoint.checkpoint_id == checkpoint_id)
+        }))
+    }
+
+    /// Forgets the runner's side of the run's open checkpoints, so nobody can
+    /// resolve them once the run ends.
+    async fn drop_pending_checkpoints(&self, run_id: &str) {
+        self.pending_checkpoints
+            .write()
+            .await
+            .retain(|_, pending| pending.run_id != run_id);
+    }
+
+    async fn record_attempt(&self, run_id: &str, attempt: u32) {
+        let stored = self
+            .store
+            .update_run(run_id, Box::new(move |run| run.attempts = attempt))
+            .await;
+        log_store_error(stored, "run attempt", run_id);
+    }
+
+    /// Records the cancellation and emits `run.canceled`. The event is sent
+    /// even when the store write fails, so streams still end.
+    async fn finish_canceled(
+        &self,
+        run_id: &str,
+        started_at: Option<chrono::DateTime<Utc>>,
+    ) -> Result<(), StoreError> {
+        let finished_at = Utc::now();
+        let wall_ms = started_at.map(|started_at| (finished_at - started_at).num_milliseconds());
+        self.drop_pending_checkpoints(run_id).await;
+        self.update_run_with_event(
+            run_id,
+            Box::new(move |run| {
+                run.status = RunStatus::Canceled;
+                cancel_open_checkpoints(run);
+                run.error = Some(ErrorResponse {
+                    code: "canceled".to_string(),
+                    message: "run canceled".to_string(),
+                    retryable: false,
+                    details: None,
+                });
+                run.timing.finished_at = Some(finished_at);
+                run.timing.wall_ms = wall_ms;
+            }),
+            EventType::RunCanceled,
+            json!({ "status": "canceled" }),
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn finish_timed_out(
+        &self,
+        run_id: &str,
+        started_at: chrono::DateTime<Utc>,
+        timeout: Duration,
+    ) {
+        let finished_at = Utc::now();
+        let wall_ms = (finished_at - started_at).num_milliseconds();
+        let timeout_ms = timeout.as_millis() as u64;
+        // There is no dedicated timeout event type; `run.failed` keeps existing
+        // stream consumers terminating, and the payload carries the real status.
+        self.drop_pending_checkpoints(run_id).await;
+        let stored = self
+            .update_run_with_event(
+                run_id,
+                Box::new(move |run| {
+                    run.status = RunStatus::TimedOut;
+                    cancel_open_checkpoints(run);
+                    run.error = Some(ErrorResponse {
+                        code: "timeout".to_string(),
+                        message: format!("run exceeded timeout of {} ms", timeout_ms),
+                        retryable: true,
+                        details: Some(json!({ "timeout_ms": timeout_ms })),
+                    });
+                    run.timing.finished_at = Some(finished_at);
+                    run.timing.wall_ms = Some(wall_ms);
+                }),
+                EventType::RunFailed,
+                json!({ "status": "timed_out" }),
+            )
+            .await;
+        log_store_error(stored, "timed out run", run_id);
     }
 
     async fn emit_event(&self, run_id: &str, event_type: EventType, step_id: Option<String>, payload: Value) {
-        let event = Event {
+        let event = self.new_event(run_id, event_type, step_id, payload).await;
+        self.publish(event).await;
+    }
+
+    async fn new_event(
+        &self,
+        run_id: &str,
+        event_type: EventType,
+        step_id: Option<String>,
+        payload: Value,
+    ) -> Event {
+        Event {
             event_id: format!("evt_{}", Uuid::new_v4()),
             ts: Utc::now(),
             event_type,
             run_id: run_id.to_string(),
             step_id,
             tool_name: None,
+            tenant_id: self.run_tenant(run_id).await,
             payload,
-        };
-        let mut runs = self.runs.write().await;
-        if let Some(record) = runs.get_mut(run_id) {
-            record.events.push(event.clone());
-            let _ = record.sender.send(event);
         }
     }
 
+    /// Applies `update` to a run and emits the event announcing it. The event
+    /// is stored before the run changes and sent live after, so anyone who
+    /// sees the new status also finds the event.
+    async fn update_run_with_event(
+        &self,
+        run_id: &str,
+        update: RunUpdate,
+        event_type: EventType,
+        payload: Value,
+    ) -> Result<Option<Run>, StoreError> {
+        let event = self.new_event(run_id, event_type, None, payload).await;
+        let marker = self.store_event(&event).await;
+        let updated = self.store.update_run(run_id, update).await;
+        self.broadcast(event, marker).await;
+        updated
+    }
+
+    async fn run_tenant(&self, run_id: &str) -> Option<String> {
+        if let Some(tenant_id) = self.run_tenants.read().await.get(run_id) {
+            return tenant_id.clone();
+        }
+        let tenant_id = self.store.get_run(run_id).await.ok().flatten()?.tenant_id;
+        self.run_tenants
+            .write()
+            .await
+            .insert(run_id.to_string(), tenant_id.clone());
+        tenant_id
+    }
+
+    async fn publish(&self, event: Event) {
+        let marker = self.store_event(&event).await;
+        self.broadcast(event, marker).await;
+    }
+
+    /// Sends a stored `event` to live subscribers, after its truncation
+    /// `marker` if one was stored in its place.
+    async fn broadcast(&self, event: Event, marker: Option<Event>) {
+        if event.event_type.is_terminal() {
+            self.run_tenants.write().await.remove(&event.run_id);
+            self.event_counts.lock().await.remove(&event.run_id);
+        }
+        if let Some(sender) = self.senders.read().await.get(&event.run_id) {
+            if let Some(marker) = marker {
+                let _ = sender.send(marker);
+            }
+            let _ = sender.send(event);
+        }
+    }
+
+    async fn register_artifact(&self, run_id: &str, mut artifact: Artifact) -> ArtifactRef {
+        artifact.tenant_id = self.run_tenant(run_id).await;
+        artifact.run_id = Some(run_id.to_string());
+        let artifact_ref = ArtifactRef {
+            artifact_id: artifact.artifact_id.clone(),
+            r#type: artifact.r#type.clone(),
+            name: artifact.name.clone(),
+        };
+        let stored = self.store.insert_artifact(artifact).await;
+        log_store_error(stored, "artifact", &artifact_ref.artifact_id);
+        let listed = artifact_ref.clone();
+        let stored = self
+            .store
+            .update_run(run_id, Box::new(move |run| run.artifacts.push(listed)))
+            .await;
+        log_store_error(stored, "run artifact list", run_id);
+        self.emit_event(
+            run_id,
+            EventType::ArtifactCreated,
+            None,
+            json!({ "artifact_id": artifact_ref.artifact_id }),
+        )
+        .await;
+        artifact_ref
+    }
+
+    async fn add_cost(&self, run_id: &str, cost: Cost) {
+        let stored = self
+            .store
+            .update_run(
+                run_id,
+                Box::new(move |run| run.cost.get_or_insert_with(Cost::default).add(&cost)),
+            )
+            .await;
+        log_store_error(stored, "run cost", run_id);
+    }
+
     async fn update_run_status(
         &self,
         run_id: &str,
@@ -372,41 +1603,78 @@ impl InMemoryRuntime {
         finished_at: Option<chrono::DateTime<Utc>>,
         wall_ms: Option<i64>,
     ) {
-        let mut runs = self.runs.write().await;
-        if let Some(record) = runs.get_mut(run_id) {
-            record.run.status = status;
-            if let Some(started_at) = started_at {
-                record.run.timing.started_at = Some(started_at);
-            }
-            if let Some(finished_at) = finished_at {
-                record.run.timing.finished_at = Some(finished_at);
-            }
-            if let Some(wall_ms) = wall_ms {
-                record.run.timing.wall_ms = Some(wall_ms);
-            }
-        }
+        let stored = self
+            .store
+            .update_run(
+                run_id,
+                Box::new(move |run| {
+                    run.status = status;
+                    if let Some(started_at) = started_at {
+                        run.timing.started_at = Some(started_at);
+                    }
+                    if let Some(finished_at) = finished_at {
+                        run.timing.finished_at = Some(finished_at);
+                    }
+                    if let Some(wall_ms) = wall_ms {
+                        run.timing.wall_ms = Some(wall_ms);
+                    }
+                }),
+            )
+            .await;
+        log_store_error(stored, "run status", run_id);
     }
 
-    async fn update_run_success(
+    async fn finish_succeeded(
         &self,
         run_id: &str,
         output: Value,
-        artifacts: Vec<ArtifactRef>,
         started_at: chrono::DateTime<Utc>,
         finished_at: chrono::DateTime<Utc>,
     ) {
         let wall_ms = (finished_at - started_at).num_milliseconds();
-        let mut runs = self.runs.write().await;
-        if let Some(record) = runs.get_mut(run_id) {
-            record.run.status = RunStatus::Succeeded;
-            record.run.output = Some(output);
-            record.run.artifacts = artifacts;
-            record.run.timing.finished_at = Some(finished_at);
-            record.run.timing.wall_ms = Some(wall_ms);
-        }
+        let stored = self
+            .update_run_with_event(
+                run_id,
+                Box::new(move |run| {
+                    run.status = RunStatus::Succeeded;
+                    run.output = Some(output);
+                    run.timing.finished_at = Some(finished_at);
+                    run.timing.wall_ms = Some(wall_ms);
+                }),
+                EventType::RunCompleted,
+                json!({ "status": "succeeded" }),
+            )
+            .await;
+        log_store_error(stored, "run output", run_id);
     }
 
-    async fn update_run_failure(
+    /// Fails a run that no task will finish, e.g. one interrupted by shutdown
+    /// or a crash.
+    async fn fail_unfinished(&self, run_id: &str, error: ErrorResponse) {
+        let finished_at = Utc::now();
+        let failure = error.clone();
+        self.drop_pending_checkpoints(run_id).await;
+        let stored = self
+            .update_run_with_event(
+                run_id,
+                Box::new(move |run| {
+                    run.status = RunStatus::Failed;
+                    cancel_open_checkpoints(run);
+                    run.timing.wall_ms = run
+                        .timing
+                        .started_at
+                        .map(|started_at| (finished_at - started_at).num_milliseconds());
+                    run.timing.finished_at = Some(finished_at);
+                    run.error = Some(failure);
+                }),
+                EventType::RunFailed,
+                json!({ "status": "failed", "error": error }),
+            )
+            .await;
+        log_store_error(stored, "failed run", run_id);
+    }
+
+    async fn finish_failed(
         &self,
         run_id: &str,
         error: ErrorResponse,
@@ -414,16 +1682,282 @@ impl InMemoryRuntime {
         finished_at: chrono::DateTime<Utc>,
     ) {
         let wall_ms = (finished_at - started_at).num_milliseconds();
-        let mut runs = self.runs.write().await;
-        if let Some(record) = runs.get_mut(run_id) {
-            record.run.status = RunStatus::Failed;
-            record.run.error = Some(error);
-            record.run.timing.finished_at = Some(finished_at);
-            record.run.timing.wall_ms = Some(wall_ms);
+        self.drop_pending_checkpoints(run_id).await;
+        let stored = self
+            .update_run_with_event(
+                run_id,
+                Box::new(move |run| {
+                    run.status = RunStatus::Failed;
+                    cancel_open_checkpoints(run);
+                    run.error = Some(error);
+                    run.timing.finished_at = Some(finished_at);
+                    run.timing.wall_ms = Some(wall_ms);
+                }),
+                EventType::RunFailed,
+                json!({ "status": "failed" }),
+            )
+            .await;
+        log_store_error(stored, "run failure", run_id);
+    }
+}
+
+/// Step id of one attempt at running the workflow, so retries show up as
+/// separate steps.
+fn attempt_step_id(attempt: u32) -> String {
+    format!("workflow.run#{}", attempt)
+}
+
+/// Marks checkpoints still open on a run that is ending as `canceled`.
+fn cancel_open_checkpoints(run: &mut Run) {
+    for checkpoint in &mut run.checkpoints {
+        if checkpoint.status == HumanCheckpointStatus::Required {
+            checkpoint.status = HumanCheckpointStatus::Canceled;
         }
     }
 }
 
+/// Versions a caller scoped to `tenant_id` sees in the registry.
+fn visible_versions(versions: &[WorkflowEntry], tenant_id: Option<&str>) -> Vec<WorkflowEntry> {
+    versions
+        .iter()
+        .filter(|entry| entry.visible_to(tenant_id))
+        .cloned()
+        .collect()
+}
+
+/// Versions runs of `tenant_id` may use.
+fn usable_versions(versions: &[WorkflowEntry], tenant_id: Option<&str>) -> Vec<WorkflowEntry> {
+    versions
+        .iter()
+        .filter(|entry| entry.usable_by(tenant_id))
+        .cloned()
+        .collect()
+}
+
+/// Parses `1.2.3` or `v1.2.3`.
+fn parse_version(text: &str) -> Option<Version> {
+    Version::parse(text.strip_prefix('v').unwrap_or(text)).ok()
+}
+
+/// Picks the version a caller asked for: an exact version (`1.2.0` or
+/// `v1.2.0`), a semver range such as `^1.2`, or the latest active version when
+/// nothing (or `latest`) is requested. Ranges only match active versions.
+fn resolve_version<'a>(
+    versions: &'a [WorkflowEntry],
+    requested: Option<&str>,
+) -> Option<&'a WorkflowEntry> {
+    let latest_active = |matches: &dyn Fn(&WorkflowEntry) -> bool| {
+        versions
+            .iter()
+            .rev()
+            .find(|entry| entry.options.active && matches(entry))
+    };
+    let requested = match requested.map(str::trim) {
+        None | Some("") | Some("latest") => return latest_active(&|_| true),
+        Some(requested) => requested,
+    };
+    if let Some(entry) = versions
+        .iter()
+        .find(|entry| entry.version.as_deref() == Some(requested))
+    {
+        return Some(entry);
+    }
+    if let Some(version) = parse_version(requested) {
+        return versions
+            .iter()
+            .find(|entry| entry.semver.as_ref() == Some(&version));
+    }
+    let range = VersionReq::parse(requested).ok()?;
+    latest_active(&|entry| entry.semver.as_ref().is_some_and(|v| range.matches(v)))
+}
+
+fn compile_schema(workflow_name: &str, kind: &str, schema: &Value) -> Arc<JSONSchema> {
+    match JSONSchema::compile(schema) {
+        Ok(compiled) => Arc::new(compiled),
+        Err(err) => panic!("{} schema of {} is invalid: {}", kind, workflow_name, err),
+    }
+}
+
+/// Lists every violation of `schema` with the JSON pointer of the offending value.
+fn schema_violations(schema: &JSONSchema, value: &Value) -> Option<Vec<Value>> {
+    let errors = schema.validate(value).err()?;
+    Some(
+        errors
+            .map(|err| {
+                json!({
+                    "path": err.instance_path.to_string(),
+                    "schema_path": err.schema_path.to_string(),
+                    "message": err.to_string(),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn validate_input(
+    workflow_name: &str,
+    validator: &JSONSchema,
+    input: &Value,
+) -> Result<(), ErrorResponse> {
+    match schema_violations(validator, input) {
+        None => Ok(()),
+        Some(errors) => Err(ErrorResponse {
+            code: "input_validation_failed".to_string(),
+            message: format!("input does not match the input schema of {}", workflow_name),
+            retryable: false,
+            details: Some(json!({ "errors": errors })),
+        }),
+    }
+}
+
+/// Uses `metadata.trace_id` when the caller propagates one, otherwise starts a
+/// new W3C-style 32-hex trace id.
+fn requested_trace_id(metadata: Option<&serde_json::Map<String, Value>>) -> String {
+    metadata
+        .and_then(|metadata| metadata.get("trace_id"))
+        .and_then(Value::as_str)
+        .filter(|trace_id| !trace_id.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().simple().to_string())
+}
+
+/// Reads the tenant a run is queued and listed under from `metadata.tenant_id`.
+fn requested_tenant_id(metadata: Option<&serde_json::Map<String, Value>>) -> Option<String> {
+    metadata
+        .and_then(|metadata| metadata.get("tenant_id"))
+        .and_then(Value::as_str)
+        .filter(|tenant_id| !tenant_id.is_empty())
+        .map(str::to_string)
+}
+
+/// Reads the per-run timeout override from `metadata.timeout_ms`.
+fn requested_timeout(
+    metadata: Option<&serde_json::Map<String, Value>>,
+) -> Result<Option<Duration>, ErrorResponse> {
+    let Some(value) = metadata.and_then(|metadata| metadata.get("timeout_ms")) else {
+        return Ok(None);
+    };
+    match value.as_u64() {
+        Some(timeout_ms) if timeout_ms > 0 => Ok(Some(Duration::from_millis(timeout_ms))),
+        _ => Err(ErrorResponse {
+            code: "invalid_metadata".to_string(),
+            message: "metadata.timeout_ms must be a positive integer".to_string(),
+            retryable: false,
+            details: Some(json!({ "timeout_ms": value })),
+        }),
+    }
+}
+
+fn encode_run_cursor(cursor: &RunCursor) -> String {
+    let raw = format!(
+        "{}|{}",
+        cursor.created_at.to_rfc3339_opts(SecondsFormat::Nanos, true),
+        cursor.run_id
+    );
+    URL_SAFE_NO_PAD.encode(raw)
+}
+
+fn decode_run_cursor(cursor: &str) -> Result<RunCursor, ErrorResponse> {
+    let invalid = || ErrorResponse {
+        code: "invalid_cursor".to_string(),
+        message: "cursor is malformed".to_string(),
+        retryable: false,
+        details: None,
+    };
+    let raw = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+    let (created_at, run_id) = raw.split_once('|').ok_or_else(invalid)?;
+    let created_at = DateTime::parse_from_rfc3339(created_at).map_err(|_| invalid())?;
+    Ok(RunCursor {
+        created_at: created_at.with_timezone(&Utc),
+        run_id: run_id.to_string(),
+    })
+}
+
+/// Event cursors carry the id of the last event on the previous page.
+fn decode_event_cursor(cursor: &str) -> Result<String, ErrorResponse> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|raw| String::from_utf8(raw).ok())
+        .ok_or_else(|| ErrorResponse {
+            code: "invalid_cursor".to_string(),
+            message: "cursor is malformed".to_string(),
+            retryable: false,
+            details: None,
+        })
+}
+
+/// Parses `step.started,tool.called`.
+fn parse_event_types(types: &str) -> Result<Vec<EventType>, ErrorResponse> {
+    types
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            serde_json::from_value(Value::String(name.to_string())).map_err(|_| ErrorResponse {
+                code: "invalid_event_type".to_string(),
+                message: format!("unknown event type {:?}", name),
+                retryable: false,
+                details: None,
+            })
+        })
+        .collect()
+}
+
+/// Parses `key=value,key2=value2`. Keys are limited to `[A-Za-z0-9_.-/]`.
+fn parse_label_selector(selector: &str) -> Result<Vec<(String, String)>, ErrorResponse> {
+    selector
+        .split(',')
+        .map(str::trim)
+        .filter(|term| !term.is_empty())
+        .map(|term| {
+            let (key, value) = term
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .filter(|(key, _)| {
+                    !key.is_empty()
+                        && key
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || "_.-/".contains(c))
+                })
+                .ok_or_else(|| ErrorResponse {
+                    code: "invalid_label_selector".to_string(),
+                    message: format!("label selector term {:?} is not key=value", term),
+                    retryable: false,
+                    details: None,
+                })?;
+            Ok((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Logs a store write that failed with no caller to report it to, so the
+/// stored state falling behind the in-memory one does not go unnoticed.
+fn log_store_error<T, E: std::fmt::Display>(result: Result<T, E>, what: &str, id: &str) {
+    if let Err(err) = result {
+        tracing::error!(id, error = %err, "failed to store {}", what);
+    }
+}
+
+fn storage_error(err: StoreError) -> ErrorResponse {
+    ErrorResponse {
+        code: "storage_error".to_string(),
+        message: err.to_string(),
+        retryable: true,
+        details: None,
+    }
+}
+
+fn hash_request(req: &RunCreateRequest) -> String {
+    // Going through `Value` sorts object keys (including the `labels` map), so
+    // equal requests hash equally regardless of field order.
+    let encoded = serde_json::to_value(req)
+        .and_then(|value| serde_json::to_vec(&value))
+        .unwrap_or_default();
+    format!("{:x}", sha2::Sha256::digest(encoded))
+}
+
 fn hash_schemas(schemas: &HashMap<String, Value>) -> String {
     let mut items: Vec<(&String, &Value)> = schemas.iter().collect();
     items.sort_by(|a, b| a.0.cmp(b.0));
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::{json, Value};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::{AgentError, InMemoryRuntime};
+use crate::types::{Artifact, ArtifactRef, Cost, Event, EventType, HumanCheckpoint, Run};
+
+/// What a runner asks a human for through [`RunContext::request_checkpoint`].
+#[derive(Debug, Clone, Default)]
+pub struct CheckpointRequest {
+    pub title: Option<String>,
+    pub instructions: Option<String>,
+    /// JSON Schema of the input expected via `provide_input`, if any.
+    pub required_schema: Option<Value>,
+    /// When set, the wait fails with a fatal error if nobody resolves the
+    /// checkpoint in time.
+    pub expires_in: Option<Duration>,
+}
+
+/// Handle given to a runner for one attempt of a run. It exposes the run's
+/// identity and caller context, and publishes step, tool, LLM, artifact and
+/// checkpoint events on the run's event stream.
+#[derive(Clone)]
+pub struct RunContext {
+    runtime: InMemoryRuntime,
+    run_id: String,
+    trace_id: Option<String>,
+    tenant_id: Option<String>,
+    attempt: u32,
+    context: Option<Value>,
+    labels: HashMap<String, String>,
+    cancel: CancellationToken,
+}
+
+impl RunContext {
+    pub(super) fn new(
+        runtime: InMemoryRuntime,
+        run: &Run,
+        attempt: u32,
+        cancel: CancellationToken,
+    ) -> Self {
+        Self {
+            runtime,
+            run_id: run.run_id.clone(),
+            trace_id: run.trace_id.clone(),
+            tenant_id: run.tenant_id.clone(),
+            attempt,
+            context: run.context.clone(),
+            labels: run.labels.clone(),
+            cancel,
+        }
+    }
+
+    /// Context that is not attached to a stored run, for calling a runner
+    /// directly (e.g. from tests). Events and artifacts go nowhere.
+    pub fn detached() -> Self {
+        Self {
+            runtime: InMemoryRuntime::new(),
+            run_id: format!("run_{}", Uuid::new_v4()),
+            trace_id: None,
+            tenant_id: None,
+            attempt: 1,
+            context: None,
+            labels: HashMap::new(),
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub fn trace_id(&self) -> Option<&str> {
+        self.trace_id.as_deref()
+    }
+
+    pub fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+
+    /// 1-based attempt number; greater than 1 when the runtime is retrying.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// `context` from the create request.
+    pub fn context(&self) -> Option<&Value> {
+        self.context.as_ref()
+    }
+
+    pub fn labels(&self) -> &HashMap<String, String> {
+        &self.labels
+    }
+
+    /// Fires when the run is canceled or times out. The runtime drops the
+    /// runner future anyway; checking it lets runners stop between stages.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub async fn step_started(&self, step_id: &str, payload: Value) {
+        self.emit(EventType::StepStarted, Some(step_id), None, payload).await;
+    }
+
+    pub async fn step_completed(&self, step_id: &str, payload: Value) {
+        self.emit(EventType::StepCompleted, Some(step_id), None, payload).await;
+    }
+
+    pub async fn step_failed(&self, step_id: &str, payload: Value) {
+        self.emit(EventType::StepFailed, Some(step_id), None, payload).await;
+    }
+
+    /// Runs `step` between `step.started` and `step.completed`/`step.failed`
+    /// events. Fails without starting the step if the run was canceled.
+    pub async fn step<T, F>(&self, step_id: &str, step: F) -> Result<T, AgentError>
+    where
+        F: Future<Output = Result<T, AgentError>>,
+    {
+        if self.is_canceled() {
+            return Err(AgentError::fatal("run canceled"));
+        }
+        self.step_started(step_id, json!({})).await;
+        let result = step.await;
+        match &result {
+            Ok(_) => self.step_completed(step_id, json!({ "ok": true })).await,
+            Err(err) => {
+                let payload = json!({
+                    "ok": false,
+                    "retryable": err.is_retryable(),
+                    "error": err.message(),
+                });
+                self.step_failed(step_id, payload).await;
+            }
+        }
+        result
+    }
+
+    pub async fn tool_called(&self, step_id: &str, tool_name: &str, payload: Value) {
+        self.emit(EventType::ToolCalled, Some(step_id), Some(tool_name), payload)
+            .await;
+    }
+
+    pub async fn tool_result(&self, step_id: &str, tool_name: &str, payload: Value) {
+        self.emit(EventType::ToolResult, Some(step_id), Some(tool_name), payload)
+            .await;
+    }
+
+    pub async fn llm_request(&self, step_id: &str, payload: Value) {
+        self.emit(EventType::LlmRequest, Some(step_id), None, payload).await;
+    }
+
+    /// A `usage` object in `payload` is also added to the run's `cost`; see
+    /// [`Cost::from_usage`].
+    pub async fn llm_response(&self, step_id: &str, payload: Value) {
+        let cost = payload.get("usage").and_then(Cost::from_usage);
+        self.emit(EventType::LlmResponse, Some(step_id), None, payload).await;
+        if let Some(cost) = cost {
+            self.add_cost(cost).await;
+        }
+    }
+
+    /// Adds model usage to the run's `cost`. Call it once per model call.
+    pub async fn add_cost(&self, cost: Cost) {
+        self.runtime.add_cost(&self.run_id, cost).await;
+    }
+
+    /// Stores `artifact` right away and lists it on the run, so clients can
+    /// fetch it before the run finishes.
+    pub async fn add_artifact(&self, artifact: Artifact) -> ArtifactRef {
+        self.runtime.register_artifact(&self.run_id, artifact).await
+    }
+
+    /// Opens a human checkpoint, parks the run in `waiting_human` and waits
+    /// for its resolution. The run's timeout keeps counting while it waits.
+    pub async fn request_checkpoint(
+        &self,
+        request: CheckpointRequest,
+    ) -> Result<HumanCheckpoint, AgentError> {
+        self.runtime.open_checkpoint(&self.run_id, request).await
+    }
+
+    async fn emit(
+        &self,
+        event_type: EventType,
+        step_id: Option<&str>,
+        tool_name: Option<&str>,
+        payload: Value,
+    ) {
+        self.runtime
+            .publish(Event {
+                event_id: format!("evt_{}", Uuid::new_v4()),
+                ts: Utc::now(),
+                event_type,
+                run_id: self.run_id.clone(),
+                step_id: step_id.map(str::to_string),
+                tool_name: tool_name.map(str::to_string),
+                tenant_id: self.tenant_id.clone(),
+                payload,
+            })
+            .await;
+    }
+}
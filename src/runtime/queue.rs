@@ -0,0 +1,290 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use serde_json::json;
+use tokio::task::AbortHandle;
+use tokio_util::sync::CancellationToken;
+
+use super::{InMemoryRuntime, WorkflowEntry};
+use crate::types::{ErrorResponse, Run};
+
+/// Runtime-wide admission limits. Unset limits are unbounded; per-workflow
+/// limits live in [`super::WorkflowOptions::max_concurrency`].
+#[derive(Debug, Clone, Default)]
+pub struct ConcurrencyLimits {
+    /// Runs executing at once, including runs waiting on a human.
+    pub max_running: Option<usize>,
+    /// Runs waiting for a slot. Creating a run beyond this fails with
+    /// `queue_full`.
+    pub max_queued: Option<usize>,
+    /// Quota of every tenant that has no entry in `tenants`. Runs without a
+    /// tenant are only bound by the runtime-wide limits.
+    pub per_tenant: TenantQuota,
+    /// Quotas of individual tenants, keyed by tenant id.
+    pub tenants: HashMap<String, TenantQuota>,
+}
+
+impl ConcurrencyLimits {
+    fn quota(&self, tenant: &str) -> Option<&TenantQuota> {
+        if tenant.is_empty() {
+            return None;
+        }
+        Some(self.tenants.get(tenant).unwrap_or(&self.per_tenant))
+    }
+}
+
+/// Share of the runtime one tenant may use.
+#[derive(Debug, Clone, Default)]
+pub struct TenantQuota {
+    /// The tenant's runs executing at once; further runs stay queued.
+    pub max_running: Option<usize>,
+    /// The tenant's runs waiting for a slot. Creating a run beyond this fails
+    /// with `tenant_quota_exceeded`.
+    pub max_queued: Option<usize>,
+}
+
+/// A created run that has not been handed to a task yet.
+pub(super) struct QueuedRun {
+    pub(super) run: Run,
+    pub(super) entry: WorkflowEntry,
+    pub(super) cancel: CancellationToken,
+    pub(super) timeout: Option<Duration>,
+}
+
+impl QueuedRun {
+    fn workflow(&self) -> &str {
+        &self.run.workflow.name
+    }
+}
+
+/// Runs waiting for a slot, grouped by tenant. Tenants take turns so one
+/// tenant's batch cannot starve everybody else's runs.
+#[derive(Default)]
+pub(super) struct RunQueue {
+    running: usize,
+    running_by_workflow: HashMap<String, usize>,
+    running_by_tenant: HashMap<String, usize>,
+    // Tasks of admitted runs, keyed by run id.
+    tasks: HashMap<String, AbortHandle>,
+    // Waiting runs of each tenant, oldest first.
+    waiting: HashMap<String, VecDeque<QueuedRun>>,
+    // Tenants with waiting runs, in the order they get their next turn.
+    turns: VecDeque<String>,
+}
+
+impl RunQueue {
+    fn len(&self) -> usize {
+        self.waiting.values().map(VecDeque::len).sum()
+    }
+
+    pub(super) fn ensure_capacity(
+        &self,
+        limits: &ConcurrencyLimits,
+        run: &Run,
+    ) -> Result<(), ErrorResponse> {
+        if let Some(max_queued) = limits.max_queued
+            && self.len() >= max_queued
+        {
+            return Err(ErrorResponse {
+                code: "queue_full".to_string(),
+                message: format!("{} runs are already waiting; retry later", self.len()),
+                retryable: true,
+                details: Some(json!({ "max_queued": max_queued })),
+            });
+        }
+        let tenant = tenant_key(run);
+        let waiting = self.waiting.get(&tenant).map_or(0, VecDeque::len);
+        if let Some(max_queued) = limits.quota(&tenant).and_then(|quota| quota.max_queued)
+            && waiting >= max_queued
+        {
+            return Err(ErrorResponse {
+                code: "tenant_quota_exceeded".to_string(),
+                message: format!("tenant {} has {} runs waiting; retry later", tenant, waiting),
+                retryable: true,
+                details: Some(json!({ "tenant_id": tenant, "max_queued": max_queued })),
+            });
+        }
+        Ok(())
+    }
+
+    pub(super) fn push(&mut self, queued: QueuedRun) {
+        let tenant = tenant_key(&queued.run);
+        let waiting = self.waiting.entry(tenant.clone()).or_default();
+        if waiting.is_empty() {
+            self.turns.push_back(tenant);
+        }
+        waiting.push_back(queued);
+    }
+
+    pub(super) fn remove(&mut self, run_id: &str) -> Option<QueuedRun> {
+        let tenant = self
+            .waiting
+            .iter()
+            .find(|(_, waiting)| waiting.iter().any(|queued| queued.run.run_id == run_id))
+            .map(|(tenant, _)| tenant.clone())?;
+        let waiting = self.waiting.get_mut(&tenant)?;
+        let index = waiting.iter().position(|queued| queued.run.run_id == run_id)?;
+        let queued = waiting.remove(index);
+        if waiting.is_empty() {
+            self.waiting.remove(&tenant);
+            self.turns.retain(|turn| *turn != tenant);
+        }
+        queued
+    }
+
+    /// Takes the next run that fits the limits and counts it as running. The
+    /// tenant whose turn it is, unless it is at its quota, gives up its oldest
+    /// run whose workflow has a free slot, then moves to the back of the line.
+    fn admit(&mut self, limits: &ConcurrencyLimits) -> Option<QueuedRun> {
+        if limits.max_running.is_some_and(|max_running| self.running >= max_running) {
+            return None;
+        }
+        for turn in 0..self.turns.len() {
+            let tenant = self.turns[turn].clone();
+            let running = self.running_by_tenant.get(&tenant).copied().unwrap_or(0);
+            if let Some(quota) = limits.quota(&tenant)
+                && quota.max_running.is_some_and(|max_running| running >= max_running)
+            {
+                continue;
+            }
+            let Some(waiting) = self.waiting.get_mut(&tenant) else {
+                continue;
+            };
+            let running_by_workflow = &self.running_by_workflow;
+            let Some(index) = waiting.iter().position(|queued| {
+                let running = running_by_workflow.get(queued.workflow()).copied().unwrap_or(0);
+                queued
+                    .entry
+                    .options
+                    .max_concurrency
+                    .is_none_or(|max_concurrency| running < max_concurrency)
+            }) else {
+                continue;
+            };
+            let queued = waiting.remove(index)?;
+            self.turns.remove(turn);
+            if waiting.is_empty() {
+                self.waiting.remove(&tenant);
+            } else {
+                self.turns.push_back(tenant.clone());
+            }
+            self.running += 1;
+            *self.running_by_tenant.entry(tenant).or_default() += 1;
+            *self
+                .running_by_workflow
+                .entry(queued.workflow().to_string())
+                .or_default() += 1;
+            return Some(queued);
+        }
+        None
+    }
+
+    fn release(&mut self, run: &Run) {
+        self.tasks.remove(&run.run_id);
+        self.running = self.running.saturating_sub(1);
+        decrement(&mut self.running_by_workflow, &run.workflow.name);
+        decrement(&mut self.running_by_tenant, &tenant_key(run));
+    }
+
+    pub(super) fn is_idle(&self) -> bool {
+        self.running == 0
+    }
+
+    /// Aborts every admitted run's task and empties the queue. Returns the ids
+    /// of the runs that were stopped or still waiting.
+    pub(super) fn stop_all(&mut self) -> Vec<String> {
+        let mut run_ids = Vec::new();
+        for (run_id, task) in self.tasks.drain() {
+            task.abort();
+            run_ids.push(run_id);
+        }
+        for (_, waiting) in self.waiting.drain() {
+            run_ids.extend(waiting.into_iter().map(|queued| queued.run.run_id));
+        }
+        self.turns.clear();
+        self.running = 0;
+        self.running_by_workflow.clear();
+        self.running_by_tenant.clear();
+        run_ids
+    }
+
+    /// 1-based position of every waiting run if tenants keep taking turns.
+    /// Per-workflow limits can let a later run overtake, so this is an
+    /// estimate.
+    pub(super) fn positions(&self) -> HashMap<String, usize> {
+        let mut positions = HashMap::new();
+        let mut round = 0;
+        while positions.len() < self.len() {
+            for tenant in &self.turns {
+                if let Some(queued) = self.waiting.get(tenant).and_then(|waiting| waiting.get(round))
+                {
+                    positions.insert(queued.run.run_id.clone(), positions.len() + 1);
+                }
+            }
+            round += 1;
+        }
+        positions
+    }
+}
+
+fn tenant_key(run: &Run) -> String {
+    run.tenant_id.clone().unwrap_or_default()
+}
+
+fn decrement(counts: &mut HashMap<String, usize>, key: &str) {
+    if let Some(count) = counts.get_mut(key) {
+        *count = count.saturating_sub(1);
+        if *count == 0 {
+            counts.remove(key);
+        }
+    }
+}
+
+impl InMemoryRuntime {
+    pub fn with_concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Starts as many waiting runs as the limits allow. Nothing starts once
+    /// the runtime is shutting down.
+    pub(super) async fn dispatch_queued(&self) {
+        let mut queue = self.queue.lock().await;
+        if self.is_shutting_down() {
+            return;
+        }
+        while let Some(queued) = queue.admit(&self.limits) {
+            let run_id = queued.run.run_id.clone();
+            let task = tokio::spawn(self.clone().run_admitted(queued));
+            queue.tasks.insert(run_id, task.abort_handle());
+        }
+    }
+
+    // Boxed because finishing a run dispatches, and so spawns, the next one.
+    fn run_admitted(self, queued: QueuedRun) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            let run = queued.run.clone();
+            let run_id = run.run_id.clone();
+            self.execute_run(queued.run, queued.entry, queued.cancel, queued.timeout)
+                .await;
+            self.cancellations.write().await.remove(&run_id);
+            self.drop_pending_checkpoints(&run_id).await;
+            let mut queue = self.queue.lock().await;
+            queue.release(&run);
+            if queue.is_idle() {
+                self.queue_idle.notify_waiters();
+            }
+            drop(queue);
+            self.dispatch_queued().await;
+        })
+    }
+
+    /// Fills in `queue_position` on runs that are still waiting for a slot.
+    pub(super) async fn with_queue_positions(&self, runs: &mut [Run]) {
+        let positions = self.queue.lock().await.positions();
+        for run in runs {
+            run.queue_position = positions.get(&run.run_id).copied();
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use serde_json::json;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+use super::queue::QueuedRun;
+use super::{
+    log_store_error, requested_timeout, resolve_version, storage_error, usable_versions,
+    InMemoryRuntime, WorkflowEntry, MAX_PAGE_SIZE,
+};
+use crate::store::{RunCursor, RunQuery};
+use crate::types::{ErrorResponse, EventType, Run, RunStatus};
+
+/// What [`InMemoryRuntime::recover_runs`] did with the runs a previous process
+/// left unfinished.
+#[derive(Debug, Clone, Default)]
+pub struct RecoveryReport {
+    /// Runs that were still `queued` and are queued again.
+    pub requeued: Vec<String>,
+    /// Interrupted runs of idempotent workflows, queued to run from the start.
+    pub restarted: Vec<String>,
+    /// Interrupted runs failed with `process_restarted`, and runs whose
+    /// workflow is no longer registered.
+    pub failed: Vec<String>,
+}
+
+impl InMemoryRuntime {
+    /// Reconciles runs a previous process left in the store. Call it once at
+    /// startup, after registering workflows and before serving requests.
+    ///
+    /// `queued` runs are queued again. `running` and `waiting_human` runs lost
+    /// their task with the old process: runs of workflows registered with
+    /// `idempotent` start over, all others fail with the retryable
+    /// `process_restarted` error. Each run gets a `run.recovered` event saying
+    /// which happened.
+    pub async fn recover_runs(&self) -> Result<RecoveryReport, ErrorResponse> {
+        let mut report = RecoveryReport::default();
+        for status in [RunStatus::Queued, RunStatus::Running, RunStatus::WaitingHuman] {
+            // Oldest first, so recovered runs keep their place in the queue.
+            let mut runs = self.stored_runs(status).await?;
+            runs.reverse();
+            for run in runs {
+                self.recover_run(run, &mut report).await;
+            }
+        }
+        self.dispatch_queued().await;
+        Ok(report)
+    }
+
+    async fn stored_runs(&self, status: RunStatus) -> Result<Vec<Run>, ErrorResponse> {
+        let mut runs: Vec<Run> = Vec::new();
+        loop {
+            let page = self
+                .store
+                .list_runs(&RunQuery {
+                    status: Some(status.clone()),
+                    cursor: runs.last().map(RunCursor::of),
+                    limit: MAX_PAGE_SIZE,
+                    ..RunQuery::default()
+                })
+                .await
+                .map_err(storage_error)?;
+            let last_page = page.len() < MAX_PAGE_SIZE;
+            runs.extend(page);
+            if last_page {
+                return Ok(runs);
+            }
+        }
+    }
+
+    async fn recover_run(&self, run: Run, report: &mut RecoveryReport) {
+        let run_id = run.run_id.clone();
+        let previous = run.status.clone();
+        let Some(entry) = self.recovery_entry(&run).await else {
+            self.record_recovery(&run_id, &previous, "failed").await;
+            let error = ErrorResponse {
+                code: "workflow_not_found".to_string(),
+                message: format!(
+                    "workflow {} {} is no longer registered",
+                    run.workflow.name,
+                    run.workflow.version.as_deref().unwrap_or("latest")
+                ),
+                retryable: false,
+                details: None,
+            };
+            self.fail_unfinished(&run_id, error).await;
+            report.failed.push(run_id);
+            return;
+        };
+        if previous == RunStatus::Queued {
+            self.record_recovery(&run_id, &previous, "requeued").await;
+            self.requeue_recovered(run, entry).await;
+            report.requeued.push(run_id);
+        } else if entry.options.idempotent {
+            self.record_recovery(&run_id, &previous, "restarted").await;
+            let stored = self
+                .store
+                .update_run(
+                    &run_id,
+                    Box::new(|run| {
+                        run.status = RunStatus::Queued;
+                        run.timing.started_at = None;
+                    }),
+                )
+                .await;
+            log_store_error(stored.as_ref(), "restarted run", &run_id);
+            let run = stored.ok().flatten().unwrap_or(run);
+            self.requeue_recovered(run, entry).await;
+            report.restarted.push(run_id);
+        } else {
+            self.record_recovery(&run_id, &previous, "failed").await;
+            let error = ErrorResponse {
+                code: "process_restarted".to_string(),
+                message: "the runtime restarted while the run was in progress".to_string(),
+                retryable: true,
+                details: Some(json!({ "status": previous })),
+            };
+            self.fail_unfinished(&run_id, error).await;
+            report.failed.push(run_id);
+        }
+    }
+
+    async fn recovery_entry(&self, run: &Run) -> Option<WorkflowEntry> {
+        let workflows = self.workflows.read().await;
+        let versions = workflows.get(&run.workflow.name)?;
+        let versions = usable_versions(versions, run.tenant_id.as_deref());
+        resolve_version(&versions, run.workflow.version.as_deref()).cloned()
+    }
+
+    async fn record_recovery(&self, run_id: &str, previous: &RunStatus, action: &str) {
+        let (sender, _) = broadcast::channel(self.event_buffer);
+        self.senders
+            .write()
+            .await
+            .entry(run_id.to_string())
+            .or_insert(sender);
+        // Counted from the store on the next event.
+        self.event_counts
+            .lock()
+            .await
+            .entry(run_id.to_string())
+            .or_default();
+        self.emit_event(
+            run_id,
+            EventType::RunRecovered,
+            None,
+            json!({
+                "reason": "process_restarted",
+                "previous_status": previous,
+                "action": action,
+            }),
+        )
+        .await;
+    }
+
+    async fn requeue_recovered(&self, run: Run, entry: WorkflowEntry) {
+        let timeout = requested_timeout(run.metadata.as_ref())
+            .ok()
+            .flatten()
+            .or(entry.options.timeout);
+        let cancel = CancellationToken::new();
+        self.cancellations
+            .write()
+            .await
+            .insert(run.run_id.clone(), cancel.clone());
+        self.queue.lock().await.push(QueuedRun {
+            run,
+            entry,
+            cancel,
+            timeout,
+        });
+    }
+}
@@ -0,0 +1,193 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::json;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::{log_store_error, storage_error, InMemoryRuntime};
+use crate::store::EvictionReport;
+use crate::types::{ErrorResponse, Event, EventType};
+
+const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Bounds on what the runtime keeps around. Every limit is off by default.
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    /// Events stored per run. Once reached, one `events.truncated` marker is
+    /// stored and later events are only delivered to live subscribers; the
+    /// terminal run event is always stored.
+    pub max_events_per_run: Option<usize>,
+    /// How long a finished run, its events and its artifacts are kept.
+    pub run_ttl: Option<Duration>,
+    /// How long an artifact is kept. Artifacts of runs that are still active
+    /// or waiting on a human are never evicted.
+    pub artifact_ttl: Option<Duration>,
+    /// How often the background sweeper looks for expired records.
+    pub sweep_interval: Duration,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_events_per_run: None,
+            run_ttl: None,
+            artifact_ttl: None,
+            sweep_interval: DEFAULT_SWEEP_INTERVAL,
+        }
+    }
+}
+
+/// Totals since the runtime started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionMetrics {
+    pub runs_evicted: u64,
+    pub artifacts_evicted: u64,
+    /// Events that were delivered live but not stored because their run hit
+    /// `max_events_per_run`.
+    pub events_dropped: u64,
+}
+
+impl RetentionMetrics {
+    /// Renders the counters in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let counters = [
+            ("runs_evicted", "Finished runs removed by retention.", self.runs_evicted),
+            ("artifacts_evicted", "Artifacts removed by retention.", self.artifacts_evicted),
+            ("events_dropped", "Events not stored past max_events_per_run.", self.events_dropped),
+        ];
+        let mut text = String::new();
+        for (name, help, value) in counters {
+            text.push_str(&format!(
+                "# HELP agent_runtime_{name}_total {help}\n\
+                 # TYPE agent_runtime_{name}_total counter\n\
+                 agent_runtime_{name}_total {value}\n"
+            ));
+        }
+        text
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct RetentionCounters {
+    runs_evicted: AtomicU64,
+    artifacts_evicted: AtomicU64,
+    events_dropped: AtomicU64,
+}
+
+impl InMemoryRuntime {
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = policy;
+        self
+    }
+
+    pub fn retention_metrics(&self) -> RetentionMetrics {
+        let counters = &self.retention_counters;
+        RetentionMetrics {
+            runs_evicted: counters.runs_evicted.load(Ordering::Relaxed),
+            artifacts_evicted: counters.artifacts_evicted.load(Ordering::Relaxed),
+            events_dropped: counters.events_dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Runs one eviction pass with the configured TTLs. The background
+    /// sweeper calls this on every tick.
+    pub async fn sweep_expired(&self) -> Result<EvictionReport, ErrorResponse> {
+        let now = Utc::now();
+        // A TTL too large to subtract from now never expires anything.
+        let cutoff = |ttl: Option<Duration>| {
+            ttl.and_then(|ttl| chrono::Duration::from_std(ttl).ok())
+                .and_then(|ttl| now.checked_sub_signed(ttl))
+        };
+        let report = self
+            .store
+            .evict(cutoff(self.retention.run_ttl), cutoff(self.retention.artifact_ttl))
+            .await
+            .map_err(storage_error)?;
+        if !report.run_ids.is_empty() {
+            let mut senders = self.senders.write().await;
+            let mut tenants = self.run_tenants.write().await;
+            let mut counts = self.event_counts.lock().await;
+            for run_id in &report.run_ids {
+                senders.remove(run_id);
+                tenants.remove(run_id);
+                counts.remove(run_id);
+            }
+        }
+        let counters = &self.retention_counters;
+        counters
+            .runs_evicted
+            .fetch_add(report.run_ids.len() as u64, Ordering::Relaxed);
+        counters
+            .artifacts_evicted
+            .fetch_add(report.artifacts as u64, Ordering::Relaxed);
+        Ok(report)
+    }
+
+    /// Starts a task that calls [`Self::sweep_expired`] every
+    /// `sweep_interval`. Abort the handle to stop it.
+    pub fn spawn_retention_sweeper(&self) -> JoinHandle<()> {
+        let runtime = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(runtime.retention.sweep_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                // A failed pass is retried on the next tick.
+                let _ = runtime.sweep_expired().await;
+            }
+        })
+    }
+
+    /// Appends `event` to the store, honouring `max_events_per_run` while the
+    /// run is live. Returns the truncation marker when one was stored in the
+    /// event's place.
+    pub(super) async fn store_event(&self, event: &Event) -> Option<Event> {
+        let count = match self.retention.max_events_per_run {
+            Some(_) => self.event_counts.lock().await.get(&event.run_id).cloned(),
+            None => None,
+        };
+        // Runs that already finished (or were evicted) are no longer counted,
+        // so a late event does not bring their entry back.
+        let (Some(max_events), Some(count)) = (self.retention.max_events_per_run, count) else {
+            let stored = self.store.append_event(event.clone()).await;
+            log_store_error(stored, "event", &event.event_id);
+            return None;
+        };
+        // Counting and appending must not interleave with another publish for
+        // the same run, or two events could both take the last slot.
+        let mut count = count.lock().await;
+        let stored = match *count {
+            Some(stored) => stored,
+            None => self.store.count_events(&event.run_id).await.unwrap_or(0),
+        };
+        if event.event_type.is_terminal() || stored + 1 < max_events {
+            let appended = self.store.append_event(event.clone()).await;
+            *count = Some(stored + usize::from(appended.is_ok()));
+            log_store_error(appended, "event", &event.event_id);
+            return None;
+        }
+        *count = Some(stored);
+        self.retention_counters
+            .events_dropped
+            .fetch_add(1, Ordering::Relaxed);
+        if stored + 1 > max_events {
+            return None;
+        }
+        let marker = Event {
+            event_id: format!("evt_{}", Uuid::new_v4()),
+            ts: event.ts,
+            event_type: EventType::EventsTruncated,
+            run_id: event.run_id.clone(),
+            step_id: None,
+            tool_name: None,
+            tenant_id: event.tenant_id.clone(),
+            payload: json!({ "max_events": max_events }),
+        };
+        let appended = self.store.append_event(marker.clone()).await;
+        *count = Some(stored + usize::from(appended.is_ok()));
+        log_store_error(appended, "event", &marker.event_id);
+        Some(marker)
+    }
+}
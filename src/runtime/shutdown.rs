@@ -0,0 +1,103 @@
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use serde_json::json;
+
+use super::{log_store_error, InMemoryRuntime};
+use crate::types::{ErrorResponse, EventType, RunStatus};
+
+/// What [`InMemoryRuntime::shutdown`] did with runs that outlived the drain.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownReport {
+    /// Runs left `queued` in a durable store, to be picked up after a restart.
+    pub requeued: Vec<String>,
+    /// Runs failed with `shutdown` because the store does not outlive the
+    /// process.
+    pub failed: Vec<String>,
+}
+
+impl InMemoryRuntime {
+    /// Whether [`Self::shutdown`] has been called. New runs are rejected with
+    /// `shutting_down` from then on.
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Stops admitting runs, publishes a `runtime.shutdown` event to every
+    /// unfinished run, and waits up to `drain` for running ones to finish.
+    /// Runs still unfinished afterwards are stopped: a durable store keeps
+    /// them `queued`, otherwise they fail with the retryable `shutdown` error.
+    /// Live event streams end once this returns.
+    pub async fn shutdown(&self, drain: Duration) -> ShutdownReport {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        let unfinished: Vec<String> = self.cancellations.read().await.keys().cloned().collect();
+        for run_id in &unfinished {
+            self.emit_event(
+                run_id,
+                EventType::RuntimeShutdown,
+                None,
+                json!({ "drain_ms": drain.as_millis() as u64 }),
+            )
+            .await;
+        }
+
+        let drained = async {
+            loop {
+                // Registered before checking so a release in between is not missed.
+                let idle = self.queue_idle.notified();
+                if self.queue.lock().await.is_idle() {
+                    break;
+                }
+                idle.await;
+            }
+        };
+        let _ = tokio::time::timeout(drain, drained).await;
+
+        let interrupted = self.queue.lock().await.stop_all();
+        let mut report = ShutdownReport::default();
+        for run_id in interrupted {
+            self.cancellations.write().await.remove(&run_id);
+            self.drop_pending_checkpoints(&run_id).await;
+            let finished = self
+                .get_run(&run_id)
+                .await
+                .is_none_or(|run| run.status.is_terminal());
+            if finished {
+                continue;
+            }
+            if self.store.is_durable() {
+                self.requeue_interrupted(&run_id).await;
+                report.requeued.push(run_id);
+            } else {
+                let error = ErrorResponse {
+                    code: "shutdown".to_string(),
+                    message: "runtime shut down before the run finished".to_string(),
+                    retryable: true,
+                    details: None,
+                };
+                self.fail_unfinished(&run_id, error).await;
+                report.failed.push(run_id);
+            }
+        }
+        // Dropping the channels ends every open event stream.
+        self.senders.write().await.clear();
+        report
+    }
+
+    /// Puts an interrupted run back to `queued`, with a `runtime.shutdown`
+    /// event saying so, for recovery to pick up after a restart.
+    async fn requeue_interrupted(&self, run_id: &str) {
+        let stored = self
+            .update_run_with_event(
+                run_id,
+                Box::new(|run| {
+                    run.status = RunStatus::Queued;
+                    run.timing.started_at = None;
+                }),
+                EventType::RuntimeShutdown,
+                json!({ "requeued": true, "status": RunStatus::Queued }),
+            )
+            .await;
+        log_store_error(stored, "requeued run", run_id);
+    }
+}
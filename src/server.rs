@@ -1,59 +1,422 @@
-use std::sync::Arc;
+use std::convert::Infallible;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 use axum::{
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        Extension, Path, Query, Request, State,
+    },
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Sse},
-    routing::{get, post},
+    middleware,
+    routing::{get, post, Route},
     Json, Router,
 };
-use axum::response::sse::Event as SseEvent;
+use axum::response::sse::{Event as SseEvent, KeepAlive};
 use futures::StreamExt;
-use tokio_stream::wrappers::BroadcastStream;
+use tower_layer::Layer;
+use tower_service::Service;
 
+use crate::auth::{require_api_key, AuthConfig, Authenticator, Principal};
+use crate::grpc::proto::agent_runtime_server::AgentRuntimeServer;
+use crate::grpc::{grpc_service_with_hooks, GrpcService};
 use crate::runtime::InMemoryRuntime;
 use crate::types::{
-    Artifact, Event, EventListResponse, ErrorResponse, Run, RunCreateRequest, RunCreateResponse,
-    SchemaBundle, Workflow, WorkflowListResponse,
+    Artifact, CheckpointDecisionRequest, CheckpointInputRequest, ErrorResponse, Event,
+    EventListQuery, HumanCheckpoint, Run, RunCreateRequest, RunCreateResponse,
+    RunListQuery, RunListResponse, SchemaBundle, Workflow, WorkflowListResponse,
+    WorkflowVersionQuery,
 };
 
+mod ws;
+
+/// Idle SSE streams send a `: keep-alive` comment this often so proxies keep
+/// them open.
+const SSE_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// The OpenAPI document the routes implement.
+pub const OPENAPI_YAML: &str = include_str!("../openapi/agent-runtime.yaml");
+
+/// State of every route, including those added with [`RouterBuilder::routes`].
 #[derive(Clone)]
 pub struct AppState {
     pub runtime: Arc<InMemoryRuntime>,
+    create_run_hooks: Arc<Vec<CreateRunHook>>,
 }
 
+/// Adjusts a create request before it is checked against the caller's key and
+/// handed to the runtime; the caller is `None` without authentication. An
+/// error rejects the request with it.
+pub type CreateRunHook = Arc<
+    dyn Fn(&mut RunCreateRequest, Option<&Principal>) -> Result<(), ErrorResponse> + Send + Sync,
+>;
+
+type LayerFn = Box<dyn FnOnce(Router) -> Router + Send>;
+
+/// Resolves on SIGTERM or Ctrl-C. Pass it, followed by
+/// [`InMemoryRuntime::shutdown`], to `axum::serve(..).with_graceful_shutdown`.
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+}
+
+/// Routes without authentication, for tests and trusted networks.
 pub fn router(runtime: Arc<InMemoryRuntime>) -> Router {
-    let state = AppState { runtime };
+    RouterBuilder::new(runtime).build()
+}
+
+/// Routes where every `/v1` request needs a bearer token from `auth` with the
+/// route's scope. `/metrics` and the OpenAPI document stay open.
+pub fn router_with_auth(runtime: Arc<InMemoryRuntime>, auth: AuthConfig) -> Router {
+    RouterBuilder::new(runtime).auth(auth).build()
+}
+
+/// Assembles the HTTP API for an app: the `/v1` routes, `/metrics` and the
+/// OpenAPI document, plus whatever routes, hooks and middleware the app adds.
+pub struct RouterBuilder {
+    runtime: Arc<InMemoryRuntime>,
+    auth: Option<AuthConfig>,
+    prefix: String,
+    create_run_hooks: Vec<CreateRunHook>,
+    routes: Router<AppState>,
+    layers: Vec<LayerFn>,
+}
+
+impl RouterBuilder {
+    pub fn new(runtime: Arc<InMemoryRuntime>) -> Self {
+        Self {
+            runtime,
+            auth: None,
+            prefix: String::new(),
+            create_run_hooks: Vec::new(),
+            routes: Router::new(),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Requires a bearer token from `auth` on the `/v1` routes, with the
+    /// route's scope, and on routes added with [`Self::routes`].
+    pub fn auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Mounts every route under `prefix`, e.g. `/agent-runtime`.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        let prefix = prefix.trim_end_matches('/');
+        self.prefix = if prefix.is_empty() || prefix.starts_with('/') {
+            prefix.to_string()
+        } else {
+            format!("/{}", prefix)
+        };
+        self
+    }
+
+    /// Runs `hook` on every `POST /v1/runs` body, and on every gRPC
+    /// `CreateRun` of [`Self::grpc_service`], in the order hooks were added.
+    /// See [`CreateRunHook`].
+    pub fn on_create_run<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut RunCreateRequest, Option<&Principal>) -> Result<(), ErrorResponse>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.create_run_hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Adds app routes next to the API. They get [`AppState`], and with
+    /// [`Self::auth`] they need a valid key but no particular scope.
+    pub fn routes(mut self, routes: Router<AppState>) -> Self {
+        self.routes = self.routes.merge(routes);
+        self
+    }
+
+    /// Wraps every route, open ones included, in `layer` (CORS, request ids,
+    /// tracing). As with [`Router::layer`], the layer added last runs first.
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + 'static,
+        L::Service: Service<Request> + Clone + Send + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        self.layers.push(Box::new(move |router: Router| router.layer(layer)));
+        self
+    }
+
+    /// The gRPC service for the same runtime, with the keys and create-run
+    /// hooks added so far. Call it before [`Self::build`].
+    pub fn grpc_service(&self) -> AgentRuntimeServer<GrpcService> {
+        grpc_service_with_hooks(
+            self.runtime.clone(),
+            self.auth.clone(),
+            Arc::new(self.create_run_hooks.clone()),
+        )
+    }
+
+    pub fn build(self) -> Router {
+        let mut api = api_routes().merge(self.routes);
+        if let Some(auth) = self.auth {
+            let authenticator = Authenticator::new(auth).mounted_at(&self.prefix);
+            api = api.route_layer(middleware::from_fn_with_state(authenticator, require_api_key));
+        }
+        let routes = api
+            .route("/metrics", get(get_metrics))
+            .route("/openapi.yaml", get(openapi_yaml))
+            .route("/openapi.json", get(openapi_json))
+            .with_state(AppState {
+                runtime: self.runtime,
+                create_run_hooks: Arc::new(self.create_run_hooks),
+            });
+        let mut router = if self.prefix.is_empty() {
+            routes
+        } else {
+            Router::new().nest(&self.prefix, routes)
+        };
+        for layer in self.layers {
+            router = layer(router);
+        }
+        router
+    }
+}
+
+fn api_routes() -> Router<AppState> {
     Router::new()
-        .route("/v1/runs", post(create_run))
-        .route("/v1/runs/:run_id", get(get_run))
+        .route("/v1/runs", post(create_run).get(list_runs))
+        .route("/v1/runs/:run_id", get(get_run).delete(cancel_run))
+        .route("/v1/runs/:run_id/cancel", post(cancel_run))
         .route("/v1/runs/:run_id/events", get(get_events))
+        .route("/v1/runs/:run_id/ws", get(ws::run_socket))
+        .route("/v1/ws", get(ws::multiplexed_socket))
+        .route(
+            "/v1/runs/:run_id/human/:checkpoint_id/approve",
+            post(approve_checkpoint),
+        )
+        .route(
+            "/v1/runs/:run_id/human/:checkpoint_id/reject",
+            post(reject_checkpoint),
+        )
+        .route(
+            "/v1/runs/:run_id/human/:checkpoint_id/provide_input",
+            post(provide_checkpoint_input),
+        )
         .route("/v1/artifacts/:artifact_id", get(get_artifact))
         .route("/v1/workflows", get(list_workflows))
         .route("/v1/workflows/:name", get(get_workflow))
         .route("/v1/workflows/:name/schemas", get(get_workflow_schemas))
-        .with_state(state)
 }
 
 async fn create_run(
     State(state): State<AppState>,
-    Json(req): Json<RunCreateRequest>,
+    principal: Option<Extension<Principal>>,
+    headers: HeaderMap,
+    body: Result<Json<RunCreateRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let Json(mut req) = body.map_err(invalid_body)?;
+    let caller = principal.as_ref().map(|Extension(principal)| principal);
+    for hook in state.create_run_hooks.iter() {
+        hook(&mut req, caller).map_err(|err| (error_status(&err), Json(err)))?;
+    }
+    let created_by = match &principal {
+        Some(Extension(principal)) => {
+            principal
+                .scope_create_request(&mut req)
+                .map_err(|err| (error_status(&err), Json(err)))?;
+            Some(principal.key_id.as_str())
+        }
+        None => None,
+    };
+    let idempotency_key = headers
+        .get("idempotency-key")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string());
+    let (run, replayed) = match idempotency_key {
+        Some(key) => {
+            state
+                .runtime
+                .create_run_with_idempotency_as(&key, req, created_by)
+                .await
+        }
+        None => state
+            .runtime
+            .create_run_as(req, created_by)
+            .await
+            .map(|run| (run, false)),
+    }
+    .map_err(|err| (error_status(&err), Json(err)))?;
+    let mut response = (StatusCode::CREATED, Json(RunCreateResponse { run })).into_response();
+    if replayed {
+        response
+            .headers_mut()
+            .insert("idempotent-replayed", HeaderValue::from_static("true"));
+    }
+    Ok(response)
+}
+
+async fn list_runs(
+    State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
+    query: Result<Query<RunListQuery>, QueryRejection>,
+) -> Result<Json<RunListResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let Query(mut query) = query.map_err(|rejection| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                code: "invalid_query".to_string(),
+                message: rejection.body_text(),
+                retryable: false,
+                details: None,
+            }),
+        )
+    })?;
+    if let Some(Extension(principal)) = &principal {
+        principal
+            .scope_run_query(&mut query)
+            .map_err(|err| (error_status(&err), Json(err)))?;
+    }
+    let page = state
+        .runtime
+        .list_runs(query)
+        .await
+        .map_err(|err| (error_status(&err), Json(err)))?;
+    Ok(Json(page))
+}
+
+async fn cancel_run(
+    State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
+    Path(run_id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    visible_run(&state, &principal, &run_id).await?;
     let run = state
         .runtime
-        .create_run(req)
+        .cancel_run(&run_id)
+        .await
+        .map_err(|err| (error_status(&err), Json(err)))?;
+    Ok((StatusCode::ACCEPTED, Json(run)))
+}
+
+async fn approve_checkpoint(
+    State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
+    Path((run_id, checkpoint_id)): Path<(String, String)>,
+    body: Option<Json<CheckpointDecisionRequest>>,
+) -> Result<Json<HumanCheckpoint>, (StatusCode, Json<ErrorResponse>)> {
+    visible_run(&state, &principal, &run_id).await?;
+    let note = body.and_then(|Json(body)| body.note);
+    state
+        .runtime
+        .approve_checkpoint(&run_id, &checkpoint_id, note)
         .await
-        .map_err(|err| (StatusCode::BAD_REQUEST, Json(err)))?;
-    Ok((StatusCode::CREATED, Json(RunCreateResponse { run })))
+        .map(Json)
+        .map_err(|err| (error_status(&err), Json(err)))
+}
+
+async fn reject_checkpoint(
+    State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
+    Path((run_id, checkpoint_id)): Path<(String, String)>,
+    body: Option<Json<CheckpointDecisionRequest>>,
+) -> Result<Json<HumanCheckpoint>, (StatusCode, Json<ErrorResponse>)> {
+    visible_run(&state, &principal, &run_id).await?;
+    let note = body.and_then(|Json(body)| body.note);
+    state
+        .runtime
+        .reject_checkpoint(&run_id, &checkpoint_id, note)
+        .await
+        .map(Json)
+        .map_err(|err| (error_status(&err), Json(err)))
+}
+
+async fn provide_checkpoint_input(
+    State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
+    Path((run_id, checkpoint_id)): Path<(String, String)>,
+    body: Result<Json<CheckpointInputRequest>, JsonRejection>,
+) -> Result<Json<HumanCheckpoint>, (StatusCode, Json<ErrorResponse>)> {
+    visible_run(&state, &principal, &run_id).await?;
+    let Json(body) = body.map_err(invalid_body)?;
+    state
+        .runtime
+        .provide_checkpoint_input(&run_id, &checkpoint_id, body.input, body.note)
+        .await
+        .map(Json)
+        .map_err(|err| (error_status(&err), Json(err)))
+}
+
+/// 400 `invalid_request` for a body that is missing, not JSON, or not the
+/// route's request shape.
+fn invalid_body(rejection: JsonRejection) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            code: "invalid_request".to_string(),
+            message: rejection.body_text(),
+            retryable: false,
+            details: None,
+        }),
+    )
+}
+
+fn error_status(err: &ErrorResponse) -> StatusCode {
+    match err.code.as_str() {
+        "unauthorized" => StatusCode::UNAUTHORIZED,
+        "forbidden" => StatusCode::FORBIDDEN,
+        "not_found" => StatusCode::NOT_FOUND,
+        "run_not_cancelable"
+        | "idempotency_conflict"
+        | "checkpoint_already_resolved"
+        | "checkpoint_not_pending" => StatusCode::CONFLICT,
+        "queue_full" | "tenant_quota_exceeded" => StatusCode::TOO_MANY_REQUESTS,
+        "shutting_down" => StatusCode::SERVICE_UNAVAILABLE,
+        "storage_error" => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::BAD_REQUEST,
+    }
 }
 
 async fn get_run(
     State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
     Path(run_id): Path<String>,
 ) -> Result<Json<Run>, (StatusCode, Json<ErrorResponse>)> {
-    match state.runtime.get_run(&run_id).await {
-        Some(run) => Ok(Json(run)),
-        None => Err((
+    visible_run(&state, &principal, &run_id).await.map(Json)
+}
+
+/// Loads a run the caller may see. Runs of other tenants are reported as not
+/// found, so tenant keys cannot probe for them; runs of workflows outside the
+/// key's list are forbidden.
+async fn visible_run(
+    state: &AppState,
+    principal: &Option<Extension<Principal>>,
+    run_id: &str,
+) -> Result<Run, (StatusCode, Json<ErrorResponse>)> {
+    match state.runtime.get_run(run_id).await {
+        Some(run) if allows_tenant(principal, run.tenant_id.as_deref()) => {
+            if let Some(Extension(principal)) = principal {
+                principal
+                    .check_workflow(&run.workflow.name)
+                    .map_err(|err| (error_status(&err), Json(err)))?;
+            }
+            Ok(run)
+        }
+        _ => Err((
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
                 code: "not_found".to_string(),
@@ -65,51 +428,68 @@ async fn get_run(
     }
 }
 
+fn allows_tenant(principal: &Option<Extension<Principal>>, tenant_id: Option<&str>) -> bool {
+    principal
+        .as_ref()
+        .is_none_or(|Extension(principal)| principal.allows_tenant(tenant_id))
+}
+
 async fn get_events(
     State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
     Path(run_id): Path<String>,
+    query: Result<Query<EventListQuery>, QueryRejection>,
     headers: HeaderMap,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let Query(query) = query.map_err(|rejection| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                code: "invalid_query".to_string(),
+                message: rejection.body_text(),
+                retryable: false,
+                details: None,
+            }),
+        )
+    })?;
+    visible_run(&state, &principal, &run_id).await?;
+    let not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                code: "not_found".to_string(),
+                message: "run not found".to_string(),
+                retryable: false,
+                details: None,
+            }),
+        )
+    };
     let accept = headers
         .get(axum::http::header::ACCEPT)
         .and_then(|v| v.to_str().ok())
         .unwrap_or("");
     if accept.contains("text/event-stream") {
-        let receiver = state.runtime.subscribe_events(&run_id).await.ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    code: "not_found".to_string(),
-                    message: "run not found".to_string(),
-                    retryable: false,
-                    details: None,
-                }),
-            )
-        })?;
-        let stream = BroadcastStream::new(receiver).filter_map(|result| async move {
-            match result {
-                Ok(event) => Some(Ok::<SseEvent, std::convert::Infallible>(to_sse_event(event))),
-                Err(_) => None,
-            }
-        });
-        Ok(Sse::new(stream).into_response())
+        let last_event_id = headers
+            .get("last-event-id")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let after = last_event_id.or(query.after);
+        let events = state
+            .runtime
+            .event_stream(&run_id, after.as_deref())
+            .await
+            .ok_or_else(not_found)?;
+        let stream =
+            events.map(|event| Ok::<SseEvent, std::convert::Infallible>(to_sse_event(event)));
+        let keep_alive = KeepAlive::new().interval(SSE_KEEP_ALIVE).text("keep-alive");
+        Ok(Sse::new(stream).keep_alive(keep_alive).into_response())
     } else {
-        let events = state.runtime.list_events(&run_id).await.ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    code: "not_found".to_string(),
-                    message: "run not found".to_string(),
-                    retryable: false,
-                    details: None,
-                }),
-            )
-        })?;
-        Ok(Json(EventListResponse {
-            data: events,
-            next_cursor: None,
-        })
-        .into_response())
+        let page = state
+            .runtime
+            .list_events_page(&run_id, query)
+            .await
+            .map_err(|err| (error_status(&err), Json(err)))?;
+        Ok(Json(page).into_response())
     }
 }
 
@@ -119,16 +499,19 @@ fn to_sse_event(event: Event) -> SseEvent {
         .and_then(|s| s.strip_prefix('"').and_then(|s| s.strip_suffix('"')).map(|s| s.to_string()))
         .unwrap_or_else(|| "event".to_string());
     let data = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
-    SseEvent::default().event(event_name).data(data)
+    SseEvent::default()
+        .id(event.event_id)
+        .event(event_name)
+        .data(data)
 }
 
 async fn get_artifact(
     State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
     Path(artifact_id): Path<String>,
 ) -> Result<Json<Artifact>, (StatusCode, Json<ErrorResponse>)> {
-    match state.runtime.get_artifact(&artifact_id).await {
-        Some(artifact) => Ok(Json(artifact)),
-        None => Err((
+    let not_found = || {
+        (
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
                 code: "not_found".to_string(),
@@ -136,14 +519,34 @@ async fn get_artifact(
                 retryable: false,
                 details: None,
             }),
-        )),
+        )
+    };
+    let artifact = match state.runtime.get_artifact(&artifact_id).await {
+        Some(artifact) if allows_tenant(&principal, artifact.tenant_id.as_deref()) => artifact,
+        _ => return Err(not_found()),
+    };
+    if principal
+        .as_ref()
+        .is_some_and(|Extension(principal)| principal.workflows.is_some())
+    {
+        // The allow-list applies to the workflow of the run that made it.
+        let run_id = artifact.run_id.as_deref().ok_or_else(not_found)?;
+        visible_run(&state, &principal, run_id).await?;
     }
+    Ok(Json(artifact))
 }
 
 async fn list_workflows(
     State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
 ) -> Result<Json<WorkflowListResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let data = state.runtime.list_workflows().await;
+    let tenant_id = principal
+        .as_ref()
+        .and_then(|Extension(principal)| principal.tenant_id.as_deref());
+    let mut data = state.runtime.list_workflows_for_tenant(tenant_id).await;
+    if let Some(Extension(principal)) = principal {
+        data.retain(|workflow| principal.allows_workflow(&workflow.name));
+    }
     Ok(Json(WorkflowListResponse {
         data,
         next_cursor: None,
@@ -152,9 +555,32 @@ async fn list_workflows(
 
 async fn get_workflow(
     State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
     Path(name): Path<String>,
+    query: Result<Query<WorkflowVersionQuery>, QueryRejection>,
 ) -> Result<Json<Workflow>, (StatusCode, Json<ErrorResponse>)> {
-    match state.runtime.get_workflow(&name).await {
+    let Query(query) = query.map_err(|rejection| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                code: "invalid_query".to_string(),
+                message: rejection.body_text(),
+                retryable: false,
+                details: None,
+            }),
+        )
+    })?;
+    if let Some(Extension(principal)) = &principal {
+        principal
+            .check_workflow(&name)
+            .map_err(|err| (error_status(&err), Json(err)))?;
+    }
+    let tenant_id = principal.and_then(|Extension(principal)| principal.tenant_id);
+    match state
+        .runtime
+        .get_workflow_for_tenant(&name, query.version.as_deref(), tenant_id.as_deref())
+        .await
+    {
         Some(workflow) => Ok(Json(workflow)),
         None => Err((
             StatusCode::NOT_FOUND,
@@ -170,9 +596,32 @@ async fn get_workflow(
 
 async fn get_workflow_schemas(
     State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
     Path(name): Path<String>,
+    query: Result<Query<WorkflowVersionQuery>, QueryRejection>,
 ) -> Result<Json<SchemaBundle>, (StatusCode, Json<ErrorResponse>)> {
-    match state.runtime.get_workflow_schemas(&name).await {
+    let Query(query) = query.map_err(|rejection| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                code: "invalid_query".to_string(),
+                message: rejection.body_text(),
+                retryable: false,
+                details: None,
+            }),
+        )
+    })?;
+    if let Some(Extension(principal)) = &principal {
+        principal
+            .check_workflow(&name)
+            .map_err(|err| (error_status(&err), Json(err)))?;
+    }
+    let tenant_id = principal.and_then(|Extension(principal)| principal.tenant_id);
+    match state
+        .runtime
+        .get_workflow_schemas_for_tenant(&name, query.version.as_deref(), tenant_id.as_deref())
+        .await
+    {
         Some(bundle) => Ok(Json(bundle)),
         None => Err((
             StatusCode::NOT_FOUND,
@@ -185,3 +634,24 @@ async fn get_workflow_schemas(
         )),
     }
 }
+
+/// Serves [`OPENAPI_YAML`] as written.
+pub async fn openapi_yaml() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/yaml")], OPENAPI_YAML)
+}
+
+/// Serves [`OPENAPI_YAML`] converted to JSON.
+pub async fn openapi_json() -> Json<serde_json::Value> {
+    static DOCUMENT: OnceLock<serde_json::Value> = OnceLock::new();
+    let document = DOCUMENT.get_or_init(|| {
+        serde_yaml::from_str(OPENAPI_YAML).expect("openapi/agent-runtime.yaml is valid YAML")
+    });
+    Json(document.clone())
+}
+
+async fn get_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.runtime.retention_metrics().to_prometheus(),
+    )
+}
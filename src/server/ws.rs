@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{
+        rejection::QueryRejection,
+        ws::{
+            close_code, rejection::WebSocketUpgradeRejection, CloseFrame, Message, WebSocket,
+            WebSocketUpgrade,
+        },
+        Extension, Path, Query, State,
+    },
+    http::StatusCode,
+    response::Response,
+    Json,
+};
+use futures::stream::BoxStream;
+use serde::Deserialize;
+use tokio_stream::{StreamExt, StreamMap};
+
+use super::{visible_run, AppState};
+use crate::auth::Principal;
+use crate::types::{ErrorResponse, Event, WsClientMessage, WsServerMessage};
+
+/// Sockets get a WebSocket ping this often so proxies keep idle ones open.
+const WS_PING_INTERVAL: Duration = Duration::from_secs(15);
+
+type Rejection = (StatusCode, Json<ErrorResponse>);
+
+#[derive(Debug, Deserialize)]
+pub(super) struct ResumeQuery {
+    after: Option<String>,
+}
+
+/// `GET /v1/runs/{run_id}/ws`: a socket subscribed to one run from the start,
+/// or after the `after` event id. It closes once the run has ended.
+pub(super) async fn run_socket(
+    State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
+    Path(run_id): Path<String>,
+    query: Result<Query<ResumeQuery>, QueryRejection>,
+    upgrade: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+) -> Result<Response, Rejection> {
+    let Query(query) = query.map_err(|rejection| {
+        rejected("invalid_query", StatusCode::BAD_REQUEST, rejection.body_text())
+    })?;
+    let upgrade = upgrade.map_err(|rejection| {
+        rejected("invalid_request", rejection.status(), rejection.body_text())
+    })?;
+    visible_run(&state, &principal, &run_id).await?;
+    let session = Session::new(state, principal, true);
+    Ok(upgrade.on_upgrade(move |socket| session.run(socket, Some((run_id, query.after)))))
+}
+
+/// `GET /v1/ws`: a socket that follows whichever runs the client subscribes
+/// to. It stays open until the client closes it.
+pub(super) async fn multiplexed_socket(
+    State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
+    upgrade: Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+) -> Result<Response, Rejection> {
+    let upgrade = upgrade.map_err(|rejection| {
+        rejected("invalid_request", rejection.status(), rejection.body_text())
+    })?;
+    let session = Session::new(state, principal, false);
+    Ok(upgrade.on_upgrade(move |socket| session.run(socket, None)))
+}
+
+fn rejected(code: &str, status: StatusCode, message: String) -> Rejection {
+    (
+        status,
+        Json(ErrorResponse {
+            code: code.to_string(),
+            message,
+            retryable: false,
+            details: None,
+        }),
+    )
+}
+
+struct Session {
+    state: AppState,
+    principal: Option<Extension<Principal>>,
+    // Each stream yields `None` once after its run's events end.
+    subscriptions: StreamMap<String, BoxStream<'static, Option<Event>>>,
+    close_when_idle: bool,
+}
+
+impl Session {
+    fn new(
+        state: AppState,
+        principal: Option<Extension<Principal>>,
+        close_when_idle: bool,
+    ) -> Self {
+        Self {
+            state,
+            principal,
+            subscriptions: StreamMap::new(),
+            close_when_idle,
+        }
+    }
+
+    async fn run(mut self, mut socket: WebSocket, initial: Option<(String, Option<String>)>) {
+        if let Some((run_id, after)) = initial {
+            let reply = self.subscribe(run_id, after).await;
+            if send(&mut socket, &reply).await.is_err() {
+                return;
+            }
+        }
+        let mut ping = tokio::time::interval_at(
+            tokio::time::Instant::now() + WS_PING_INTERVAL,
+            WS_PING_INTERVAL,
+        );
+        loop {
+            if self.close_when_idle && self.subscriptions.is_empty() {
+                let frame = CloseFrame {
+                    code: close_code::NORMAL,
+                    reason: "run ended".into(),
+                };
+                let _ = socket.send(Message::Close(Some(frame))).await;
+                return;
+            }
+            let reply = tokio::select! {
+                message = socket.recv() => match message {
+                    Some(Ok(Message::Text(text))) => self.handle(&text).await,
+                    Some(Ok(Message::Binary(_))) => invalid_message("expected a text message"),
+                    Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                },
+                Some((run_id, event)) = self.subscriptions.next() => match event {
+                    Some(event) => WsServerMessage::Event { event },
+                    None => {
+                        self.subscriptions.remove(&run_id);
+                        WsServerMessage::Unsubscribed { run_id }
+                    }
+                },
+                _ = ping.tick() => {
+                    if socket.send(Message::Ping(Vec::new())).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+            };
+            if send(&mut socket, &reply).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    async fn handle(&mut self, text: &str) -> WsServerMessage {
+        match serde_json::from_str(text) {
+            Ok(WsClientMessage::Subscribe { run_id, after }) => self.subscribe(run_id, after).await,
+            Ok(WsClientMessage::Unsubscribe { run_id }) => {
+                self.subscriptions.remove(&run_id);
+                WsServerMessage::Unsubscribed { run_id }
+            }
+            Ok(WsClientMessage::Ping) => WsServerMessage::Pong,
+            Err(err) => invalid_message(&format!("invalid message: {}", err)),
+        }
+    }
+
+    async fn subscribe(&mut self, run_id: String, after: Option<String>) -> WsServerMessage {
+        let events = match visible_run(&self.state, &self.principal, &run_id).await {
+            Ok(_) => self.state.runtime.event_stream(&run_id, after.as_deref()).await,
+            Err((_, Json(error))) => {
+                return WsServerMessage::Error {
+                    run_id: Some(run_id),
+                    error,
+                };
+            }
+        };
+        let Some(events) = events else {
+            return WsServerMessage::Error {
+                run_id: Some(run_id),
+                error: ErrorResponse {
+                    code: "not_found".to_string(),
+                    message: "run not found".to_string(),
+                    retryable: false,
+                    details: None,
+                },
+            };
+        };
+        let events = events.map(Some).chain(tokio_stream::once(None));
+        self.subscriptions.insert(run_id.clone(), Box::pin(events));
+        WsServerMessage::Subscribed { run_id }
+    }
+}
+
+fn invalid_message(message: &str) -> WsServerMessage {
+    WsServerMessage::Error {
+        run_id: None,
+        error: ErrorResponse {
+            code: "invalid_request".to_string(),
+            message: message.to_string(),
+            retryable: false,
+            details: None,
+        },
+    }
+}
+
+async fn send(socket: &mut WebSocket, message: &WsServerMessage) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).unwrap_or_else(|_| "{}".to_string());
+    socket.send(Message::Text(text)).await
+}
@@ -0,0 +1,206 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+
+use tokio::sync::RwLock;
+
+use super::{
+    EventQuery, EvictionReport, IdempotencyRecord, RunQuery, RunStore, RunUpdate, StoreError,
+    WorkflowRegistration,
+};
+use crate::types::{Artifact, Event, Run};
+
+struct RunEntry {
+    run: Run,
+    events: Vec<Event>,
+}
+
+/// Default backend: everything lives in process memory and is lost on restart.
+#[derive(Default)]
+pub struct InMemoryRunStore {
+    runs: RwLock<HashMap<String, RunEntry>>,
+    artifacts: RwLock<HashMap<String, Artifact>>,
+    workflows: RwLock<HashMap<(String, Option<String>), WorkflowRegistration>>,
+    idempotency: RwLock<HashMap<String, IdempotencyRecord>>,
+}
+
+impl InMemoryRunStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl RunStore for InMemoryRunStore {
+    async fn insert_run(&self, run: Run) -> Result<(), StoreError> {
+        let mut runs = self.runs.write().await;
+        runs.insert(
+            run.run_id.clone(),
+            RunEntry {
+                run,
+                events: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn update_run(&self, run_id: &str, update: RunUpdate) -> Result<Option<Run>, StoreError> {
+        let mut runs = self.runs.write().await;
+        Ok(runs.get_mut(run_id).map(|entry| {
+            update(&mut entry.run);
+            entry.run.clone()
+        }))
+    }
+
+    async fn get_run(&self, run_id: &str) -> Result<Option<Run>, StoreError> {
+        let runs = self.runs.read().await;
+        Ok(runs.get(run_id).map(|entry| entry.run.clone()))
+    }
+
+    async fn list_runs(&self, query: &RunQuery) -> Result<Vec<Run>, StoreError> {
+        let runs = self.runs.read().await;
+        let mut data: Vec<Run> = runs
+            .values()
+            .map(|entry| &entry.run)
+            .filter(|run| query.matches(run))
+            .filter(|run| query.cursor.as_ref().is_none_or(|cursor| cursor.precedes(run)))
+            .cloned()
+            .collect();
+        data.sort_by(|a, b| {
+            b.timing
+                .created_at
+                .cmp(&a.timing.created_at)
+                .then_with(|| b.run_id.cmp(&a.run_id))
+        });
+        data.truncate(query.limit);
+        Ok(data)
+    }
+
+    async fn append_event(&self, event: Event) -> Result<(), StoreError> {
+        let mut runs = self.runs.write().await;
+        let Some(entry) = runs.get_mut(&event.run_id) else {
+            return Err(StoreError::UnknownRun(event.run_id));
+        };
+        entry.events.push(event);
+        Ok(())
+    }
+
+    async fn list_events(&self, run_id: &str) -> Result<Option<Vec<Event>>, StoreError> {
+        let runs = self.runs.read().await;
+        Ok(runs.get(run_id).map(|entry| entry.events.clone()))
+    }
+
+    async fn query_events(
+        &self,
+        run_id: &str,
+        query: &EventQuery,
+    ) -> Result<Option<Vec<Event>>, StoreError> {
+        let runs = self.runs.read().await;
+        let Some(entry) = runs.get(run_id) else {
+            return Ok(None);
+        };
+        let start = match &query.after {
+            Some(after) => {
+                let index = entry.events.iter().position(|event| &event.event_id == after);
+                index.ok_or_else(|| StoreError::UnknownEvent(after.clone()))? + 1
+            }
+            None => 0,
+        };
+        Ok(Some(
+            entry.events[start..]
+                .iter()
+                .filter(|event| query.matches(event))
+                .take(query.limit)
+                .cloned()
+                .collect(),
+        ))
+    }
+
+    async fn count_events(&self, run_id: &str) -> Result<usize, StoreError> {
+        let runs = self.runs.read().await;
+        Ok(runs.get(run_id).map_or(0, |entry| entry.events.len()))
+    }
+
+    async fn insert_artifact(&self, artifact: Artifact) -> Result<(), StoreError> {
+        let mut artifacts = self.artifacts.write().await;
+        artifacts.insert(artifact.artifact_id.clone(), artifact);
+        Ok(())
+    }
+
+    async fn get_artifact(&self, artifact_id: &str) -> Result<Option<Artifact>, StoreError> {
+        let artifacts = self.artifacts.read().await;
+        Ok(artifacts.get(artifact_id).cloned())
+    }
+
+    async fn save_workflow(&self, registration: WorkflowRegistration) -> Result<(), StoreError> {
+        let mut workflows = self.workflows.write().await;
+        let key = (registration.name.clone(), registration.version.clone());
+        workflows.insert(key, registration);
+        Ok(())
+    }
+
+    async fn list_workflows(&self) -> Result<Vec<WorkflowRegistration>, StoreError> {
+        let workflows = self.workflows.read().await;
+        Ok(workflows.values().cloned().collect())
+    }
+
+    async fn get_idempotency_record(
+        &self,
+        key: &str,
+    ) -> Result<Option<IdempotencyRecord>, StoreError> {
+        let idempotency = self.idempotency.read().await;
+        Ok(idempotency.get(key).cloned())
+    }
+
+    async fn put_idempotency_record(&self, record: IdempotencyRecord) -> Result<(), StoreError> {
+        let mut idempotency = self.idempotency.write().await;
+        idempotency.insert(record.key.clone(), record);
+        Ok(())
+    }
+
+    async fn evict(
+        &self,
+        runs_finished_before: Option<DateTime<Utc>>,
+        artifacts_created_before: Option<DateTime<Utc>>,
+    ) -> Result<EvictionReport, StoreError> {
+        let mut runs = self.runs.write().await;
+        let mut artifacts = self.artifacts.write().await;
+        let mut report = EvictionReport::default();
+        if let Some(cutoff) = runs_finished_before {
+            let expired: Vec<String> = runs
+                .values()
+                .filter(|entry| entry.run.status.is_terminal() && finished_at(&entry.run) < cutoff)
+                .map(|entry| entry.run.run_id.clone())
+                .collect();
+            for run_id in expired {
+                if let Some(entry) = runs.remove(&run_id) {
+                    for artifact in &entry.run.artifacts {
+                        if artifacts.remove(&artifact.artifact_id).is_some() {
+                            report.artifacts += 1;
+                        }
+                    }
+                    report.run_ids.push(run_id);
+                }
+            }
+        }
+        if let Some(cutoff) = artifacts_created_before {
+            let in_use: HashSet<&str> = runs
+                .values()
+                .filter(|entry| !entry.run.status.is_terminal())
+                .flat_map(|entry| entry.run.artifacts.iter())
+                .map(|artifact| artifact.artifact_id.as_str())
+                .collect();
+            let before = artifacts.len();
+            artifacts.retain(|artifact_id, artifact| {
+                artifact.created_at >= cutoff || in_use.contains(artifact_id.as_str())
+            });
+            report.artifacts += before - artifacts.len();
+        }
+        Ok(report)
+    }
+}
+
+/// Runs canceled before they started may lack `finished_at`.
+fn finished_at(run: &Run) -> DateTime<Utc> {
+    run.timing.finished_at.unwrap_or(run.timing.created_at)
+}
@@ -0,0 +1,219 @@
+mod memory;
+mod sqlite;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::types::{Artifact, Event, EventType, Run, RunStatus};
+
+pub use memory::InMemoryRunStore;
+pub use sqlite::SqliteRunStore;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("serialization error: {0}")]
+    Serde(#[from] serde_json::Error),
+    #[error("storage task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    #[error("unknown event: {0}")]
+    UnknownEvent(String),
+    #[error("unknown run: {0}")]
+    UnknownRun(String),
+}
+
+/// Persisted description of a registered workflow. Runners themselves live in
+/// process memory; only the metadata needed to describe them is stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRegistration {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_schema: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+    pub active: bool,
+}
+
+/// Maps an `Idempotency-Key` to the run it created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    pub key: String,
+    pub run_id: String,
+    /// Hash of the original request body; a replay with a different body is a conflict.
+    pub request_hash: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Position in the run listing, which is ordered newest first by
+/// `(created_at, run_id)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunCursor {
+    pub created_at: DateTime<Utc>,
+    pub run_id: String,
+}
+
+impl RunCursor {
+    pub fn of(run: &Run) -> Self {
+        Self {
+            created_at: run.timing.created_at,
+            run_id: run.run_id.clone(),
+        }
+    }
+
+    /// Whether `run` comes after this position in listing order.
+    pub fn precedes(&self, run: &Run) -> bool {
+        (&run.timing.created_at, &run.run_id) < (&self.created_at, &self.run_id)
+    }
+}
+
+/// Filters and page bounds for [`RunStore::list_runs`]. Every set filter must match.
+#[derive(Debug, Clone, Default)]
+pub struct RunQuery {
+    pub workflow_name: Option<String>,
+    /// Only runs of one of these workflows; `None` keeps all.
+    pub workflow_names: Option<Vec<String>>,
+    pub status: Option<RunStatus>,
+    pub labels: Vec<(String, String)>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub tenant_id: Option<String>,
+    /// Only return runs after this position.
+    pub cursor: Option<RunCursor>,
+    pub limit: usize,
+}
+
+impl RunQuery {
+    /// Whether `run` passes the filters; the cursor and limit are not considered.
+    pub fn matches(&self, run: &Run) -> bool {
+        self.workflow_name
+            .as_ref()
+            .is_none_or(|name| &run.workflow.name == name)
+            && self
+                .workflow_names
+                .as_ref()
+                .is_none_or(|names| names.contains(&run.workflow.name))
+            && self.status.as_ref().is_none_or(|status| &run.status == status)
+            && self
+                .labels
+                .iter()
+                .all(|(key, value)| run.labels.get(key) == Some(value))
+            && self
+                .created_after
+                .is_none_or(|after| run.timing.created_at >= after)
+            && self
+                .created_before
+                .is_none_or(|before| run.timing.created_at < before)
+            && self
+                .tenant_id
+                .as_ref()
+                .is_none_or(|tenant| run.tenant_id.as_ref() == Some(tenant))
+    }
+}
+
+/// Filters and page bounds for [`RunStore::query_events`]. Every set filter must match.
+#[derive(Debug, Clone, Default)]
+pub struct EventQuery {
+    /// Event types to keep; empty keeps all.
+    pub types: Vec<EventType>,
+    pub step_id: Option<String>,
+    /// Only return events emitted after the event with this id. An id the run
+    /// does not have fails with [`StoreError::UnknownEvent`].
+    pub after: Option<String>,
+    pub limit: usize,
+}
+
+impl EventQuery {
+    /// Whether `event` passes the filters; `after` and the limit are not considered.
+    pub fn matches(&self, event: &Event) -> bool {
+        (self.types.is_empty() || self.types.contains(&event.event_type))
+            && self
+                .step_id
+                .as_ref()
+                .is_none_or(|step_id| event.step_id.as_ref() == Some(step_id))
+    }
+}
+
+/// What one [`RunStore::evict`] call removed.
+#[derive(Debug, Clone, Default)]
+pub struct EvictionReport {
+    pub run_ids: Vec<String>,
+    /// Artifacts removed, including those of evicted runs.
+    pub artifacts: usize,
+}
+
+/// In-place mutation applied to a stored run. Stores apply it atomically with
+/// respect to other updates of the same run.
+pub type RunUpdate = Box<dyn FnOnce(&mut Run) + Send>;
+
+/// Storage backend for runs, their events, artifacts and workflow registrations.
+/// Workflow registrations are keyed by name and version.
+#[async_trait::async_trait]
+pub trait RunStore: Send + Sync {
+    /// Whether stored runs outlive the process. Shutdown leaves interrupted
+    /// runs queued in durable stores instead of failing them.
+    fn is_durable(&self) -> bool {
+        false
+    }
+
+    async fn insert_run(&self, run: Run) -> Result<(), StoreError>;
+
+    /// Applies `update` to the run and returns the updated copy, or `None` if
+    /// the run does not exist.
+    async fn update_run(&self, run_id: &str, update: RunUpdate) -> Result<Option<Run>, StoreError>;
+
+    async fn get_run(&self, run_id: &str) -> Result<Option<Run>, StoreError>;
+
+    /// Returns up to `query.limit` runs matching `query`, newest first.
+    async fn list_runs(&self, query: &RunQuery) -> Result<Vec<Run>, StoreError>;
+
+    /// Fails with [`StoreError::UnknownRun`] when the event's run is not stored.
+    async fn append_event(&self, event: Event) -> Result<(), StoreError>;
+
+    /// Returns the events of a run in emission order, or `None` if the run does
+    /// not exist.
+    async fn list_events(&self, run_id: &str) -> Result<Option<Vec<Event>>, StoreError>;
+
+    /// Returns up to `query.limit` events of a run matching `query` in emission
+    /// order, or `None` if the run does not exist.
+    async fn query_events(
+        &self,
+        run_id: &str,
+        query: &EventQuery,
+    ) -> Result<Option<Vec<Event>>, StoreError>;
+
+    async fn count_events(&self, run_id: &str) -> Result<usize, StoreError>;
+
+    async fn insert_artifact(&self, artifact: Artifact) -> Result<(), StoreError>;
+
+    async fn get_artifact(&self, artifact_id: &str) -> Result<Option<Artifact>, StoreError>;
+
+    async fn save_workflow(&self, registration: WorkflowRegistration) -> Result<(), StoreError>;
+
+    async fn list_workflows(&self) -> Result<Vec<WorkflowRegistration>, StoreError>;
+
+    /// Returns the record for `key`, including expired ones; callers check `expires_at`.
+    async fn get_idempotency_record(
+        &self,
+        key: &str,
+    ) -> Result<Option<IdempotencyRecord>, StoreError>;
+
+    async fn put_idempotency_record(&self, record: IdempotencyRecord) -> Result<(), StoreError>;
+
+    /// Deletes finished runs that finished before `runs_finished_before`,
+    /// together with their events and artifacts, and artifacts created before
+    /// `artifacts_created_before` unless an unfinished run lists them. Queued,
+    /// running and waiting runs are never removed.
+    async fn evict(
+        &self,
+        runs_finished_before: Option<DateTime<Utc>>,
+        artifacts_created_before: Option<DateTime<Utc>>,
+    ) -> Result<EvictionReport, StoreError>;
+}
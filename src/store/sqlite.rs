@@ -0,0 +1,538 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde_json::Value;
+
+use super::{
+    EventQuery, EvictionReport, IdempotencyRecord, RunQuery, RunStore, RunUpdate, StoreError,
+    WorkflowRegistration,
+};
+use crate::types::{Artifact, ArtifactRef, Event, Run, RunStatus};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    run_id TEXT PRIMARY KEY,
+    created_at TEXT NOT NULL,
+    body TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS events (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL,
+    event_id TEXT NOT NULL,
+    body TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS events_run_id_seq ON events (run_id, seq);
+CREATE TABLE IF NOT EXISTS artifacts (
+    artifact_id TEXT PRIMARY KEY,
+    body TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS workflows (
+    name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    body TEXT NOT NULL,
+    PRIMARY KEY (name, version)
+);
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    key TEXT PRIMARY KEY,
+    body TEXT NOT NULL
+);
+";
+
+/// SQLite-backed store. Runs, events and artifacts survive process restarts.
+///
+/// Records are stored as JSON bodies next to the few columns needed for
+/// lookups and ordering, so adding fields to the API types needs no migration.
+///
+/// Queries are blocking, so each one runs on tokio's blocking thread pool
+/// rather than on the async worker that awaits it.
+pub struct SqliteRunStore {
+    conn: Arc<Mutex<Connection>>,
+    // False for in-memory databases, which vanish with the process.
+    durable: bool,
+}
+
+impl SqliteRunStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open(path)?, true)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_in_memory()?, false)
+    }
+
+    fn from_connection(conn: Connection, durable: bool) -> Result<Self, StoreError> {
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        conn.execute_batch(SCHEMA)?;
+        // Files created before workflows were keyed by version still have the
+        // name-only table. Registrations are saved again on every start, so the
+        // old table is dropped rather than migrated.
+        if conn.prepare("SELECT version FROM workflows LIMIT 0").is_err() {
+            conn.execute_batch("DROP TABLE workflows;")?;
+            conn.execute_batch(SCHEMA)?;
+        }
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            durable,
+        })
+    }
+
+    /// Runs `query` with the connection on a blocking thread.
+    async fn call<T, F>(&self, query: F) -> Result<T, StoreError>
+    where
+        F: FnOnce(&mut Connection) -> Result<T, StoreError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            query(&mut conn)
+        })
+        .await?
+    }
+}
+
+/// Fixed-width RFC 3339 timestamp, so string order matches time order.
+fn sort_key(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+fn run_exists(conn: &Connection, run_id: &str) -> Result<bool, StoreError> {
+    let found = conn
+        .query_row("SELECT 1 FROM runs WHERE run_id = ?1", params![run_id], |_| Ok(()))
+        .optional()?;
+    Ok(found.is_some())
+}
+
+#[async_trait::async_trait]
+impl RunStore for SqliteRunStore {
+    fn is_durable(&self) -> bool {
+        self.durable
+    }
+
+    async fn insert_run(&self, run: Run) -> Result<(), StoreError> {
+        let body = serde_json::to_string(&run)?;
+        let created_at = sort_key(&run.timing.created_at);
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO runs (run_id, created_at, body) VALUES (?1, ?2, ?3)",
+                params![run.run_id, created_at, body],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn update_run(&self, run_id: &str, update: RunUpdate) -> Result<Option<Run>, StoreError> {
+        let run_id = run_id.to_string();
+        self.call(move |conn| {
+            let tx = conn.transaction()?;
+            let body: Option<String> = tx
+                .query_row("SELECT body FROM runs WHERE run_id = ?1", params![run_id], |row| {
+                    row.get(0)
+                })
+                .optional()?;
+            let Some(body) = body else {
+                return Ok(None);
+            };
+            let mut run: Run = serde_json::from_str(&body)?;
+            update(&mut run);
+            tx.execute(
+                "UPDATE runs SET body = ?2 WHERE run_id = ?1",
+                params![run_id, serde_json::to_string(&run)?],
+            )?;
+            tx.commit()?;
+            Ok(Some(run))
+        })
+        .await
+    }
+
+    async fn get_run(&self, run_id: &str) -> Result<Option<Run>, StoreError> {
+        let run_id = run_id.to_string();
+        let body: Option<String> = self
+            .call(move |conn| {
+                let body = conn
+                    .query_row("SELECT body FROM runs WHERE run_id = ?1", params![run_id], |row| {
+                        row.get(0)
+                    })
+                    .optional()?;
+                Ok(body)
+            })
+            .await?;
+        body.map(|body| serde_json::from_str(&body).map_err(StoreError::from))
+            .transpose()
+    }
+
+    async fn list_runs(&self, query: &RunQuery) -> Result<Vec<Run>, StoreError> {
+        let workflow_clause = query.workflow_names.as_ref().map(|names| {
+            let placeholders = vec!["?"; names.len()].join(", ");
+            format!("json_extract(body, '$.workflow.name') IN ({})", placeholders)
+        });
+        let mut clauses: Vec<&str> = Vec::new();
+        let mut args: Vec<String> = Vec::new();
+        if let Some(name) = &query.workflow_name {
+            clauses.push("json_extract(body, '$.workflow.name') = ?");
+            args.push(name.clone());
+        }
+        if let (Some(clause), Some(names)) = (&workflow_clause, &query.workflow_names) {
+            clauses.push(clause);
+            args.extend(names.iter().cloned());
+        }
+        if let Some(status) = &query.status {
+            clauses.push("json_extract(body, '$.status') = ?");
+            args.push(serde_json::to_value(status)?.as_str().unwrap_or_default().to_string());
+        }
+        for (key, value) in &query.labels {
+            clauses.push("json_extract(body, ?) = ?");
+            args.push(format!("$.labels.\"{}\"", key.replace('"', "")));
+            args.push(value.clone());
+        }
+        if let Some(after) = &query.created_after {
+            clauses.push("created_at >= ?");
+            args.push(sort_key(after));
+        }
+        if let Some(before) = &query.created_before {
+            clauses.push("created_at < ?");
+            args.push(sort_key(before));
+        }
+        if let Some(tenant) = &query.tenant_id {
+            clauses.push("json_extract(body, '$.tenant_id') = ?");
+            args.push(tenant.clone());
+        }
+        if let Some(cursor) = &query.cursor {
+            clauses.push("(created_at < ? OR (created_at = ? AND run_id < ?))");
+            args.push(sort_key(&cursor.created_at));
+            args.push(sort_key(&cursor.created_at));
+            args.push(cursor.run_id.clone());
+        }
+        let mut sql = String::from("SELECT body FROM runs");
+        if !clauses.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&clauses.join(" AND "));
+        }
+        sql.push_str(&format!(" ORDER BY created_at DESC, run_id DESC LIMIT {}", query.limit));
+
+        let bodies = self
+            .call(move |conn| {
+                let mut stmt = conn.prepare(&sql)?;
+                let bodies = stmt
+                    .query_map(params_from_iter(args.iter()), |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(bodies)
+            })
+            .await?;
+        bodies
+            .iter()
+            .map(|body| serde_json::from_str(body).map_err(StoreError::from))
+            .collect()
+    }
+
+    async fn append_event(&self, event: Event) -> Result<(), StoreError> {
+        let body = serde_json::to_string(&event)?;
+        self.call(move |conn| {
+            if !run_exists(conn, &event.run_id)? {
+                return Err(StoreError::UnknownRun(event.run_id));
+            }
+            conn.execute(
+                "INSERT INTO events (run_id, event_id, body) VALUES (?1, ?2, ?3)",
+                params![event.run_id, event.event_id, body],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list_events(&self, run_id: &str) -> Result<Option<Vec<Event>>, StoreError> {
+        let run_id = run_id.to_string();
+        let bodies = self
+            .call(move |conn| {
+                if !run_exists(conn, &run_id)? {
+                    return Ok(None);
+                }
+                let mut stmt =
+                    conn.prepare("SELECT body FROM events WHERE run_id = ?1 ORDER BY seq")?;
+                let bodies = stmt
+                    .query_map(params![run_id], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Some(bodies))
+            })
+            .await?;
+        let Some(bodies) = bodies else {
+            return Ok(None);
+        };
+        let events = bodies
+            .iter()
+            .map(|body| serde_json::from_str(body).map_err(StoreError::from))
+            .collect::<Result<Vec<Event>, _>>()?;
+        Ok(Some(events))
+    }
+
+    async fn query_events(
+        &self,
+        run_id: &str,
+        query: &EventQuery,
+    ) -> Result<Option<Vec<Event>>, StoreError> {
+        let mut clauses: Vec<String> = vec!["run_id = ?".to_string()];
+        let mut args: Vec<String> = vec![run_id.to_string()];
+        if let Some(after) = &query.after {
+            clauses.push(
+                "seq > (SELECT seq FROM events WHERE run_id = ? AND event_id = ?)".to_string(),
+            );
+            args.push(run_id.to_string());
+            args.push(after.clone());
+        }
+        if !query.types.is_empty() {
+            let placeholders = vec!["?"; query.types.len()].join(", ");
+            clauses.push(format!("json_extract(body, '$.type') IN ({})", placeholders));
+            for event_type in &query.types {
+                args.push(
+                    serde_json::to_value(event_type)?
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                );
+            }
+        }
+        if let Some(step_id) = &query.step_id {
+            clauses.push("json_extract(body, '$.step_id') = ?".to_string());
+            args.push(step_id.clone());
+        }
+        let sql = format!(
+            "SELECT body FROM events WHERE {} ORDER BY seq LIMIT {}",
+            clauses.join(" AND "),
+            query.limit
+        );
+
+        let run_id = run_id.to_string();
+        let after = query.after.clone();
+        let bodies = self
+            .call(move |conn| {
+                if !run_exists(conn, &run_id)? {
+                    return Ok(None);
+                }
+                if let Some(after) = after {
+                    let found = conn
+                        .query_row(
+                            "SELECT 1 FROM events WHERE run_id = ?1 AND event_id = ?2",
+                            params![run_id, after],
+                            |_| Ok(()),
+                        )
+                        .optional()?;
+                    if found.is_none() {
+                        return Err(StoreError::UnknownEvent(after));
+                    }
+                }
+                let mut stmt = conn.prepare(&sql)?;
+                let bodies = stmt
+                    .query_map(params_from_iter(args.iter()), |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Some(bodies))
+            })
+            .await?;
+        let Some(bodies) = bodies else {
+            return Ok(None);
+        };
+        let events = bodies
+            .iter()
+            .map(|body| serde_json::from_str(body).map_err(StoreError::from))
+            .collect::<Result<Vec<Event>, _>>()?;
+        Ok(Some(events))
+    }
+
+    async fn count_events(&self, run_id: &str) -> Result<usize, StoreError> {
+        let run_id = run_id.to_string();
+        let count: i64 = self
+            .call(move |conn| {
+                let count = conn.query_row(
+                    "SELECT COUNT(*) FROM events WHERE run_id = ?1",
+                    params![run_id],
+                    |row| row.get(0),
+                )?;
+                Ok(count)
+            })
+            .await?;
+        Ok(count as usize)
+    }
+
+    async fn insert_artifact(&self, artifact: Artifact) -> Result<(), StoreError> {
+        let body = serde_json::to_string(&artifact)?;
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO artifacts (artifact_id, body) VALUES (?1, ?2)",
+                params![artifact.artifact_id, body],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_artifact(&self, artifact_id: &str) -> Result<Option<Artifact>, StoreError> {
+        let artifact_id = artifact_id.to_string();
+        let body: Option<String> = self
+            .call(move |conn| {
+                let body = conn
+                    .query_row(
+                        "SELECT body FROM artifacts WHERE artifact_id = ?1",
+                        params![artifact_id],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                Ok(body)
+            })
+            .await?;
+        body.map(|body| serde_json::from_str(&body).map_err(StoreError::from))
+            .transpose()
+    }
+
+    async fn save_workflow(&self, registration: WorkflowRegistration) -> Result<(), StoreError> {
+        let body = serde_json::to_string(&registration)?;
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO workflows (name, version, body) VALUES (?1, ?2, ?3)",
+                params![
+                    registration.name,
+                    registration.version.as_deref().unwrap_or_default(),
+                    body
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list_workflows(&self) -> Result<Vec<WorkflowRegistration>, StoreError> {
+        let bodies = self
+            .call(|conn| {
+                let mut stmt = conn.prepare("SELECT body FROM workflows ORDER BY name, version")?;
+                let bodies = stmt
+                    .query_map([], |row| row.get::<_, String>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(bodies)
+            })
+            .await?;
+        bodies
+            .iter()
+            .map(|body| serde_json::from_str(body).map_err(StoreError::from))
+            .collect()
+    }
+
+    async fn get_idempotency_record(
+        &self,
+        key: &str,
+    ) -> Result<Option<IdempotencyRecord>, StoreError> {
+        let key = key.to_string();
+        let body: Option<String> = self
+            .call(move |conn| {
+                let body = conn
+                    .query_row(
+                        "SELECT body FROM idempotency_keys WHERE key = ?1",
+                        params![key],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                Ok(body)
+            })
+            .await?;
+        body.map(|body| serde_json::from_str(&body).map_err(StoreError::from))
+            .transpose()
+    }
+
+    async fn put_idempotency_record(&self, record: IdempotencyRecord) -> Result<(), StoreError> {
+        let body = serde_json::to_string(&record)?;
+        self.call(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO idempotency_keys (key, body) VALUES (?1, ?2)",
+                params![record.key, body],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn evict(
+        &self,
+        runs_finished_before: Option<DateTime<Utc>>,
+        artifacts_created_before: Option<DateTime<Utc>>,
+    ) -> Result<EvictionReport, StoreError> {
+        self.call(move |conn| evict(conn, runs_finished_before, artifacts_created_before))
+            .await
+    }
+}
+
+/// [`RunStore::evict`] in one transaction.
+fn evict(
+    conn: &mut Connection,
+    runs_finished_before: Option<DateTime<Utc>>,
+    artifacts_created_before: Option<DateTime<Utc>>,
+) -> Result<EvictionReport, StoreError> {
+    let tx = conn.transaction()?;
+    let mut report = EvictionReport::default();
+    // Only the fields eviction looks at are read, not whole run bodies.
+    let runs = tx
+        .prepare(
+            "SELECT run_id, json_extract(body, '$.status'),
+                COALESCE(json_extract(body, '$.timing.finished_at'), created_at),
+                json_extract(body, '$.artifacts')
+             FROM runs",
+        )?
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut in_use = HashSet::new();
+    for (run_id, status, finished_at, artifacts) in runs {
+        let status: RunStatus = serde_json::from_value(Value::String(status))?;
+        let artifacts: Vec<ArtifactRef> = match artifacts {
+            Some(artifacts) => serde_json::from_str(&artifacts)?,
+            None => Vec::new(),
+        };
+        if !status.is_terminal() {
+            in_use.extend(artifacts.into_iter().map(|artifact| artifact.artifact_id));
+            continue;
+        }
+        let expired = runs_finished_before
+            .is_some_and(|cutoff| parse_time(&finished_at).is_some_and(|at| at < cutoff));
+        if !expired {
+            continue;
+        }
+        tx.execute("DELETE FROM events WHERE run_id = ?1", params![run_id])?;
+        tx.execute("DELETE FROM runs WHERE run_id = ?1", params![run_id])?;
+        for artifact in artifacts {
+            report.artifacts += tx.execute(
+                "DELETE FROM artifacts WHERE artifact_id = ?1",
+                params![artifact.artifact_id],
+            )?;
+        }
+        report.run_ids.push(run_id);
+    }
+    if let Some(cutoff) = artifacts_created_before {
+        let artifacts = tx
+            .prepare("SELECT artifact_id, json_extract(body, '$.created_at') FROM artifacts")?
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (artifact_id, created_at) in artifacts {
+            let expired = parse_time(&created_at).is_some_and(|at| at < cutoff);
+            if expired && !in_use.contains(&artifact_id) {
+                report.artifacts += tx.execute(
+                    "DELETE FROM artifacts WHERE artifact_id = ?1",
+                    params![artifact_id],
+                )?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(report)
+}
+
+fn parse_time(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
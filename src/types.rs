@@ -1,8 +1,10 @@
+use std::collections::HashMap;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum RunStatus {
     Queued,
@@ -14,6 +16,15 @@ pub enum RunStatus {
     TimedOut,
 }
 
+impl RunStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            Self::Succeeded | Self::Failed | Self::Canceled | Self::TimedOut
+        )
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowRef {
     pub name: String,
@@ -41,6 +52,51 @@ pub struct ErrorResponse {
     pub details: Option<Value>,
 }
 
+/// Model usage reported by a run's runner, summed over the run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cost {
+    #[serde(default)]
+    pub total_tokens: u64,
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_usd: f64,
+}
+
+impl Cost {
+    /// Reads a model API's `usage` object, with OpenAI (`prompt_tokens`,
+    /// `completion_tokens`) or Anthropic (`input_tokens`, `output_tokens`)
+    /// field names. `None` when it holds no token counts.
+    pub fn from_usage(usage: &Value) -> Option<Cost> {
+        let tokens = |keys: [&str; 2]| keys.iter().find_map(|key| usage.get(*key)?.as_u64());
+        let prompt_tokens = tokens(["prompt_tokens", "input_tokens"]);
+        let completion_tokens = tokens(["completion_tokens", "output_tokens"]);
+        if prompt_tokens.is_none() && completion_tokens.is_none() {
+            return None;
+        }
+        let prompt_tokens = prompt_tokens.unwrap_or(0);
+        let completion_tokens = completion_tokens.unwrap_or(0);
+        Some(Cost {
+            total_tokens: usage
+                .get("total_tokens")
+                .and_then(Value::as_u64)
+                .unwrap_or(prompt_tokens + completion_tokens),
+            prompt_tokens,
+            completion_tokens,
+            total_usd: 0.0,
+        })
+    }
+
+    pub fn add(&mut self, other: &Cost) {
+        self.total_tokens += other.total_tokens;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_usd += other.total_usd;
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Run {
     pub run_id: String,
@@ -59,8 +115,83 @@ pub struct Run {
     pub output: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<ErrorResponse>,
+    /// Set once the runner reports model usage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<Cost>,
     #[serde(default)]
     pub artifacts: Vec<ArtifactRef>,
+    /// Number of times the runner has been invoked for this run.
+    #[serde(default)]
+    pub attempts: u32,
+    /// Human checkpoints opened by the runner, in the order they were opened.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checkpoints: Vec<HumanCheckpoint>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub labels: HashMap<String, String>,
+    /// Free-form metadata from the create request, returned as submitted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Map<String, Value>>,
+    /// 1-based place in the admission queue while the run is `queued`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_position: Option<usize>,
+    /// Id of the API key that created the run, when the server requires one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HumanCheckpointStatus {
+    Required,
+    Approved,
+    Rejected,
+    InputProvided,
+    Resolved,
+    /// Nobody answered before `expires_at`.
+    Expired,
+    /// The run ended (canceled, timed out or failed) while the checkpoint was open.
+    Canceled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointDecision {
+    pub approved: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub decided_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HumanCheckpoint {
+    pub checkpoint_id: String,
+    pub run_id: String,
+    pub status: HumanCheckpointStatus,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_schema: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provided_input: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<CheckpointDecision>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CheckpointDecisionRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointInputRequest {
+    pub input: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,7 +203,7 @@ pub struct RunCreateRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<serde_json::Map<String, Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub labels: Option<std::collections::HashMap<String, String>>,
+    pub labels: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,6 +211,35 @@ pub struct RunCreateResponse {
     pub run: Run,
 }
 
+/// Query parameters of `GET /v1/runs`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunListQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Opaque `next_cursor` from a previous page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<RunStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workflow_name: Option<String>,
+    /// Comma-separated `key=value` pairs; a run must carry all of them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_selector: Option<String>,
+    /// Inclusive lower bound on `timing.created_at`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_after: Option<DateTime<Utc>>,
+    /// Exclusive upper bound on `timing.created_at`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    /// Workflows a restricted API key may list; set by the server, never
+    /// read from the query string.
+    #[serde(skip)]
+    pub workflow_names: Option<Vec<String>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunListResponse {
     pub data: Vec<Run>,
@@ -108,6 +268,12 @@ pub struct Artifact {
     pub data: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub file: Option<ArtifactFile>,
+    /// Tenant of the run that produced the artifact; set by the runtime.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    /// Run that produced the artifact; set by the runtime.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -140,9 +306,31 @@ pub struct Event {
     pub step_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
     pub payload: Value,
 }
 
+/// Query parameters of `GET /v1/runs/{run_id}/events`. Only `after` applies
+/// to SSE streams.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventListQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Opaque `next_cursor` from a previous page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Comma-separated event types, e.g. `step.failed,tool.called`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub types: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step_id: Option<String>,
+    /// Only return events recorded after this `event_id`. For SSE the
+    /// `Last-Event-ID` header takes precedence.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventListResponse {
     pub data: Vec<Event>,
@@ -150,6 +338,39 @@ pub struct EventListResponse {
     pub next_cursor: Option<String>,
 }
 
+/// Control message a client sends over the event WebSockets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsClientMessage {
+    /// Streams the run's events, starting after `after` like SSE's
+    /// `Last-Event-ID`. Subscribing again restarts the run's stream.
+    Subscribe {
+        run_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        after: Option<String>,
+    },
+    Unsubscribe { run_id: String },
+    Ping,
+}
+
+/// Message the server sends over the event WebSockets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsServerMessage {
+    Subscribed { run_id: String },
+    /// Sent on `unsubscribe` and after a run's terminal event.
+    Unsubscribed { run_id: String },
+    /// The same JSON an SSE `data:` line carries.
+    Event { event: Event },
+    /// A rejected control message; `run_id` is set when it named a run.
+    Error {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        run_id: Option<String>,
+        error: ErrorResponse,
+    },
+    Pong,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Workflow {
     pub name: String,
@@ -160,9 +381,30 @@ pub struct Workflow {
     #[serde(default)]
     pub tags: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_timeout_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub input_schema_ref: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub output_schema_ref: Option<String>,
+    /// Every registered version, newest first.
+    #[serde(default)]
+    pub versions: Vec<WorkflowVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowVersion {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Whether `latest` and semver ranges may resolve to this version.
+    pub active: bool,
+}
+
+/// Query parameters of the single-workflow endpoints.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkflowVersionQuery {
+    /// Exact version, semver range or `latest`; defaults to the latest active version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -190,7 +432,7 @@ pub struct SchemaBundle {
     pub schemas: std::collections::HashMap<String, Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum EventType {
     #[serde(rename = "run.started")]
     RunStarted,
@@ -198,12 +440,44 @@ pub enum EventType {
     RunCompleted,
     #[serde(rename = "run.failed")]
     RunFailed,
+    #[serde(rename = "run.canceled")]
+    RunCanceled,
     #[serde(rename = "step.started")]
     StepStarted,
     #[serde(rename = "step.completed")]
     StepCompleted,
     #[serde(rename = "step.failed")]
     StepFailed,
+    #[serde(rename = "tool.called")]
+    ToolCalled,
+    #[serde(rename = "tool.result")]
+    ToolResult,
+    #[serde(rename = "llm.request")]
+    LlmRequest,
+    #[serde(rename = "llm.response")]
+    LlmResponse,
+    #[serde(rename = "hitl.required")]
+    HitlRequired,
+    #[serde(rename = "hitl.resolved")]
+    HitlResolved,
     #[serde(rename = "artifact.created")]
     ArtifactCreated,
+    /// Stored once a run reaches its event limit; later events are only
+    /// delivered live.
+    #[serde(rename = "events.truncated")]
+    EventsTruncated,
+    /// Explains what startup recovery did with a run a previous process left
+    /// unfinished.
+    #[serde(rename = "run.recovered")]
+    RunRecovered,
+    /// Sent to unfinished runs when the runtime starts shutting down.
+    #[serde(rename = "runtime.shutdown")]
+    RuntimeShutdown,
+}
+
+impl EventType {
+    /// Whether this is the last event of a run.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::RunCompleted | Self::RunFailed | Self::RunCanceled)
+    }
 }
@@ -0,0 +1,695 @@
+use std::sync::Arc;
+
+use agent_runtime::auth::{ApiKey, AuthConfig, Scope};
+use agent_runtime::grpc::proto::agent_runtime_client::AgentRuntimeClient;
+use agent_runtime::grpc::proto::agent_runtime_server::AgentRuntimeServer;
+use agent_runtime::grpc::{self, error_details, grpc_service, grpc_service_with_auth, proto};
+use agent_runtime::runtime::{
+    AgentError, CheckpointRequest, InMemoryRuntime, RunContext, WorkflowOutput, WorkflowRunner,
+};
+use agent_runtime::server::{router, RouterBuilder};
+use agent_runtime::store::SqliteRunStore;
+use agent_runtime::types::{Artifact, ArtifactType, ErrorResponse, RunStatus};
+use axum::body::Body;
+use futures::StreamExt;
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tokio::time::{sleep, timeout, Duration};
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::{Channel, Server};
+use tonic::Code;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// Echoes its input back as output and as a record artifact.
+struct EchoWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for EchoWorkflow {
+    fn name(&self) -> &'static str {
+        "echo"
+    }
+
+    fn version(&self) -> Option<&'static str> {
+        Some("0.1.0")
+    }
+
+    async fn run(&self, input: Value, ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
+        ctx.step("echo", async { Ok(()) }).await?;
+        let artifact = Artifact {
+            artifact_id: format!("art_{}", Uuid::new_v4()),
+            r#type: ArtifactType::Record,
+            name: Some("echo".to_string()),
+            created_at: chrono::Utc::now(),
+            mime_type: Some("application/json".to_string()),
+            data: Some(json!({ "echo": input })),
+            file: None,
+            tenant_id: None,
+            run_id: None,
+        };
+        Ok(WorkflowOutput {
+            output: json!({ "echo": input }),
+            artifacts: vec![artifact],
+        })
+    }
+}
+
+/// Asks a human before finishing and echoes the resolution back as output.
+struct ApprovalWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for ApprovalWorkflow {
+    fn name(&self) -> &'static str {
+        "approval"
+    }
+
+    async fn run(&self, _input: Value, ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
+        let checkpoint = ctx
+            .request_checkpoint(CheckpointRequest {
+                title: Some("Send the prebrief?".to_string()),
+                ..CheckpointRequest::default()
+            })
+            .await?;
+        Ok(WorkflowOutput {
+            output: json!({ "status": checkpoint.status }),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+fn backends() -> Vec<Arc<InMemoryRuntime>> {
+    let sqlite = SqliteRunStore::open_in_memory().expect("open sqlite store");
+    vec![
+        Arc::new(InMemoryRuntime::new()),
+        Arc::new(InMemoryRuntime::with_store(Arc::new(sqlite))),
+    ]
+}
+
+/// Serves `service` on an ephemeral port and connects a client to it.
+async fn connect(service: AgentRuntimeServer<grpc::GrpcService>) -> AgentRuntimeClient<Channel> {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("local addr");
+    let incoming = TcpIncoming::from_listener(listener, true, None).expect("incoming");
+    tokio::spawn(Server::builder().add_service(service).serve_with_incoming(incoming));
+    AgentRuntimeClient::connect(format!("http://{}", addr))
+        .await
+        .expect("connect")
+}
+
+async fn get_json(app: &axum::Router, uri: &str) -> (axum::http::StatusCode, Value) {
+    let response = app
+        .clone()
+        .oneshot(axum::http::Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .expect("response");
+    let status = response.status();
+    let body = response.into_body().collect().await.expect("body").to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+async fn wait_for_status(runtime: &InMemoryRuntime, run_id: &str, status: RunStatus) {
+    for _ in 0..100 {
+        let run = runtime.get_run(run_id).await.expect("run exists");
+        if run.status == status {
+            return;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    panic!("run {} never reached {:?}", run_id, status);
+}
+
+/// The proto enum name of a wire value, e.g. `run.started` is
+/// `EVENT_TYPE_RUN_STARTED`.
+fn enum_name(prefix: &str, wire: &Value) -> String {
+    let wire = wire.as_str().expect("enum string");
+    format!("{}_{}", prefix, wire.replace('.', "_").to_uppercase())
+}
+
+fn create_request(workflow: &str, input: Value) -> proto::CreateRunRequest {
+    proto::CreateRunRequest {
+        workflow: Some(proto::WorkflowRef {
+            name: workflow.to_string(),
+            version: String::new(),
+        }),
+        input: Some(input.into()),
+        ..Default::default()
+    }
+}
+
+fn with_token<T>(token: &str, message: T) -> tonic::Request<T> {
+    let mut request = tonic::Request::new(message);
+    let value = format!("Bearer {}", token).parse().expect("metadata value");
+    request.metadata_mut().insert("authorization", value);
+    request
+}
+
+fn assert_run_matches(run: &proto::Run, http: &Value) {
+    assert_eq!(run.run_id, http["run_id"].as_str().unwrap());
+    assert_eq!(run.status().as_str_name(), enum_name("RUN_STATUS", &http["status"]));
+    assert_eq!(run.workflow.as_ref().unwrap().name, http["workflow"]["name"].as_str().unwrap());
+    assert_eq!(run.trace_id, http["trace_id"].as_str().unwrap_or_default());
+    assert_eq!(run.tenant_id, http["tenant_id"].as_str().unwrap_or_default());
+    assert_eq!(run.attempts, http["attempts"].as_u64().unwrap() as u32);
+    let timing = run.timing.as_ref().unwrap();
+    assert_eq!(timing.created_at, http["timing"]["created_at"].as_str().unwrap());
+    assert_eq!(timing.started_at, http["timing"]["started_at"].as_str().unwrap_or_default());
+    assert_eq!(timing.finished_at, http["timing"]["finished_at"].as_str().unwrap_or_default());
+    assert_eq!(Value::from(run.input.clone().unwrap()), http["input"]);
+    assert_eq!(run.output.clone().map(Value::from).unwrap_or_default(), http["output"]);
+    let artifacts: Vec<&str> = run.artifacts.iter().map(|a| a.artifact_id.as_str()).collect();
+    let http_artifacts: Vec<&str> = http["artifacts"]
+        .as_array()
+        .map(|artifacts| artifacts.iter().map(|a| a["artifact_id"].as_str().unwrap()).collect())
+        .unwrap_or_default();
+    assert_eq!(artifacts, http_artifacts);
+}
+
+fn assert_event_matches(event: &proto::Event, http: &Value) {
+    assert_eq!(event.event_id, http["event_id"].as_str().unwrap());
+    assert_eq!(event.ts, http["ts"].as_str().unwrap());
+    assert_eq!(event.r#type().as_str_name(), enum_name("EVENT_TYPE", &http["type"]));
+    assert_eq!(event.step_id, http["step_id"].as_str().unwrap_or_default());
+    assert_eq!(Value::from(event.payload.clone().unwrap()), http["payload"]);
+}
+
+#[test]
+fn json_values_round_trip() {
+    let value = json!({
+        "null": null,
+        "flag": true,
+        "count": -3,
+        "big": u64::MAX,
+        "ratio": 0.25,
+        "name": "上海",
+        "items": [1, "two", [3.5], { "nested": {} }],
+    });
+    let proto = proto::JsonValue::from(value.clone());
+    assert_eq!(Value::from(proto), json!({
+        "null": null,
+        "flag": true,
+        "count": -3,
+        "big": u64::MAX as f64,
+        "ratio": 0.25,
+        "name": "上海",
+        "items": [1, "two", [3.5], { "nested": {} }],
+    }));
+
+    let nan = proto::JsonValue {
+        kind: Some(proto::json_value::Kind::DoubleValue(f64::NAN)),
+    };
+    assert_eq!(Value::from(nan), Value::Null);
+    assert_eq!(Value::from(proto::JsonValue { kind: None }), Value::Null);
+}
+
+#[tokio::test]
+async fn runs_and_events_match_http() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(EchoWorkflow)).await;
+        let app = router(runtime.clone());
+        let mut client = connect(grpc_service(runtime.clone())).await;
+
+        let mut request = create_request("echo", json!({ "text": "hi", "count": 2 }));
+        request.labels.insert("team".to_string(), "sales".to_string());
+        let created = client.create_run(request).await.expect("create run");
+        let run_id = created.into_inner().run.unwrap().run_id;
+        wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+
+        let run = client
+            .get_run(proto::GetRunRequest { run_id: run_id.clone() })
+            .await
+            .expect("get run")
+            .into_inner()
+            .run
+            .unwrap();
+        let (_, http_run) = get_json(&app, &format!("/v1/runs/{}", run_id)).await;
+        assert_run_matches(&run, &http_run);
+        assert_eq!(run.labels["team"], "sales");
+
+        let listed = client
+            .list_runs(proto::ListRunsRequest {
+                label_selector: "team=sales".to_string(),
+                ..Default::default()
+            })
+            .await
+            .expect("list runs")
+            .into_inner();
+        assert_eq!(listed.data.len(), 1);
+        assert_run_matches(&listed.data[0], &http_run);
+
+        let artifact_id = run.artifacts[0].artifact_id.clone();
+        let artifact = client
+            .get_artifact(proto::GetArtifactRequest {
+                artifact_id: artifact_id.clone(),
+            })
+            .await
+            .expect("get artifact")
+            .into_inner();
+        let (_, http_artifact) = get_json(&app, &format!("/v1/artifacts/{}", artifact_id)).await;
+        assert_eq!(artifact.created_at, http_artifact["created_at"].as_str().unwrap());
+        assert_eq!(artifact.r#type(), proto::ArtifactType::Record);
+        assert_eq!(Value::from(artifact.data.unwrap()), http_artifact["data"]);
+
+        let (_, http_events) = get_json(&app, &format!("/v1/runs/{}/events", run_id)).await;
+        let http_events = http_events["data"].as_array().unwrap().clone();
+        let listed = client
+            .list_events(proto::ListEventsRequest {
+                run_id: run_id.clone(),
+                ..Default::default()
+            })
+            .await
+            .expect("list events")
+            .into_inner();
+        let streamed: Vec<proto::Event> = client
+            .stream_events(proto::StreamEventsRequest {
+                run_id: run_id.clone(),
+                after: String::new(),
+            })
+            .await
+            .expect("stream events")
+            .into_inner()
+            .map(|event| event.expect("event"))
+            .collect()
+            .await;
+        assert_eq!(listed.data.len(), http_events.len());
+        assert_eq!(streamed.len(), http_events.len());
+        for ((listed, streamed), http) in listed.data.iter().zip(&streamed).zip(&http_events) {
+            assert_event_matches(listed, http);
+            assert_event_matches(streamed, http);
+        }
+
+        // Resuming after an event skips everything up to it, as SSE does.
+        let resumed = client
+            .stream_events(proto::StreamEventsRequest {
+                run_id: run_id.clone(),
+                after: streamed[0].event_id.clone(),
+            })
+            .await
+            .expect("resume events")
+            .into_inner()
+            .count()
+            .await;
+        assert_eq!(resumed, streamed.len() - 1);
+    }
+}
+
+#[tokio::test]
+async fn workflows_match_http() {
+    for runtime in backends() {
+        runtime
+            .register_workflow_with_schemas(
+                Arc::new(EchoWorkflow),
+                Some(json!({ "type": "object", "required": ["text"] })),
+                Some(json!({ "type": "object" })),
+            )
+            .await;
+        let app = router(runtime.clone());
+        let mut client = connect(grpc_service(runtime.clone())).await;
+
+        let listed = client
+            .list_workflows(proto::ListWorkflowsRequest::default())
+            .await
+            .expect("list workflows")
+            .into_inner();
+        assert_eq!(listed.data.len(), 1);
+        assert_eq!(listed.data[0].name, "echo");
+        assert_eq!(listed.data[0].version, "0.1.0");
+
+        let workflow = client
+            .get_workflow(proto::GetWorkflowRequest {
+                name: "echo".to_string(),
+                version: String::new(),
+            })
+            .await
+            .expect("get workflow")
+            .into_inner();
+        let (_, http_workflow) = get_json(&app, "/v1/workflows/echo").await;
+        let schema_ref = http_workflow["input_schema_ref"].as_str().unwrap_or_default();
+        assert_eq!(workflow.input_schema_ref, schema_ref);
+        assert_eq!(workflow.description, http_workflow["description"].as_str().unwrap_or_default());
+        assert_eq!(workflow.versions.len(), http_workflow["versions"].as_array().unwrap().len());
+
+        let bundle = client
+            .get_workflow_schemas(proto::GetWorkflowSchemasRequest {
+                name: "echo".to_string(),
+                version: String::new(),
+            })
+            .await
+            .expect("get schemas")
+            .into_inner();
+        let (_, http_bundle) = get_json(&app, "/v1/workflows/echo/schemas").await;
+        assert_eq!(bundle.schema_hash, http_bundle["schema_hash"].as_str().unwrap());
+        let schemas: serde_json::Map<String, Value> = bundle
+            .schemas
+            .into_iter()
+            .map(|(kind, schema)| (kind, schema.into()))
+            .collect();
+        assert_eq!(Value::Object(schemas), http_bundle["schemas"]);
+
+        // Input validation failures carry the same error as over HTTP.
+        let status = client
+            .create_run(create_request("echo", json!({})))
+            .await
+            .expect_err("invalid input");
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let error = error_details(&status).expect("error details");
+        assert_eq!(error.code, "input_validation_failed");
+        assert!(error.details.is_some());
+    }
+}
+
+#[tokio::test]
+async fn errors_match_http() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(EchoWorkflow)).await;
+        let app = router(runtime.clone());
+        let mut client = connect(grpc_service(runtime.clone())).await;
+
+        let status = client
+            .get_run(proto::GetRunRequest {
+                run_id: "run_missing".to_string(),
+            })
+            .await
+            .expect_err("missing run");
+        let (http_status, http_error) = get_json(&app, "/v1/runs/run_missing").await;
+        assert_eq!(http_status, axum::http::StatusCode::NOT_FOUND);
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(error_details(&status).unwrap().code, http_error["code"].as_str().unwrap());
+
+        let status = client
+            .create_run(create_request("missing", json!({})))
+            .await
+            .expect_err("unknown workflow");
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(error_details(&status).unwrap().code, "workflow_not_found");
+
+        let created = client
+            .create_run(create_request("echo", json!({})))
+            .await
+            .expect("create run");
+        let run_id = created.into_inner().run.unwrap().run_id;
+        wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+        let status = client
+            .cancel_run(proto::CancelRunRequest { run_id })
+            .await
+            .expect_err("finished run");
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert_eq!(error_details(&status).unwrap().code, "run_not_cancelable");
+    }
+}
+
+#[tokio::test]
+async fn idempotency_key_replays_original_run() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(EchoWorkflow)).await;
+        let mut client = connect(grpc_service(runtime.clone())).await;
+
+        let mut request = create_request("echo", json!({ "text": "once" }));
+        request.idempotency_key = "order-42".to_string();
+        let first = client.create_run(request.clone()).await.expect("first");
+        assert!(first.metadata().get("idempotent-replayed").is_none());
+        let second = client.create_run(request.clone()).await.expect("replay");
+        assert_eq!(second.metadata().get("idempotent-replayed").unwrap(), "true");
+        assert_eq!(
+            first.into_inner().run.unwrap().run_id,
+            second.into_inner().run.unwrap().run_id
+        );
+
+        request.input = Some(json!({ "text": "twice" }).into());
+        let status = client.create_run(request).await.expect_err("conflict");
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert_eq!(error_details(&status).unwrap().code, "idempotency_conflict");
+    }
+}
+
+#[tokio::test]
+async fn stream_follows_run_through_checkpoint() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let mut client = connect(grpc_service(runtime.clone())).await;
+
+        let created = client
+            .create_run(create_request("approval", json!({})))
+            .await
+            .expect("create run");
+        let run_id = created.into_inner().run.unwrap().run_id;
+        let mut events = client
+            .stream_events(proto::StreamEventsRequest {
+                run_id: run_id.clone(),
+                after: String::new(),
+            })
+            .await
+            .expect("stream events")
+            .into_inner();
+
+        let mut checkpoint_id = None;
+        while checkpoint_id.is_none() {
+            let event = timeout(Duration::from_secs(1), events.next())
+                .await
+                .expect("event in time")
+                .expect("stream open")
+                .expect("event");
+            if event.r#type() == proto::EventType::HitlRequired {
+                let payload = Value::from(event.payload.unwrap());
+                checkpoint_id = payload["checkpoint_id"].as_str().map(str::to_string);
+            }
+        }
+
+        let checkpoint = client
+            .approve_checkpoint(proto::CheckpointDecisionRequest {
+                run_id: run_id.clone(),
+                checkpoint_id: checkpoint_id.unwrap(),
+                note: "ship it".to_string(),
+            })
+            .await
+            .expect("approve")
+            .into_inner();
+        assert_eq!(checkpoint.status(), proto::HumanCheckpointStatus::Approved);
+        assert_eq!(checkpoint.decision.unwrap().note, "ship it");
+
+        // The stream ends with the run's terminal event.
+        let rest: Vec<proto::Event> = timeout(Duration::from_secs(1), events.collect::<Vec<_>>())
+            .await
+            .expect("stream ends")
+            .into_iter()
+            .map(|event| event.expect("event"))
+            .collect();
+        assert_eq!(rest.last().unwrap().r#type(), proto::EventType::RunCompleted);
+        let run = runtime.get_run(&run_id).await.unwrap();
+        assert_eq!(run.output, Some(json!({ "status": "approved" })));
+    }
+}
+
+fn tenant_auth_config() -> AuthConfig {
+    let all = [Scope::RunsCreate, Scope::RunsRead, Scope::RunsCancel, Scope::WorkflowsRead];
+    AuthConfig {
+        keys: vec![
+            ApiKey::new("shanghai-ops", "shanghai-token", all).with_tenant("shanghai"),
+            ApiKey::new("suzhou-ops", "suzhou-token", all).with_tenant("suzhou"),
+            ApiKey::new("reader", "reader-token", [Scope::RunsRead]),
+        ],
+    }
+}
+
+#[tokio::test]
+async fn keys_scopes_and_tenants_apply() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(EchoWorkflow)).await;
+        let service = grpc_service_with_auth(runtime.clone(), tenant_auth_config());
+        let mut client = connect(service).await;
+
+        let status = client
+            .list_workflows(proto::ListWorkflowsRequest::default())
+            .await
+            .expect_err("no token");
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert_eq!(error_details(&status).unwrap().message, "missing bearer token");
+
+        let request = with_token("reader-token", create_request("echo", json!({})));
+        let status = client.create_run(request).await.expect_err("missing scope");
+        assert_eq!(status.code(), Code::PermissionDenied);
+        let error = error_details(&status).unwrap();
+        assert_eq!(error.details, Some(json!({ "required_scope": "runs:create" })));
+
+        let request = with_token("shanghai-token", create_request("echo", json!({})));
+        let run = client.create_run(request).await.expect("create run").into_inner().run.unwrap();
+        assert_eq!(run.tenant_id, "shanghai");
+        assert_eq!(run.created_by, "shanghai-ops");
+        wait_for_status(&runtime, &run.run_id, RunStatus::Succeeded).await;
+
+        let request = proto::GetRunRequest {
+            run_id: run.run_id.clone(),
+        };
+        let status = client
+            .get_run(with_token("suzhou-token", request.clone()))
+            .await
+            .expect_err("other tenant");
+        assert_eq!(status.code(), Code::NotFound);
+        client
+            .get_run(with_token("shanghai-token", request))
+            .await
+            .expect("own run");
+
+        let status = client
+            .stream_events(with_token(
+                "suzhou-token",
+                proto::StreamEventsRequest {
+                    run_id: run.run_id.clone(),
+                    after: String::new(),
+                },
+            ))
+            .await
+            .expect_err("other tenant's events");
+        assert_eq!(status.code(), Code::NotFound);
+
+        let listed = client
+            .list_runs(with_token("suzhou-token", proto::ListRunsRequest::default()))
+            .await
+            .expect("list runs")
+            .into_inner();
+        assert!(listed.data.is_empty());
+        let status = client
+            .list_runs(with_token(
+                "suzhou-token",
+                proto::ListRunsRequest {
+                    tenant_id: "shanghai".to_string(),
+                    ..Default::default()
+                },
+            ))
+            .await
+            .expect_err("other tenant's listing");
+        assert_eq!(status.code(), Code::PermissionDenied);
+    }
+}
+
+#[tokio::test]
+async fn keys_only_read_runs_of_their_workflows() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(EchoWorkflow)).await;
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let config = AuthConfig {
+            keys: vec![
+                ApiKey::new("operator", "operator-token", [Scope::RunsCreate, Scope::RunsRead]),
+                ApiKey::new("approval-reader", "approval-token", [Scope::RunsRead])
+                    .with_workflows(["approval"]),
+            ],
+        };
+        let mut client = connect(grpc_service_with_auth(runtime.clone(), config)).await;
+
+        let mut runs = Vec::new();
+        for name in ["echo", "approval"] {
+            let request = with_token("operator-token", create_request(name, json!({})));
+            let run = client.create_run(request).await.expect("create run").into_inner();
+            runs.push(run.run.unwrap().run_id);
+        }
+        wait_for_status(&runtime, &runs[0], RunStatus::Succeeded).await;
+        wait_for_status(&runtime, &runs[1], RunStatus::WaitingHuman).await;
+        let echo_run = runtime.get_run(&runs[0]).await.expect("run");
+
+        let request = proto::GetRunRequest {
+            run_id: runs[0].clone(),
+        };
+        let status = client
+            .get_run(with_token("approval-token", request))
+            .await
+            .expect_err("other workflow's run");
+        assert_eq!(status.code(), Code::PermissionDenied);
+        assert_eq!(error_details(&status).unwrap().details, Some(json!({ "workflow": "echo" })));
+        let request = proto::ListEventsRequest {
+            run_id: runs[0].clone(),
+            ..Default::default()
+        };
+        let status = client
+            .list_events(with_token("approval-token", request))
+            .await
+            .expect_err("other workflow's events");
+        assert_eq!(status.code(), Code::PermissionDenied);
+        let request = proto::StreamEventsRequest {
+            run_id: runs[0].clone(),
+            after: String::new(),
+        };
+        let status = client
+            .stream_events(with_token("approval-token", request))
+            .await
+            .expect_err("other workflow's stream");
+        assert_eq!(status.code(), Code::PermissionDenied);
+        let request = proto::GetArtifactRequest {
+            artifact_id: echo_run.artifacts[0].artifact_id.clone(),
+        };
+        let status = client
+            .get_artifact(with_token("approval-token", request.clone()))
+            .await
+            .expect_err("other workflow's artifact");
+        assert_eq!(status.code(), Code::PermissionDenied);
+        let artifact = client
+            .get_artifact(with_token("operator-token", request))
+            .await
+            .expect("operator reads any artifact")
+            .into_inner();
+        assert_eq!(artifact.run_id, runs[0]);
+
+        let request = proto::GetRunRequest {
+            run_id: runs[1].clone(),
+        };
+        client
+            .get_run(with_token("approval-token", request))
+            .await
+            .expect("own workflow's run");
+        let listed = client
+            .list_runs(with_token("approval-token", proto::ListRunsRequest::default()))
+            .await
+            .expect("list runs")
+            .into_inner();
+        let listed: Vec<String> = listed.data.into_iter().map(|run| run.run_id).collect();
+        assert_eq!(listed, [runs[1].clone()]);
+        let request = proto::ListRunsRequest {
+            workflow_name: "echo".to_string(),
+            ..Default::default()
+        };
+        let status = client
+            .list_runs(with_token("approval-token", request))
+            .await
+            .expect_err("other workflow's listing");
+        assert_eq!(status.code(), Code::PermissionDenied);
+    }
+}
+
+#[tokio::test]
+async fn create_run_hooks_apply_to_grpc() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(EchoWorkflow)).await;
+        let builder = RouterBuilder::new(runtime.clone()).on_create_run(|req, principal| {
+            if req.input.get("blocked").is_some() {
+                return Err(ErrorResponse {
+                    code: "invalid_request".to_string(),
+                    message: "blocked input".to_string(),
+                    retryable: false,
+                    details: None,
+                });
+            }
+            let caller = principal.map_or("anonymous", |principal| principal.key_id.as_str());
+            let metadata = req.metadata.get_or_insert_with(Default::default);
+            metadata.insert("caller".to_string(), json!(caller));
+            Ok(())
+        });
+        let mut client = connect(builder.grpc_service()).await;
+
+        let status = client
+            .create_run(create_request("echo", json!({ "blocked": true })))
+            .await
+            .expect_err("hook rejects");
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let error = error_details(&status).unwrap();
+        assert_eq!(error.code, "invalid_request");
+        assert_eq!(error.message, "blocked input");
+
+        let run = client
+            .create_run(create_request("echo", json!({})))
+            .await
+            .expect("create run")
+            .into_inner()
+            .run
+            .unwrap();
+        let run = runtime.get_run(&run.run_id).await.expect("run exists");
+        assert_eq!(run.metadata.expect("metadata")["caller"], "anonymous");
+    }
+}
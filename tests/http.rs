@@ -1,9 +1,21 @@
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
-use agent_runtime::runtime::{AgentError, InMemoryRuntime, WorkflowOutput, WorkflowRunner};
-use agent_runtime::server::router;
-use agent_runtime::types::{EventListResponse, RunCreateResponse, WorkflowListResponse};
+use agent_runtime::runtime::{
+    AgentError, CheckpointRequest, ConcurrencyLimits, InMemoryRuntime, RetentionMetrics,
+    RetentionPolicy, RetryPolicy, RunContext, TenantQuota, WorkflowOptions, WorkflowOutput,
+    WorkflowRunner,
+};
+use agent_runtime::auth::{ApiKey, AuthConfig, Principal, Scope};
+use agent_runtime::server::{router, router_with_auth, AppState, RouterBuilder};
+use agent_runtime::store::{InMemoryRunStore, RunStore, SqliteRunStore, StoreError};
+use agent_runtime::types::{
+    Event, EventListResponse, EventType, HumanCheckpointStatus, Run, RunCreateRequest,
+    RunCreateResponse, RunStatus, WorkflowListResponse, WorkflowRef,
+};
 use axum::body::Body;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use http_body_util::BodyExt;
 use serde_json::json;
 use tower::ServiceExt;
@@ -22,7 +34,11 @@ impl WorkflowRunner for TestWorkflow {
         Some("0.1.0")
     }
 
-    async fn run(&self, input: serde_json::Value) -> Result<WorkflowOutput, AgentError> {
+    async fn run(
+        &self,
+        input: serde_json::Value,
+        _ctx: &RunContext,
+    ) -> Result<WorkflowOutput, AgentError> {
         let artifact = agent_runtime::types::Artifact {
             artifact_id: format!("art_{}", Uuid::new_v4()),
             r#type: agent_runtime::types::ArtifactType::Record,
@@ -31,6 +47,8 @@ impl WorkflowRunner for TestWorkflow {
             mime_type: Some("application/json".to_string()),
             data: Some(json!({ "echo": input })),
             file: None,
+            tenant_id: None,
+            run_id: None,
         };
         Ok(WorkflowOutput {
             output: json!({ "echo": input }),
@@ -39,6 +57,249 @@ impl WorkflowRunner for TestWorkflow {
     }
 }
 
+struct SlowWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for SlowWorkflow {
+    fn name(&self) -> &'static str {
+        "slow"
+    }
+
+    async fn run(
+        &self,
+        _input: serde_json::Value,
+        _ctx: &RunContext,
+    ) -> Result<WorkflowOutput, AgentError> {
+        sleep(Duration::from_secs(10)).await;
+        Ok(WorkflowOutput {
+            output: json!({}),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+/// Fails with the configured error until `failures` attempts have been made.
+struct FlakyWorkflow {
+    failures: u32,
+    retryable: bool,
+    calls: AtomicU32,
+}
+
+impl FlakyWorkflow {
+    fn new(failures: u32, retryable: bool) -> Self {
+        Self {
+            failures,
+            retryable,
+            calls: AtomicU32::new(0),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl WorkflowRunner for FlakyWorkflow {
+    fn name(&self) -> &'static str {
+        "flaky"
+    }
+
+    async fn run(
+        &self,
+        _input: serde_json::Value,
+        _ctx: &RunContext,
+    ) -> Result<WorkflowOutput, AgentError> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+        if call <= self.failures {
+            return Err(if self.retryable {
+                AgentError::retryable("db unavailable")
+            } else {
+                AgentError::fatal("bad input")
+            });
+        }
+        Ok(WorkflowOutput {
+            output: json!({ "call": call }),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+/// Asks a human before finishing and echoes the resolution back as output.
+struct ApprovalWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for ApprovalWorkflow {
+    fn name(&self) -> &'static str {
+        "approval"
+    }
+
+    async fn run(
+        &self,
+        _input: serde_json::Value,
+        ctx: &RunContext,
+    ) -> Result<WorkflowOutput, AgentError> {
+        let checkpoint = ctx
+            .request_checkpoint(CheckpointRequest {
+                title: Some("Send the prebrief?".to_string()),
+                ..CheckpointRequest::default()
+            })
+            .await?;
+        let decision = checkpoint.decision.expect("decision");
+        if !decision.approved {
+            return Err(AgentError::fatal("rejected by reviewer"));
+        }
+        Ok(WorkflowOutput {
+            output: json!({ "note": decision.note, "input": checkpoint.provided_input }),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+/// Asks a human, but only waits briefly for the answer.
+struct ExpiringWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for ExpiringWorkflow {
+    fn name(&self) -> &'static str {
+        "expiring"
+    }
+
+    async fn run(
+        &self,
+        _input: serde_json::Value,
+        ctx: &RunContext,
+    ) -> Result<WorkflowOutput, AgentError> {
+        ctx.request_checkpoint(CheckpointRequest {
+            expires_in: Some(Duration::from_millis(50)),
+            ..CheckpointRequest::default()
+        })
+        .await?;
+        Ok(WorkflowOutput {
+            output: json!({}),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+/// Reports a step with a tool call and an LLM call, and publishes an artifact
+/// before finishing.
+struct StagedWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for StagedWorkflow {
+    fn name(&self) -> &'static str {
+        "staged"
+    }
+
+    async fn run(
+        &self,
+        _input: serde_json::Value,
+        ctx: &RunContext,
+    ) -> Result<WorkflowOutput, AgentError> {
+        ctx.step("fetch", async {
+            ctx.tool_called("fetch", "mysql.query", json!({ "table": "visits" }))
+                .await;
+            ctx.tool_result("fetch", "mysql.query", json!({ "rows": 3 }))
+                .await;
+            Ok(())
+        })
+        .await?;
+        ctx.llm_request("summarize", json!({ "model": "test" })).await;
+        ctx.llm_response(
+            "summarize",
+            json!({ "ok": true, "usage": { "prompt_tokens": 12, "completion_tokens": 5 } }),
+        )
+        .await;
+        ctx.add_artifact(agent_runtime::types::Artifact {
+            artifact_id: format!("art_{}", Uuid::new_v4()),
+            r#type: agent_runtime::types::ArtifactType::Message,
+            name: Some("draft".to_string()),
+            created_at: chrono::Utc::now(),
+            mime_type: None,
+            data: Some(json!("draft")),
+            file: None,
+            tenant_id: None,
+            run_id: None,
+        })
+        .await;
+        Ok(WorkflowOutput {
+            output: json!({
+                "run_id": ctx.run_id(),
+                "trace_id": ctx.trace_id(),
+                "context": ctx.context(),
+                "labels": ctx.labels(),
+            }),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+/// Waits for approval, then reports a burst of steps.
+struct BurstWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for BurstWorkflow {
+    fn name(&self) -> &'static str {
+        "burst"
+    }
+
+    async fn run(
+        &self,
+        _input: serde_json::Value,
+        ctx: &RunContext,
+    ) -> Result<WorkflowOutput, AgentError> {
+        ctx.request_checkpoint(CheckpointRequest::default()).await?;
+        for step in 0..20 {
+            ctx.step(&format!("step_{}", step), async { Ok(()) }).await?;
+        }
+        Ok(WorkflowOutput {
+            output: json!({}),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+/// Reports the version it was registered under.
+struct VersionedWorkflow(&'static str);
+
+#[async_trait::async_trait]
+impl WorkflowRunner for VersionedWorkflow {
+    fn name(&self) -> &'static str {
+        "versioned"
+    }
+
+    fn version(&self) -> Option<&'static str> {
+        Some(self.0)
+    }
+
+    async fn run(
+        &self,
+        _input: serde_json::Value,
+        _ctx: &RunContext,
+    ) -> Result<WorkflowOutput, AgentError> {
+        Ok(WorkflowOutput {
+            output: json!({ "version": self.0 }),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+fn fast_retry(max_attempts: u32) -> WorkflowOptions {
+    WorkflowOptions {
+        retry: RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        },
+        ..WorkflowOptions::default()
+    }
+}
+
+fn backends() -> Vec<Arc<InMemoryRuntime>> {
+    let sqlite = SqliteRunStore::open_in_memory().expect("open sqlite store");
+    vec![
+        Arc::new(InMemoryRuntime::new()),
+        Arc::new(InMemoryRuntime::with_store(Arc::new(sqlite))),
+    ]
+}
+
 async fn read_body_bytes(body: Body) -> Vec<u8> {
     let mut data = Vec::new();
     let mut body = body;
@@ -51,6 +312,35 @@ async fn read_body_bytes(body: Body) -> Vec<u8> {
     data
 }
 
+async fn post_run(app: &axum::Router, payload: serde_json::Value) -> String {
+    let response = app
+        .clone()
+        .oneshot(
+            axum::http::Request::post("/v1/runs")
+                .header("content-type", "application/json")
+                .body(Body::from(payload.to_string()))
+                .unwrap(),
+        )
+        .await
+        .expect("create run response");
+    assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+    let body_bytes = read_body_bytes(response.into_body()).await;
+    let created: RunCreateResponse =
+        serde_json::from_slice(&body_bytes).expect("parse create response");
+    created.run.run_id
+}
+
+async fn wait_for_status(runtime: &InMemoryRuntime, run_id: &str, status: RunStatus) -> Run {
+    for _ in 0..100 {
+        let run = runtime.get_run(run_id).await.expect("run exists");
+        if run.status == status {
+            return run;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    panic!("run {} never reached {:?}", run_id, status);
+}
+
 async fn read_first_body_frame(body: Body) -> Vec<u8> {
     let mut body = body;
     let frame = timeout(Duration::from_millis(200), body.frame())
@@ -66,262 +356,2355 @@ async fn read_first_body_frame(body: Body) -> Vec<u8> {
 
 #[tokio::test]
 async fn create_and_get_run() {
-    let runtime = Arc::new(InMemoryRuntime::new());
-    runtime.register_workflow(Arc::new(TestWorkflow)).await;
-    let app = router(runtime);
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime);
 
-    let payload = json!({
-        "workflow": { "name": "echo", "version": "0.1.0" },
-        "input": { "hello": "world" }
-    });
+        let payload = json!({
+            "workflow": { "name": "echo", "version": "0.1.0" },
+            "input": { "hello": "world" }
+        });
 
-    let response = app
-        .clone()
-        .oneshot(
-            axum::http::Request::post("/v1/runs")
-                .header("content-type", "application/json")
-                .body(Body::from(payload.to_string()))
-                .unwrap(),
-        )
-        .await
-        .expect("create run response");
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::post("/v1/runs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(payload.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .expect("create run response");
 
-    assert_eq!(response.status(), axum::http::StatusCode::CREATED);
-    let body_bytes = read_body_bytes(response.into_body()).await;
-    let created: RunCreateResponse =
-        serde_json::from_slice(&body_bytes).expect("parse create response");
-    let run_id = created.run.run_id;
+        assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+        let body_bytes = read_body_bytes(response.into_body()).await;
+        let created: RunCreateResponse =
+            serde_json::from_slice(&body_bytes).expect("parse create response");
+        let run_id = created.run.run_id;
 
-    let response = app
-        .oneshot(
-            axum::http::Request::get(format!("/v1/runs/{}", run_id))
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .expect("get run response");
+        let response = app
+            .oneshot(
+                axum::http::Request::get(format!("/v1/runs/{}", run_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("get run response");
 
-    assert_eq!(response.status(), axum::http::StatusCode::OK);
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+    }
 }
 
 #[tokio::test]
 async fn list_events_json_fallback() {
-    let runtime = Arc::new(InMemoryRuntime::new());
-    runtime.register_workflow(Arc::new(TestWorkflow)).await;
-    let app = router(runtime);
-
-    let payload = json!({
-        "workflow": { "name": "echo", "version": "0.1.0" },
-        "input": { "hello": "events" }
-    });
-
-    let response = app
-        .clone()
-        .oneshot(
-            axum::http::Request::post("/v1/runs")
-                .header("content-type", "application/json")
-                .body(Body::from(payload.to_string()))
-                .unwrap(),
-        )
-        .await
-        .expect("create run response");
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime);
 
-    let body_bytes = read_body_bytes(response.into_body()).await;
-    let created: RunCreateResponse =
-        serde_json::from_slice(&body_bytes).expect("parse create response");
-    let run_id = created.run.run_id;
+        let payload = json!({
+            "workflow": { "name": "echo", "version": "0.1.0" },
+            "input": { "hello": "events" }
+        });
 
-    let mut events = EventListResponse {
-        data: Vec::new(),
-        next_cursor: None,
-    };
-    for _ in 0..5 {
         let response = app
             .clone()
             .oneshot(
-                axum::http::Request::get(format!("/v1/runs/{}/events", run_id))
-                    .body(Body::empty())
+                axum::http::Request::post("/v1/runs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(payload.to_string()))
                     .unwrap(),
             )
             .await
-            .expect("events response");
+            .expect("create run response");
 
-        assert_eq!(response.status(), axum::http::StatusCode::OK);
         let body_bytes = read_body_bytes(response.into_body()).await;
-        events = serde_json::from_slice(&body_bytes).expect("parse events response");
-        if !events.data.is_empty() {
-            break;
+        let created: RunCreateResponse =
+            serde_json::from_slice(&body_bytes).expect("parse create response");
+        let run_id = created.run.run_id;
+
+        let mut events = EventListResponse {
+            data: Vec::new(),
+            next_cursor: None,
+        };
+        for _ in 0..5 {
+            let response = app
+                .clone()
+                .oneshot(
+                    axum::http::Request::get(format!("/v1/runs/{}/events", run_id))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .expect("events response");
+
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let body_bytes = read_body_bytes(response.into_body()).await;
+            events = serde_json::from_slice(&body_bytes).expect("parse events response");
+            if !events.data.is_empty() {
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
         }
-        sleep(Duration::from_millis(20)).await;
+        assert!(!events.data.is_empty());
     }
-    assert!(!events.data.is_empty());
 }
 
 #[tokio::test]
 async fn stream_events_sse() {
-    let runtime = Arc::new(InMemoryRuntime::new());
-    runtime.register_workflow(Arc::new(TestWorkflow)).await;
-    let app = router(runtime);
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime);
 
-    let payload = json!({
-        "workflow": { "name": "echo", "version": "0.1.0" },
-        "input": { "hello": "sse" }
-    });
+        let payload = json!({
+            "workflow": { "name": "echo", "version": "0.1.0" },
+            "input": { "hello": "sse" }
+        });
 
-    let response = app
-        .clone()
-        .oneshot(
-            axum::http::Request::post("/v1/runs")
-                .header("content-type", "application/json")
-                .body(Body::from(payload.to_string()))
-                .unwrap(),
-        )
-        .await
-        .expect("create run response");
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::post("/v1/runs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(payload.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .expect("create run response");
 
-    let body_bytes = read_body_bytes(response.into_body()).await;
-    let created: RunCreateResponse =
-        serde_json::from_slice(&body_bytes).expect("parse create response");
-    let run_id = created.run.run_id;
+        let body_bytes = read_body_bytes(response.into_body()).await;
+        let created: RunCreateResponse =
+            serde_json::from_slice(&body_bytes).expect("parse create response");
+        let run_id = created.run.run_id;
 
-    let response = app
-        .oneshot(
-            axum::http::Request::get(format!("/v1/runs/{}/events", run_id))
-                .header("accept", "text/event-stream")
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .expect("sse response");
+        let response = app
+            .oneshot(
+                axum::http::Request::get(format!("/v1/runs/{}/events", run_id))
+                    .header("accept", "text/event-stream")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("sse response");
 
-    assert_eq!(response.status(), axum::http::StatusCode::OK);
-    let chunk = read_first_body_frame(response.into_body()).await;
-    let chunk_str = String::from_utf8_lossy(&chunk);
-    assert!(chunk_str.contains("event:"));
-    assert!(chunk_str.contains("data:"));
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let chunk = read_first_body_frame(response.into_body()).await;
+        let chunk_str = String::from_utf8_lossy(&chunk);
+        assert!(chunk_str.contains("event:"));
+        assert!(chunk_str.contains("data:"));
+    }
 }
 
 #[tokio::test]
 async fn create_run_unknown_workflow_returns_400() {
-    let runtime = Arc::new(InMemoryRuntime::new());
-    let app = router(runtime);
+    for runtime in backends() {
+        let app = router(runtime);
 
-    let payload = json!({
-        "workflow": { "name": "missing", "version": "0.1.0" },
-        "input": { "hello": "world" }
-    });
+        let payload = json!({
+            "workflow": { "name": "missing", "version": "0.1.0" },
+            "input": { "hello": "world" }
+        });
 
-    let response = app
-        .oneshot(
-            axum::http::Request::post("/v1/runs")
-                .header("content-type", "application/json")
-                .body(Body::from(payload.to_string()))
-                .unwrap(),
-        )
-        .await
-        .expect("create run response");
+        let response = app
+            .oneshot(
+                axum::http::Request::post("/v1/runs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(payload.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .expect("create run response");
 
-    assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    }
 }
 
 #[tokio::test]
 async fn get_run_not_found_returns_404() {
-    let runtime = Arc::new(InMemoryRuntime::new());
-    let app = router(runtime);
+    for runtime in backends() {
+        let app = router(runtime);
 
-    let response = app
-        .oneshot(
-            axum::http::Request::get("/v1/runs/run_missing")
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .expect("get run response");
+        let response = app
+            .oneshot(
+                axum::http::Request::get("/v1/runs/run_missing")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("get run response");
 
-    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    }
 }
 
 #[tokio::test]
 async fn get_artifact_not_found_returns_404() {
-    let runtime = Arc::new(InMemoryRuntime::new());
-    let app = router(runtime);
+    for runtime in backends() {
+        let app = router(runtime);
 
-    let response = app
-        .oneshot(
-            axum::http::Request::get("/v1/artifacts/art_missing")
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .expect("get artifact response");
+        let response = app
+            .oneshot(
+                axum::http::Request::get("/v1/artifacts/art_missing")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("get artifact response");
 
-    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    }
 }
 
 #[tokio::test]
 async fn list_workflows_returns_registered() {
-    let runtime = Arc::new(InMemoryRuntime::new());
-    runtime.register_workflow(Arc::new(TestWorkflow)).await;
-    let app = router(runtime);
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime);
 
-    let response = app
-        .oneshot(
-            axum::http::Request::get("/v1/workflows")
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .expect("list workflows response");
+        let response = app
+            .oneshot(
+                axum::http::Request::get("/v1/workflows")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("list workflows response");
 
-    assert_eq!(response.status(), axum::http::StatusCode::OK);
-    let body_bytes = read_body_bytes(response.into_body()).await;
-    let list: WorkflowListResponse =
-        serde_json::from_slice(&body_bytes).expect("parse workflow list");
-    assert!(!list.data.is_empty());
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body_bytes = read_body_bytes(response.into_body()).await;
+        let list: WorkflowListResponse =
+            serde_json::from_slice(&body_bytes).expect("parse workflow list");
+        assert!(!list.data.is_empty());
+    }
 }
 
 #[tokio::test]
 async fn get_workflow_returns_registered() {
-    let runtime = Arc::new(InMemoryRuntime::new());
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime);
+
+        let response = app
+            .oneshot(
+                axum::http::Request::get("/v1/workflows/echo")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("get workflow response");
+
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn get_workflow_schemas_returns_schemas() {
+    for runtime in backends() {
+        runtime
+            .register_workflow_with_schemas(
+                Arc::new(TestWorkflow),
+                Some(json!({ "type": "object" })),
+                Some(json!({ "type": "object" })),
+            )
+            .await;
+        let app = router(runtime);
+
+        let response = app
+            .oneshot(
+                axum::http::Request::get("/v1/workflows/echo/schemas")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("get schema response");
+
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body_bytes = read_body_bytes(response.into_body()).await;
+        let payload: serde_json::Value =
+            serde_json::from_slice(&body_bytes).expect("parse schema response");
+        assert!(payload.get("schemas").is_some());
+    }
+}
+
+fn echo_input_schema() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "hello": { "type": "string" },
+            "count": { "type": "integer", "minimum": 0 }
+        },
+        "required": ["hello"]
+    })
+}
+
+#[tokio::test]
+async fn create_run_invalid_input_returns_400() {
+    for runtime in backends() {
+        runtime
+            .register_workflow_with_schemas(Arc::new(TestWorkflow), Some(echo_input_schema()), None)
+            .await;
+        let app = router(runtime.clone());
+
+        let payload = json!({
+            "workflow": { "name": "echo" },
+            "input": { "count": -1 }
+        });
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::post("/v1/runs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(payload.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .expect("create run response");
+
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        let body_bytes = read_body_bytes(response.into_body()).await;
+        let error: serde_json::Value = serde_json::from_slice(&body_bytes).expect("parse error");
+        assert_eq!(error["code"], "input_validation_failed");
+        let mut paths: Vec<&str> = error["details"]["errors"]
+            .as_array()
+            .expect("errors")
+            .iter()
+            .map(|err| err["path"].as_str().expect("path"))
+            .collect();
+        paths.sort();
+        assert_eq!(paths, ["", "/count"]);
+        let page = runtime.list_runs(Default::default()).await.expect("list runs");
+        assert!(page.data.is_empty());
+
+        post_run(&app, json!({ "workflow": { "name": "echo" }, "input": { "hello": "x" } })).await;
+    }
+}
+
+#[tokio::test]
+async fn input_validation_can_be_disabled() {
+    for runtime in backends() {
+        runtime
+            .register_workflow_with_options(
+                Arc::new(TestWorkflow),
+                Some(echo_input_schema()),
+                None,
+                WorkflowOptions {
+                    validate_input: false,
+                    ..WorkflowOptions::default()
+                },
+            )
+            .await;
+        let app = router(runtime.clone());
+
+        let run_id = post_run(&app, json!({ "workflow": { "name": "echo" }, "input": 42 })).await;
+        wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+    }
+}
+
+async fn register_versions(runtime: &InMemoryRuntime) {
+    for (version, active) in [("1.0.0", true), ("1.2.0", true), ("2.0.0", false)] {
+        runtime
+            .register_workflow_with_options(
+                Arc::new(VersionedWorkflow(version)),
+                None,
+                None,
+                WorkflowOptions {
+                    active,
+                    ..WorkflowOptions::default()
+                },
+            )
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn create_run_resolves_requested_version() {
+    for runtime in backends() {
+        register_versions(&runtime).await;
+        let app = router(runtime.clone());
+
+        let cases = [
+            (None, "1.2.0"),
+            (Some("latest"), "1.2.0"),
+            (Some("1.0.0"), "1.0.0"),
+            (Some("~1.0"), "1.0.0"),
+            (Some("^1"), "1.2.0"),
+            // Inactive versions only run when pinned exactly.
+            (Some("v2.0.0"), "2.0.0"),
+        ];
+        for (requested, expected) in cases {
+            let run_id = post_run(
+                &app,
+                json!({ "workflow": { "name": "versioned", "version": requested }, "input": {} }),
+            )
+            .await;
+            let run = wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+            assert_eq!(run.workflow.version.as_deref(), Some(expected));
+            assert_eq!(run.output, Some(json!({ "version": expected })));
+        }
+    }
+}
+
+#[tokio::test]
+async fn create_run_unknown_version_returns_400() {
+    for runtime in backends() {
+        register_versions(&runtime).await;
+        let app = router(runtime);
+
+        for requested in ["3.0.0", "^2", "not a version"] {
+            let payload = json!({
+                "workflow": { "name": "versioned", "version": requested },
+                "input": {}
+            });
+            let response = app
+                .clone()
+                .oneshot(
+                    axum::http::Request::post("/v1/runs")
+                        .header("content-type", "application/json")
+                        .body(Body::from(payload.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .expect("create run response");
+
+            assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+            let body_bytes = read_body_bytes(response.into_body()).await;
+            let error: serde_json::Value =
+                serde_json::from_slice(&body_bytes).expect("parse error");
+            assert_eq!(error["code"], "workflow_version_not_found");
+            assert_eq!(error["details"]["available"].as_array().unwrap().len(), 3);
+        }
+    }
+}
+
+#[tokio::test]
+async fn get_workflow_lists_versions() {
+    for runtime in backends() {
+        register_versions(&runtime).await;
+        let app = router(runtime);
+
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::get("/v1/workflows/versioned")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("get workflow response");
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body_bytes = read_body_bytes(response.into_body()).await;
+        let workflow: serde_json::Value =
+            serde_json::from_slice(&body_bytes).expect("parse workflow");
+        assert_eq!(workflow["version"], "1.2.0");
+        assert_eq!(
+            workflow["versions"],
+            json!([
+                { "version": "2.0.0", "active": false },
+                { "version": "1.2.0", "active": true },
+                { "version": "1.0.0", "active": true },
+            ])
+        );
+
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::get("/v1/workflows/versioned?version=2.0.0")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("get workflow response");
+        let body_bytes = read_body_bytes(response.into_body()).await;
+        let workflow: serde_json::Value =
+            serde_json::from_slice(&body_bytes).expect("parse workflow");
+        assert_eq!(workflow["version"], "2.0.0");
+
+        for path in ["/v1/workflows/versioned", "/v1/workflows/versioned/schemas"] {
+            let uri = format!("{}?version=1.0.0&version=2.0.0", path);
+            let (status, error) = get_json(&app, &uri).await;
+            assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+            assert_eq!(error["code"], "invalid_query");
+        }
+    }
+}
+
+#[tokio::test]
+async fn sqlite_store_survives_restart() {
+    std::fs::create_dir_all("target/tmp").expect("create tmp dir");
+    let path = format!("target/tmp/run_store_{}.sqlite", Uuid::new_v4());
+
+    let runtime = Arc::new(InMemoryRuntime::with_store(Arc::new(
+        SqliteRunStore::open(&path).expect("open sqlite store"),
+    )));
     runtime.register_workflow(Arc::new(TestWorkflow)).await;
-    let app = router(runtime);
+    let run = runtime
+        .create_run(RunCreateRequest {
+            workflow: WorkflowRef {
+                name: "echo".to_string(),
+                version: None,
+            },
+            input: json!({ "hello": "restart" }),
+            context: None,
+            metadata: None,
+            labels: None,
+        })
+        .await
+        .expect("create run");
+    wait_for_status(&runtime, &run.run_id, RunStatus::Succeeded).await;
+    drop(runtime);
 
-    let response = app
+    let reopened = Arc::new(InMemoryRuntime::with_store(Arc::new(
+        SqliteRunStore::open(&path).expect("reopen sqlite store"),
+    )));
+    let restored = reopened.get_run(&run.run_id).await.expect("run persisted");
+    assert_eq!(restored.status, RunStatus::Succeeded);
+    let events = reopened.list_events(&run.run_id).await.expect("events persisted");
+    assert!(!events.is_empty());
+    let artifact_id = &restored.artifacts[0].artifact_id;
+    assert!(reopened.get_artifact(artifact_id).await.is_some());
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn sqlite_store_upgrades_name_keyed_workflows_table() {
+    std::fs::create_dir_all("target/tmp").expect("create tmp dir");
+    let path = format!("target/tmp/run_store_{}.sqlite", Uuid::new_v4());
+    rusqlite::Connection::open(&path)
+        .expect("open sqlite file")
+        .execute_batch("CREATE TABLE workflows (name TEXT PRIMARY KEY, body TEXT NOT NULL);")
+        .expect("create old workflows table");
+
+    let store = Arc::new(SqliteRunStore::open(&path).expect("open sqlite store"));
+    let runtime = InMemoryRuntime::with_store(store.clone());
+    runtime.register_workflow(Arc::new(TestWorkflow)).await;
+    let saved = store.list_workflows().await.expect("list workflows");
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].version.as_deref(), Some("0.1.0"));
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn cancel_run_marks_run_canceled() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(SlowWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let run_id = post_run(&app, json!({ "workflow": { "name": "slow" }, "input": {} })).await;
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::post(format!("/v1/runs/{}/cancel", run_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("cancel response");
+        assert_eq!(response.status(), axum::http::StatusCode::ACCEPTED);
+
+        let run = wait_for_status(&runtime, &run_id, RunStatus::Canceled).await;
+        assert_eq!(run.error.expect("cancel error").code, "canceled");
+        let events = runtime.list_events(&run_id).await.expect("events");
+        assert!(events
+            .iter()
+            .any(|event| matches!(event.event_type, EventType::RunCanceled)));
+    }
+}
+
+#[tokio::test]
+async fn cancel_finished_run_returns_409() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let run_id = post_run(&app, json!({ "workflow": { "name": "echo" }, "input": {} })).await;
+        wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+        let response = app
+            .oneshot(
+                axum::http::Request::post(format!("/v1/runs/{}/cancel", run_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("cancel response");
+        assert_eq!(response.status(), axum::http::StatusCode::CONFLICT);
+    }
+}
+
+#[tokio::test]
+async fn run_exceeding_timeout_is_timed_out() {
+    for runtime in backends() {
+        runtime
+            .register_workflow_with_options(
+                Arc::new(SlowWorkflow),
+                None,
+                None,
+                WorkflowOptions {
+                    timeout: Some(Duration::from_secs(5)),
+                    ..WorkflowOptions::default()
+                },
+            )
+            .await;
+        let app = router(runtime.clone());
+
+        // The per-run override wins over the registered default.
+        let run_id = post_run(
+            &app,
+            json!({
+                "workflow": { "name": "slow" },
+                "input": {},
+                "metadata": { "timeout_ms": 50 }
+            }),
+        )
+        .await;
+
+        let run = wait_for_status(&runtime, &run_id, RunStatus::TimedOut).await;
+        let error = run.error.expect("timeout error");
+        assert_eq!(error.code, "timeout");
+        assert!(error.retryable);
+        let events = runtime.list_events(&run_id).await.expect("events");
+        assert!(matches!(
+            events.last().map(|event| &event.event_type),
+            Some(EventType::RunFailed)
+        ));
+    }
+}
+
+#[tokio::test]
+async fn create_run_invalid_timeout_returns_400() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(SlowWorkflow)).await;
+        let app = router(runtime);
+
+        let payload = json!({
+            "workflow": { "name": "slow" },
+            "input": {},
+            "metadata": { "timeout_ms": "soon" }
+        });
+        let response = app
+            .oneshot(
+                axum::http::Request::post("/v1/runs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(payload.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .expect("create run response");
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn retryable_failures_are_retried() {
+    for runtime in backends() {
+        runtime
+            .register_workflow_with_options(
+                Arc::new(FlakyWorkflow::new(2, true)),
+                None,
+                None,
+                fast_retry(3),
+            )
+            .await;
+        let app = router(runtime.clone());
+
+        let run_id = post_run(&app, json!({ "workflow": { "name": "flaky" }, "input": {} })).await;
+        let run = wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+        assert_eq!(run.attempts, 3);
+
+        let events = runtime.list_events(&run_id).await.expect("events");
+        let attempts = |event_type: fn(&EventType) -> bool| -> Vec<u64> {
+            events
+                .iter()
+                .filter(|event| event_type(&event.event_type))
+                .filter_map(|event| event.payload.get("attempt").and_then(|v| v.as_u64()))
+                .collect()
+        };
+        assert_eq!(attempts(|t| matches!(t, EventType::StepStarted)), vec![1, 2, 3]);
+        assert_eq!(attempts(|t| matches!(t, EventType::StepFailed)), vec![1, 2]);
+        let started: Vec<&str> = events
+            .iter()
+            .filter(|event| event.event_type == EventType::StepStarted)
+            .filter_map(|event| event.step_id.as_deref())
+            .collect();
+        assert_eq!(started, ["workflow.run#1", "workflow.run#2", "workflow.run#3"]);
+    }
+}
+
+#[tokio::test]
+async fn fatal_failures_are_not_retried() {
+    for runtime in backends() {
+        runtime
+            .register_workflow_with_options(
+                Arc::new(FlakyWorkflow::new(1, false)),
+                None,
+                None,
+                fast_retry(3),
+            )
+            .await;
+        let app = router(runtime.clone());
+
+        let run_id = post_run(&app, json!({ "workflow": { "name": "flaky" }, "input": {} })).await;
+        let run = wait_for_status(&runtime, &run_id, RunStatus::Failed).await;
+        assert_eq!(run.attempts, 1);
+        assert!(!run.error.expect("run error").retryable);
+    }
+}
+
+async fn post_run_with_key(
+    app: &axum::Router,
+    key: &str,
+    payload: &serde_json::Value,
+) -> axum::response::Response {
+    app.clone()
         .oneshot(
-            axum::http::Request::get("/v1/workflows/echo")
-                .body(Body::empty())
+            axum::http::Request::post("/v1/runs")
+                .header("content-type", "application/json")
+                .header("idempotency-key", key)
+                .body(Body::from(payload.to_string()))
                 .unwrap(),
         )
         .await
-        .expect("get workflow response");
+        .expect("create run response")
+}
 
-    assert_eq!(response.status(), axum::http::StatusCode::OK);
+#[tokio::test]
+async fn idempotency_key_replays_original_run() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime);
+        let payload = json!({
+            "workflow": { "name": "echo" },
+            "input": { "hello": "cron" },
+            "labels": { "store_id": "sh01", "trigger": "cron" }
+        });
+
+        let first = post_run_with_key(&app, "cron-sh01-2025-01-01", &payload).await;
+        assert_eq!(first.status(), axum::http::StatusCode::CREATED);
+        assert!(first.headers().get("idempotent-replayed").is_none());
+        let first: RunCreateResponse =
+            serde_json::from_slice(&read_body_bytes(first.into_body()).await).expect("parse");
+
+        let second = post_run_with_key(&app, "cron-sh01-2025-01-01", &payload).await;
+        assert_eq!(second.status(), axum::http::StatusCode::CREATED);
+        assert_eq!(second.headers().get("idempotent-replayed").unwrap(), "true");
+        let second: RunCreateResponse =
+            serde_json::from_slice(&read_body_bytes(second.into_body()).await).expect("parse");
+        assert_eq!(first.run.run_id, second.run.run_id);
+    }
 }
 
 #[tokio::test]
-async fn get_workflow_schemas_returns_schemas() {
-    let runtime = Arc::new(InMemoryRuntime::new());
-    runtime
-        .register_workflow_with_schemas(
-            Arc::new(TestWorkflow),
-            Some(json!({ "type": "object" })),
-            Some(json!({ "type": "object" })),
+async fn idempotency_key_with_different_body_returns_409() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime);
+
+        let first = json!({ "workflow": { "name": "echo" }, "input": { "store_id": "sh01" } });
+        let response = post_run_with_key(&app, "key-1", &first).await;
+        assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+
+        let second = json!({ "workflow": { "name": "echo" }, "input": { "store_id": "sz02" } });
+        let response = post_run_with_key(&app, "key-1", &second).await;
+        assert_eq!(response.status(), axum::http::StatusCode::CONFLICT);
+        let error: serde_json::Value =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).expect("parse");
+        assert_eq!(error["code"], "idempotency_conflict");
+    }
+}
+
+async fn list_runs(app: &axum::Router, uri: &str) -> (axum::http::StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(axum::http::Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .expect("list runs response");
+    let status = response.status();
+    let body = serde_json::from_slice(&read_body_bytes(response.into_body()).await).expect("parse");
+    (status, body)
+}
+
+fn run_ids(page: &serde_json::Value) -> Vec<String> {
+    page["data"]
+        .as_array()
+        .expect("data array")
+        .iter()
+        .map(|run| run["run_id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn list_runs_applies_filters() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        runtime
+            .register_workflow(Arc::new(FlakyWorkflow::new(u32::MAX, false)))
+            .await;
+        let app = router(runtime.clone());
+        let labeled = |workflow: &str, store_id: &str| {
+            json!({
+                "workflow": { "name": workflow },
+                "input": {},
+                "labels": { "store_id": store_id }
+            })
+        };
+
+        let echo_sh01 = post_run(&app, labeled("echo", "sh01")).await;
+        let echo_sz02 = post_run(&app, labeled("echo", "sz02")).await;
+        let flaky_sh01 = post_run(&app, labeled("flaky", "sh01")).await;
+        wait_for_status(&runtime, &echo_sh01, RunStatus::Succeeded).await;
+        wait_for_status(&runtime, &echo_sz02, RunStatus::Succeeded).await;
+        wait_for_status(&runtime, &flaky_sh01, RunStatus::Failed).await;
+
+        let (status, page) = list_runs(&app, "/v1/runs").await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!(run_ids(&page), vec![flaky_sh01.clone(), echo_sz02, echo_sh01.clone()]);
+
+        let (_, page) = list_runs(&app, "/v1/runs?label_selector=store_id%3Dsh01").await;
+        assert_eq!(run_ids(&page), vec![flaky_sh01.clone(), echo_sh01.clone()]);
+
+        let (_, page) = list_runs(&app, "/v1/runs?status=failed&workflow_name=flaky").await;
+        assert_eq!(run_ids(&page), vec![flaky_sh01]);
+
+        let uri = "/v1/runs?workflow_name=echo&label_selector=store_id%3Dsh01";
+        let (_, page) = list_runs(&app, uri).await;
+        assert_eq!(run_ids(&page), vec![echo_sh01]);
+        assert!(page.get("next_cursor").is_none());
+
+        let (_, page) = list_runs(&app, "/v1/runs?created_after=2999-01-01T00:00:00Z").await;
+        assert!(run_ids(&page).is_empty());
+
+        let (status, error) = list_runs(&app, "/v1/runs?label_selector=store_id").await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "invalid_label_selector");
+    }
+}
+
+#[tokio::test]
+async fn list_runs_paginates_with_cursor() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime);
+
+        let mut created = Vec::new();
+        for _ in 0..5 {
+            let payload = json!({ "workflow": { "name": "echo" }, "input": {} });
+            created.push(post_run(&app, payload).await);
+        }
+        created.reverse();
+
+        let mut seen = Vec::new();
+        let mut uri = "/v1/runs?limit=2".to_string();
+        loop {
+            let (status, page) = list_runs(&app, &uri).await;
+            assert_eq!(status, axum::http::StatusCode::OK);
+            assert!(run_ids(&page).len() <= 2);
+            seen.extend(run_ids(&page));
+            match page["next_cursor"].as_str() {
+                Some(cursor) => uri = format!("/v1/runs?limit=2&cursor={}", cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, created);
+
+        let (status, error) = list_runs(&app, "/v1/runs?cursor=not-a-cursor").await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "invalid_cursor");
+    }
+}
+
+#[tokio::test]
+async fn run_keeps_labels_and_metadata() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime);
+        let run_id = post_run(
+            &app,
+            json!({
+                "workflow": { "name": "echo" },
+                "input": {},
+                "metadata": { "requested_by": "ops@example.com", "batch": 7 },
+                "labels": { "store_id": "sh01", "trigger": "manual" }
+            }),
         )
         .await;
-    let app = router(runtime);
 
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::get(format!("/v1/runs/{}", run_id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("get run response");
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let run: serde_json::Value =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).expect("parse");
+        assert_eq!(run["labels"], json!({ "store_id": "sh01", "trigger": "manual" }));
+        assert_eq!(run["metadata"], json!({ "requested_by": "ops@example.com", "batch": 7 }));
+    }
+}
+
+async fn post_checkpoint(
+    app: &axum::Router,
+    run_id: &str,
+    checkpoint_id: &str,
+    action: &str,
+    body: serde_json::Value,
+) -> (axum::http::StatusCode, serde_json::Value) {
+    let uri = format!("/v1/runs/{}/human/{}/{}", run_id, checkpoint_id, action);
     let response = app
+        .clone()
         .oneshot(
-            axum::http::Request::get("/v1/workflows/echo/schemas")
-                .body(Body::empty())
+            axum::http::Request::post(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
                 .unwrap(),
         )
         .await
-        .expect("get schema response");
+        .expect("checkpoint response");
+    let status = response.status();
+    let body = serde_json::from_slice(&read_body_bytes(response.into_body()).await).expect("parse");
+    (status, body)
+}
 
-    assert_eq!(response.status(), axum::http::StatusCode::OK);
-    let body_bytes = read_body_bytes(response.into_body()).await;
-    let payload: serde_json::Value =
-        serde_json::from_slice(&body_bytes).expect("parse schema response");
-    assert!(payload.get("schemas").is_some());
+#[tokio::test]
+async fn approved_checkpoint_resumes_run() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let payload = json!({ "workflow": { "name": "approval" }, "input": {} });
+        let run_id = post_run(&app, payload).await;
+        let run = wait_for_status(&runtime, &run_id, RunStatus::WaitingHuman).await;
+        assert_eq!(run.checkpoints.len(), 1);
+        let checkpoint_id = run.checkpoints[0].checkpoint_id.clone();
+        assert_eq!(run.checkpoints[0].title.as_deref(), Some("Send the prebrief?"));
+
+        let (status, checkpoint) =
+            post_checkpoint(&app, &run_id, &checkpoint_id, "approve", json!({ "note": "ok" }))
+                .await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!(checkpoint["status"], "approved");
+        assert_eq!(checkpoint["decision"]["approved"], true);
+
+        let run = wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+        assert_eq!(run.output, Some(json!({ "note": "ok", "input": null })));
+        let events = runtime.list_events(&run_id).await.expect("events");
+        assert!(events.iter().any(|event| matches!(event.event_type, EventType::HitlRequired)));
+        assert!(events.iter().any(|event| matches!(event.event_type, EventType::HitlResolved)));
+
+        let (status, error) =
+            post_checkpoint(&app, &run_id, &checkpoint_id, "reject", json!({})).await;
+        assert_eq!(status, axum::http::StatusCode::CONFLICT);
+        assert_eq!(error["code"], "checkpoint_already_resolved");
+    }
+}
+
+#[tokio::test]
+async fn checkpoint_input_and_rejection() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let app = router(runtime.clone());
+        let payload = json!({ "workflow": { "name": "approval" }, "input": {} });
+
+        let provided = post_run(&app, payload.clone()).await;
+        let run = wait_for_status(&runtime, &provided, RunStatus::WaitingHuman).await;
+        let checkpoint_id = run.checkpoints[0].checkpoint_id.clone();
+        let body = json!({ "input": { "store_id": "sh01" } });
+        let (status, checkpoint) =
+            post_checkpoint(&app, &provided, &checkpoint_id, "provide_input", body).await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!(checkpoint["status"], "input_provided");
+        let run = wait_for_status(&runtime, &provided, RunStatus::Succeeded).await;
+        assert_eq!(run.output.unwrap()["input"], json!({ "store_id": "sh01" }));
+
+        let rejected = post_run(&app, payload).await;
+        let run = wait_for_status(&runtime, &rejected, RunStatus::WaitingHuman).await;
+        let checkpoint_id = run.checkpoints[0].checkpoint_id.clone();
+        let (status, _) =
+            post_checkpoint(&app, &rejected, &checkpoint_id, "reject", json!({})).await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        let run = wait_for_status(&runtime, &rejected, RunStatus::Failed).await;
+        assert_eq!(run.checkpoints[0].status, HumanCheckpointStatus::Rejected);
+
+        let (status, error) =
+            post_checkpoint(&app, &rejected, "hcp_missing", "approve", json!({})).await;
+        assert_eq!(status, axum::http::StatusCode::NOT_FOUND);
+        assert_eq!(error["code"], "not_found");
+    }
+}
+
+#[tokio::test]
+async fn checkpoints_close_when_they_expire_or_the_run_ends() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(ExpiringWorkflow)).await;
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let payload = json!({ "workflow": { "name": "expiring" }, "input": {} });
+        let expired = post_run(&app, payload).await;
+        let run = wait_for_status(&runtime, &expired, RunStatus::Failed).await;
+        assert_eq!(run.checkpoints[0].status, HumanCheckpointStatus::Expired);
+        assert!(run.checkpoints[0].decision.is_none());
+
+        let payload = json!({ "workflow": { "name": "approval" }, "input": {} });
+        let canceled = post_run(&app, payload).await;
+        let run = wait_for_status(&runtime, &canceled, RunStatus::WaitingHuman).await;
+        let checkpoint_id = run.checkpoints[0].checkpoint_id.clone();
+        runtime.cancel_run(&canceled).await.expect("cancel");
+        let run = wait_for_status(&runtime, &canceled, RunStatus::Canceled).await;
+        assert_eq!(run.checkpoints[0].status, HumanCheckpointStatus::Canceled);
+        let (status, error) =
+            post_checkpoint(&app, &canceled, &checkpoint_id, "approve", json!({})).await;
+        assert_eq!(status, axum::http::StatusCode::CONFLICT);
+        assert_eq!(error["code"], "checkpoint_already_resolved");
+        let events = runtime.list_events(&canceled).await.expect("events");
+        assert!(events.iter().all(|event| event.event_type != EventType::HitlResolved));
+    }
+}
+
+#[tokio::test]
+async fn run_context_reports_steps_tools_and_artifacts() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(StagedWorkflow)).await;
+        let app = router(runtime.clone());
+        let payload = json!({
+            "workflow": { "name": "staged" },
+            "input": {},
+            "context": { "channel": "wecom" },
+            "labels": { "store_id": "sh01" }
+        });
+
+        let run_id = post_run(&app, payload).await;
+        let run = wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+        let output = run.output.expect("output");
+        assert_eq!(output["run_id"], run_id.as_str());
+        assert_eq!(output["trace_id"], run.trace_id.as_deref().expect("trace id"));
+        assert_eq!(output["context"], json!({ "channel": "wecom" }));
+        assert_eq!(output["labels"], json!({ "store_id": "sh01" }));
+        assert_eq!(run.artifacts.len(), 1);
+        let cost = run.cost.expect("cost");
+        assert_eq!(
+            (cost.prompt_tokens, cost.completion_tokens, cost.total_tokens),
+            (12, 5, 17)
+        );
+
+        let events = runtime.list_events(&run_id).await.expect("events");
+        let runner_events: Vec<(String, Option<String>, Option<String>)> = events
+            .iter()
+            .filter(|event| {
+                !event.step_id.as_deref().is_some_and(|step| step.starts_with("workflow.run#"))
+            })
+            .map(|event| {
+                let event_type = serde_json::to_value(&event.event_type).unwrap();
+                (
+                    event_type.as_str().unwrap().to_string(),
+                    event.step_id.clone(),
+                    event.tool_name.clone(),
+                )
+            })
+            .collect();
+        let step = |id: &str| Some(id.to_string());
+        let tool = Some("mysql.query".to_string());
+        assert_eq!(
+            runner_events,
+            vec![
+                ("run.started".to_string(), None, None),
+                ("step.started".to_string(), step("fetch"), None),
+                ("tool.called".to_string(), step("fetch"), tool.clone()),
+                ("tool.result".to_string(), step("fetch"), tool),
+                ("step.completed".to_string(), step("fetch"), None),
+                ("llm.request".to_string(), step("summarize"), None),
+                ("llm.response".to_string(), step("summarize"), None),
+                ("artifact.created".to_string(), None, None),
+                ("run.completed".to_string(), None, None),
+            ]
+        );
+    }
+}
+
+#[tokio::test]
+async fn output_schema_violation_fails_run() {
+    for runtime in backends() {
+        let output_schema = json!({
+            "type": "object",
+            "properties": {
+                "echo": {
+                    "type": "object",
+                    "properties": { "hello": { "type": "string" } }
+                }
+            },
+            "required": ["echo"]
+        });
+        runtime
+            .register_workflow_with_schemas(Arc::new(TestWorkflow), None, Some(output_schema))
+            .await;
+        let app = router(runtime.clone());
+
+        let payload = json!({ "workflow": { "name": "echo" }, "input": { "hello": 1 } });
+        let run_id = post_run(&app, payload).await;
+        let run = wait_for_status(&runtime, &run_id, RunStatus::Failed).await;
+        let error = run.error.expect("error");
+        assert_eq!(error.code, "output_validation_failed");
+        assert!(!error.retryable);
+        assert_eq!(error.details.expect("details")["errors"][0]["path"], "/echo/hello");
+        assert!(run.output.is_none());
+        assert!(run.artifacts.is_empty());
+
+        let events = runtime.list_events(&run_id).await.expect("events");
+        let tail: Vec<(serde_json::Value, Option<String>)> = events[events.len() - 3..]
+            .iter()
+            .map(|event| {
+                let event_type = serde_json::to_value(&event.event_type).unwrap();
+                (event_type, event.step_id.clone())
+            })
+            .collect();
+        let step = Some("workflow.validate_output".to_string());
+        assert_eq!(
+            tail,
+            vec![
+                (json!("step.started"), step.clone()),
+                (json!("step.failed"), step),
+                (json!("run.failed"), None),
+            ]
+        );
+
+        let payload = json!({ "workflow": { "name": "echo" }, "input": { "hello": "x" } });
+        let run_id = post_run(&app, payload).await;
+        wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+    }
+}
+
+/// Reads an SSE response to the end and returns the `id:` of every event.
+async fn read_sse_ids(app: &axum::Router, uri: &str, last_event_id: Option<&str>) -> Vec<String> {
+    let mut request = axum::http::Request::get(uri).header("accept", "text/event-stream");
+    if let Some(last_event_id) = last_event_id {
+        request = request.header("last-event-id", last_event_id);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .expect("sse response");
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    let body = timeout(Duration::from_secs(2), read_body_bytes(response.into_body()))
+        .await
+        .expect("stream closes after the terminal event");
+    String::from_utf8_lossy(&body)
+        .lines()
+        .filter_map(|line| line.strip_prefix("id:"))
+        .map(|id| id.trim().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn sse_replays_finished_run_and_resumes() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let payload = json!({ "workflow": { "name": "echo" }, "input": { "hello": "sse" } });
+        let run_id = post_run(&app, payload).await;
+        wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+        let stored: Vec<String> = runtime
+            .list_events(&run_id)
+            .await
+            .expect("events")
+            .into_iter()
+            .map(|event| event.event_id)
+            .collect();
+
+        let uri = format!("/v1/runs/{}/events", run_id);
+        assert_eq!(read_sse_ids(&app, &uri, None).await, stored);
+        assert_eq!(read_sse_ids(&app, &uri, Some(&stored[1])).await, stored[2..]);
+        let after = format!("{}?after={}", uri, stored[2]);
+        assert_eq!(read_sse_ids(&app, &after, None).await, stored[3..]);
+
+        let response = app
+            .clone()
+            .oneshot(axum::http::Request::get(after).body(Body::empty()).unwrap())
+            .await
+            .expect("list events response");
+        let body_bytes = read_body_bytes(response.into_body()).await;
+        let page: serde_json::Value = serde_json::from_slice(&body_bytes).expect("parse events");
+        let listed: Vec<&str> = page["data"]
+            .as_array()
+            .expect("events")
+            .iter()
+            .map(|event| event["event_id"].as_str().expect("event id"))
+            .collect();
+        assert_eq!(listed, stored[3..]);
+    }
+}
+
+#[tokio::test]
+async fn sse_switches_from_replay_to_live_events() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let payload = json!({ "workflow": { "name": "approval" }, "input": {} });
+        let run_id = post_run(&app, payload).await;
+        let run = wait_for_status(&runtime, &run_id, RunStatus::WaitingHuman).await;
+        let checkpoint_id = run.checkpoints[0].checkpoint_id.clone();
+
+        let uri = format!("/v1/runs/{}/events", run_id);
+        let reader = {
+            let app = app.clone();
+            tokio::spawn(async move { read_sse_ids(&app, &uri, None).await })
+        };
+        sleep(Duration::from_millis(50)).await;
+        let (status, _) =
+            post_checkpoint(&app, &run_id, &checkpoint_id, "approve", json!({})).await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+
+        let streamed = reader.await.expect("reader");
+        let stored: Vec<String> = runtime
+            .list_events(&run_id)
+            .await
+            .expect("events")
+            .into_iter()
+            .map(|event| event.event_id)
+            .collect();
+        assert_eq!(streamed, stored);
+    }
+}
+
+#[tokio::test]
+async fn lagging_sse_client_catches_up_from_stored_events() {
+    let sqlite = SqliteRunStore::open_in_memory().expect("open sqlite store");
+    let runtimes = [
+        InMemoryRuntime::new(),
+        InMemoryRuntime::with_store(Arc::new(sqlite)),
+    ];
+    for runtime in runtimes {
+        let runtime = Arc::new(runtime.with_event_buffer(2));
+        runtime.register_workflow(Arc::new(BurstWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let payload = json!({ "workflow": { "name": "burst" }, "input": {} });
+        let run_id = post_run(&app, payload).await;
+        let run = wait_for_status(&runtime, &run_id, RunStatus::WaitingHuman).await;
+        let checkpoint_id = run.checkpoints[0].checkpoint_id.clone();
+
+        // The stream is subscribed but not read while the run publishes far
+        // more events than the channel holds.
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::get(format!("/v1/runs/{}/events", run_id))
+                    .header("accept", "text/event-stream")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("sse response");
+        let (status, _) =
+            post_checkpoint(&app, &run_id, &checkpoint_id, "approve", json!({})).await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+
+        let body = timeout(Duration::from_secs(2), read_body_bytes(response.into_body()))
+            .await
+            .expect("stream closes after the terminal event");
+        let streamed: Vec<String> = String::from_utf8_lossy(&body)
+            .lines()
+            .filter_map(|line| line.strip_prefix("id:"))
+            .map(|id| id.trim().to_string())
+            .collect();
+        let stored: Vec<String> = runtime
+            .list_events(&run_id)
+            .await
+            .expect("events")
+            .into_iter()
+            .map(|event| event.event_id)
+            .collect();
+        assert!(stored.len() > 40);
+        assert_eq!(streamed, stored);
+    }
+}
+
+async fn get_json(app: &axum::Router, uri: &str) -> (axum::http::StatusCode, serde_json::Value) {
+    let response = app
+        .clone()
+        .oneshot(axum::http::Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .expect("response");
+    let status = response.status();
+    let body = serde_json::from_slice(&read_body_bytes(response.into_body()).await).expect("parse");
+    (status, body)
+}
+
+#[tokio::test]
+async fn list_events_paginates_and_filters() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(StagedWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let payload = json!({ "workflow": { "name": "staged" }, "input": {} });
+        let run_id = post_run(&app, payload).await;
+        wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+        let stored: Vec<String> = runtime
+            .list_events(&run_id)
+            .await
+            .expect("events")
+            .into_iter()
+            .map(|event| event.event_id)
+            .collect();
+
+        let base = format!("/v1/runs/{}/events", run_id);
+        let mut listed = Vec::new();
+        let mut uri = format!("{}?limit=4", base);
+        loop {
+            let (status, page) = get_json(&app, &uri).await;
+            assert_eq!(status, axum::http::StatusCode::OK);
+            let data = page["data"].as_array().expect("data");
+            assert!(data.len() <= 4);
+            listed.extend(data.iter().map(|event| event["event_id"].as_str().unwrap().to_string()));
+            match page["next_cursor"].as_str() {
+                Some(cursor) => uri = format!("{}?limit=4&cursor={}", base, cursor),
+                None => break,
+            }
+        }
+        assert_eq!(listed, stored);
+
+        let uri = format!("{}?types=step.started,step.completed&step_id=fetch", base);
+        let (_, page) = get_json(&app, &uri).await;
+        let filtered: Vec<(&str, &str)> = page["data"]
+            .as_array()
+            .expect("data")
+            .iter()
+            .map(|event| (event["type"].as_str().unwrap(), event["step_id"].as_str().unwrap()))
+            .collect();
+        assert_eq!(filtered, [("step.started", "fetch"), ("step.completed", "fetch")]);
+
+        for (query, code) in [
+            ("types=step.exploded", "invalid_event_type"),
+            ("cursor=%21%21", "invalid_cursor"),
+            ("limit=many", "invalid_query"),
+        ] {
+            let (status, error) = get_json(&app, &format!("{}?{}", base, query)).await;
+            assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+            assert_eq!(error["code"], code);
+        }
+        let (status, _) = get_json(&app, "/v1/runs/run_missing/events").await;
+        assert_eq!(status, axum::http::StatusCode::NOT_FOUND);
+    }
+}
+
+fn backends_with(
+    configure: impl Fn(InMemoryRuntime) -> InMemoryRuntime,
+) -> Vec<Arc<InMemoryRuntime>> {
+    let sqlite = SqliteRunStore::open_in_memory().expect("open sqlite store");
+    vec![
+        Arc::new(configure(InMemoryRuntime::new())),
+        Arc::new(configure(InMemoryRuntime::with_store(Arc::new(sqlite)))),
+    ]
+}
+
+#[tokio::test]
+async fn max_events_per_run_truncates_stored_events() {
+    let policy = RetentionPolicy {
+        max_events_per_run: Some(10),
+        ..RetentionPolicy::default()
+    };
+    for runtime in backends_with(|runtime| runtime.with_retention(policy.clone())) {
+        runtime.register_workflow(Arc::new(BurstWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let payload = json!({ "workflow": { "name": "burst" }, "input": {} });
+        let run_id = post_run(&app, payload).await;
+        let run = wait_for_status(&runtime, &run_id, RunStatus::WaitingHuman).await;
+        let checkpoint_id = run.checkpoints[0].checkpoint_id.clone();
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::get(format!("/v1/runs/{}/events", run_id))
+                    .header("accept", "text/event-stream")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("sse response");
+        post_checkpoint(&app, &run_id, &checkpoint_id, "approve", json!({})).await;
+        wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+
+        let events = runtime.list_events(&run_id).await.expect("events");
+        assert_eq!(events.len(), 11);
+        assert_eq!(events[9].event_type, EventType::EventsTruncated);
+        assert_eq!(events[9].payload, json!({ "max_events": 10 }));
+        assert_eq!(events[10].event_type, EventType::RunCompleted);
+        let dropped = runtime.retention_metrics().events_dropped;
+        assert!(dropped > 20);
+
+        // Live subscribers still see every event, plus the marker.
+        let body = timeout(Duration::from_secs(2), read_body_bytes(response.into_body()))
+            .await
+            .expect("stream closes after the terminal event");
+        let body = String::from_utf8_lossy(&body);
+        let streamed: Vec<&str> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("id:"))
+            .map(str::trim)
+            .collect();
+        assert_eq!(streamed.len() as u64, events.len() as u64 + dropped);
+
+        // A cursor pointing at an event that was never stored is rejected
+        // instead of silently restarting from the first event.
+        let gone = streamed
+            .iter()
+            .find(|id| events.iter().all(|event| event.event_id != **id))
+            .expect("a dropped event");
+        let cursor = URL_SAFE_NO_PAD.encode(gone);
+        for query in [format!("after={}", gone), format!("cursor={}", cursor)] {
+            let uri = format!("/v1/runs/{}/events?{}", run_id, query);
+            let (status, error) = get_json(&app, &uri).await;
+            assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+            assert_eq!(error["code"], "invalid_cursor");
+        }
+    }
+}
+
+#[tokio::test]
+async fn stores_reject_events_for_unknown_runs() {
+    let stores: [Arc<dyn RunStore>; 2] = [
+        Arc::new(InMemoryRunStore::new()),
+        Arc::new(SqliteRunStore::open_in_memory().expect("open sqlite store")),
+    ];
+    for store in stores {
+        let event = Event {
+            event_id: "evt_orphan".to_string(),
+            ts: chrono::Utc::now(),
+            event_type: EventType::RunRecovered,
+            run_id: "run_missing".to_string(),
+            step_id: None,
+            tool_name: None,
+            tenant_id: None,
+            payload: json!({}),
+        };
+        let appended = store.append_event(event).await;
+        assert!(matches!(appended, Err(StoreError::UnknownRun(run_id)) if run_id == "run_missing"));
+        assert!(store.list_events("run_missing").await.expect("events").is_none());
+    }
+}
+
+#[tokio::test]
+async fn run_ttl_evicts_finished_runs_but_not_waiting_ones() {
+    let policy = RetentionPolicy {
+        run_ttl: Some(Duration::ZERO),
+        ..RetentionPolicy::default()
+    };
+    for runtime in backends_with(|runtime| runtime.with_retention(policy.clone())) {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let payload = json!({ "workflow": { "name": "echo" }, "input": { "n": 1 } });
+        let finished_id = post_run(&app, payload).await;
+        let finished = wait_for_status(&runtime, &finished_id, RunStatus::Succeeded).await;
+        let artifact_id = finished.artifacts[0].artifact_id.clone();
+        let payload = json!({ "workflow": { "name": "approval" }, "input": {} });
+        let waiting_id = post_run(&app, payload).await;
+        wait_for_status(&runtime, &waiting_id, RunStatus::WaitingHuman).await;
+
+        let report = runtime.sweep_expired().await.expect("sweep");
+        assert_eq!(report.run_ids, [finished_id.as_str()]);
+        assert_eq!(report.artifacts, 1);
+
+        let (status, _) = get_json(&app, &format!("/v1/runs/{}", finished_id)).await;
+        assert_eq!(status, axum::http::StatusCode::NOT_FOUND);
+        let (status, _) = get_json(&app, &format!("/v1/runs/{}/events", finished_id)).await;
+        assert_eq!(status, axum::http::StatusCode::NOT_FOUND);
+        let (status, _) = get_json(&app, &format!("/v1/artifacts/{}", artifact_id)).await;
+        assert_eq!(status, axum::http::StatusCode::NOT_FOUND);
+        let (status, run) = get_json(&app, &format!("/v1/runs/{}", waiting_id)).await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!(run["status"], "waiting_human");
+
+        assert_eq!(
+            runtime.retention_metrics(),
+            RetentionMetrics {
+                runs_evicted: 1,
+                artifacts_evicted: 1,
+                events_dropped: 0,
+            }
+        );
+        let response = app
+            .clone()
+            .oneshot(axum::http::Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .expect("metrics response");
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let text = String::from_utf8(read_body_bytes(response.into_body()).await).expect("utf8");
+        assert!(text.contains("agent_runtime_runs_evicted_total 1\n"));
+        assert!(text.contains("agent_runtime_artifacts_evicted_total 1\n"));
+    }
+}
+
+#[tokio::test]
+async fn artifact_ttl_keeps_the_run() {
+    let policy = RetentionPolicy {
+        artifact_ttl: Some(Duration::ZERO),
+        ..RetentionPolicy::default()
+    };
+    for runtime in backends_with(|runtime| runtime.with_retention(policy.clone())) {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let payload = json!({ "workflow": { "name": "echo" }, "input": { "n": 1 } });
+        let run_id = post_run(&app, payload).await;
+        let run = wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+        let artifact_id = run.artifacts[0].artifact_id.clone();
+
+        let report = runtime.sweep_expired().await.expect("sweep");
+        assert!(report.run_ids.is_empty());
+        assert_eq!(report.artifacts, 1);
+        assert!(runtime.get_artifact(&artifact_id).await.is_none());
+        assert!(runtime.get_run(&run_id).await.is_some());
+    }
+}
+
+fn one_at_a_time(runtime: InMemoryRuntime) -> InMemoryRuntime {
+    runtime.with_concurrency_limits(ConcurrencyLimits {
+        max_running: Some(1),
+        ..ConcurrencyLimits::default()
+    })
+}
+
+fn approval_run(tenant_id: &str) -> serde_json::Value {
+    json!({
+        "workflow": { "name": "approval" },
+        "input": {},
+        "metadata": { "tenant_id": tenant_id },
+    })
+}
+
+async fn approve_first_checkpoint(app: &axum::Router, runtime: &InMemoryRuntime, run_id: &str) {
+    let run = wait_for_status(runtime, run_id, RunStatus::WaitingHuman).await;
+    let checkpoint_id = &run.checkpoints[0].checkpoint_id;
+    let (status, _) = post_checkpoint(app, run_id, checkpoint_id, "approve", json!({})).await;
+    assert_eq!(status, axum::http::StatusCode::OK);
+    wait_for_status(runtime, run_id, RunStatus::Succeeded).await;
+}
+
+#[tokio::test]
+async fn queued_runs_wait_for_a_free_slot() {
+    for runtime in backends_with(one_at_a_time) {
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let app = router(runtime.clone());
+
+        // A run waiting on a human keeps its slot.
+        let first = post_run(&app, approval_run("acme")).await;
+        wait_for_status(&runtime, &first, RunStatus::WaitingHuman).await;
+        let second = post_run(&app, approval_run("acme")).await;
+        let third = post_run(&app, approval_run("acme")).await;
+        sleep(Duration::from_millis(20)).await;
+        let (_, run) = get_json(&app, &format!("/v1/runs/{}", second)).await;
+        assert_eq!(run["status"], "queued");
+        assert_eq!(run["queue_position"], 1);
+        let (_, page) = get_json(&app, "/v1/runs?status=queued").await;
+        assert_eq!(page["data"][0]["run_id"], third.as_str());
+        assert_eq!(page["data"][0]["queue_position"], 2);
+
+        approve_first_checkpoint(&app, &runtime, &first).await;
+        wait_for_status(&runtime, &second, RunStatus::WaitingHuman).await;
+        let run = runtime.get_run(&third).await.expect("run");
+        assert_eq!(run.status, RunStatus::Queued);
+        assert_eq!(run.queue_position, Some(1));
+
+        // Canceling a queued run finishes it without waiting for a slot.
+        let run = runtime.cancel_run(&third).await.expect("cancel");
+        assert_eq!(run.status, RunStatus::Canceled);
+        assert_eq!(run.queue_position, None);
+        let events = runtime.list_events(&third).await.expect("events");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, EventType::RunCanceled);
+    }
+}
+
+#[tokio::test]
+async fn full_queue_returns_429() {
+    let limits = ConcurrencyLimits {
+        max_running: Some(1),
+        max_queued: Some(1),
+        ..ConcurrencyLimits::default()
+    };
+    for runtime in backends_with(|runtime| runtime.with_concurrency_limits(limits.clone())) {
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let first = post_run(&app, approval_run("acme")).await;
+        wait_for_status(&runtime, &first, RunStatus::WaitingHuman).await;
+        post_run(&app, approval_run("acme")).await;
+
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::post("/v1/runs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(approval_run("acme").to_string()))
+                    .unwrap(),
+            )
+            .await
+            .expect("create run response");
+        assert_eq!(response.status(), axum::http::StatusCode::TOO_MANY_REQUESTS);
+        let error: serde_json::Value =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).expect("parse");
+        assert_eq!(error["code"], "queue_full");
+        assert_eq!(error["retryable"], true);
+        let (_, page) = get_json(&app, "/v1/runs").await;
+        assert_eq!(page["data"].as_array().expect("data").len(), 2);
+    }
+}
+
+#[tokio::test]
+async fn tenants_take_turns_in_the_queue() {
+    for runtime in backends_with(one_at_a_time) {
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let blocker = post_run(&app, approval_run("acme")).await;
+        wait_for_status(&runtime, &blocker, RunStatus::WaitingHuman).await;
+        let batch_first = post_run(&app, approval_run("batch")).await;
+        let batch_second = post_run(&app, approval_run("batch")).await;
+        let single = post_run(&app, approval_run("single")).await;
+        sleep(Duration::from_millis(20)).await;
+        let mut positions = Vec::new();
+        for run_id in [&batch_first, &single, &batch_second] {
+            positions.push(runtime.get_run(run_id).await.expect("run").queue_position);
+        }
+        assert_eq!(positions, [Some(1), Some(2), Some(3)]);
+        let run = runtime.get_run(&single).await.expect("run");
+        assert_eq!(run.tenant_id.as_deref(), Some("single"));
+
+        approve_first_checkpoint(&app, &runtime, &blocker).await;
+        approve_first_checkpoint(&app, &runtime, &batch_first).await;
+        // The single run goes before the batch's second run.
+        wait_for_status(&runtime, &single, RunStatus::WaitingHuman).await;
+        let run = runtime.get_run(&batch_second).await.expect("run");
+        assert_eq!(run.status, RunStatus::Queued);
+    }
+}
+
+#[tokio::test]
+async fn workflow_concurrency_limit_leaves_other_workflows_running() {
+    for runtime in backends() {
+        let options = WorkflowOptions {
+            max_concurrency: Some(1),
+            ..WorkflowOptions::default()
+        };
+        runtime
+            .register_workflow_with_options(Arc::new(ApprovalWorkflow), None, None, options)
+            .await;
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let first = post_run(&app, approval_run("acme")).await;
+        wait_for_status(&runtime, &first, RunStatus::WaitingHuman).await;
+        let second = post_run(&app, approval_run("acme")).await;
+        let payload = json!({ "workflow": { "name": "echo" }, "input": {} });
+        let echo = post_run(&app, payload).await;
+        wait_for_status(&runtime, &echo, RunStatus::Succeeded).await;
+        assert_eq!(runtime.get_run(&second).await.expect("run").status, RunStatus::Queued);
+
+        approve_first_checkpoint(&app, &runtime, &first).await;
+        wait_for_status(&runtime, &second, RunStatus::WaitingHuman).await;
+    }
+}
+
+#[tokio::test]
+async fn shutdown_drains_runs_and_rejects_new_ones() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let run_id = post_run(&app, approval_run("acme")).await;
+        let run = wait_for_status(&runtime, &run_id, RunStatus::WaitingHuman).await;
+        let checkpoint_id = run.checkpoints[0].checkpoint_id.clone();
+        let shutdown = tokio::spawn({
+            let runtime = runtime.clone();
+            async move { runtime.shutdown(Duration::from_secs(5)).await }
+        });
+        sleep(Duration::from_millis(20)).await;
+        assert!(runtime.is_shutting_down());
+
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::post("/v1/runs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(approval_run("acme").to_string()))
+                    .unwrap(),
+            )
+            .await
+            .expect("create run response");
+        assert_eq!(response.status(), axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        let error: serde_json::Value =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).expect("parse");
+        assert_eq!(error["code"], "shutting_down");
+
+        // Runs may still finish while the runtime drains.
+        let (status, _) =
+            post_checkpoint(&app, &run_id, &checkpoint_id, "approve", json!({})).await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        let report = timeout(Duration::from_secs(2), shutdown)
+            .await
+            .expect("drained before the deadline")
+            .expect("shutdown task");
+        assert!(report.requeued.is_empty() && report.failed.is_empty());
+        let run = runtime.get_run(&run_id).await.expect("run");
+        assert_eq!(run.status, RunStatus::Succeeded);
+        let events = runtime.list_events(&run_id).await.expect("events");
+        assert!(events.iter().any(|event| event.event_type == EventType::RuntimeShutdown));
+    }
+}
+
+#[tokio::test]
+async fn shutdown_fails_unfinished_runs_and_closes_streams() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let run_id = post_run(&app, approval_run("acme")).await;
+        wait_for_status(&runtime, &run_id, RunStatus::WaitingHuman).await;
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::get(format!("/v1/runs/{}/events", run_id))
+                    .header("accept", "text/event-stream")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .expect("sse response");
+
+        let report = runtime.shutdown(Duration::from_millis(20)).await;
+        assert_eq!(report.failed, [run_id.as_str()]);
+        let run = runtime.get_run(&run_id).await.expect("run");
+        assert_eq!(run.status, RunStatus::Failed);
+        let error = run.error.expect("error");
+        assert_eq!(error.code, "shutdown");
+        assert!(error.retryable);
+
+        let body = timeout(Duration::from_secs(2), read_body_bytes(response.into_body()))
+            .await
+            .expect("stream closes on shutdown");
+        let types: Vec<String> = String::from_utf8_lossy(&body)
+            .lines()
+            .filter_map(|line| line.strip_prefix("event:"))
+            .map(|event| event.trim().to_string())
+            .collect();
+        assert_eq!(types[types.len() - 2..], ["runtime.shutdown", "run.failed"]);
+    }
+}
+
+#[tokio::test]
+async fn shutdown_requeues_unfinished_runs_in_a_durable_store() {
+    std::fs::create_dir_all("target/tmp").expect("create tmp dir");
+    let path = format!("target/tmp/run_store_{}.sqlite", Uuid::new_v4());
+    let runtime = Arc::new(one_at_a_time(InMemoryRuntime::with_store(Arc::new(
+        SqliteRunStore::open(&path).expect("open sqlite store"),
+    ))));
+    runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+    let app = router(runtime.clone());
+
+    let started = post_run(&app, approval_run("acme")).await;
+    wait_for_status(&runtime, &started, RunStatus::WaitingHuman).await;
+    let waiting = post_run(&app, approval_run("acme")).await;
+
+    let mut report = runtime.shutdown(Duration::from_millis(20)).await;
+    report.requeued.sort();
+    let mut expected = vec![started.clone(), waiting.clone()];
+    expected.sort();
+    assert_eq!(report.requeued, expected);
+    assert!(report.failed.is_empty());
+    for run_id in [&started, &waiting] {
+        let run = runtime.get_run(run_id).await.expect("run");
+        assert_eq!(run.status, RunStatus::Queued);
+        assert_eq!(run.timing.started_at, None);
+        let events = runtime.list_events(run_id).await.expect("events");
+        let last = events.last().expect("event");
+        assert_eq!(last.event_type, EventType::RuntimeShutdown);
+        assert_eq!(last.payload["requeued"], true);
+    }
+    std::fs::remove_file(&path).ok();
+}
+
+/// A run as a crashed process would have left it in the store.
+fn orphaned_run(workflow: &str, version: Option<&str>, status: &str) -> Run {
+    serde_json::from_value(json!({
+        "run_id": format!("run_{}", Uuid::new_v4()),
+        "workflow": { "name": workflow, "version": version },
+        "status": status,
+        "timing": { "created_at": chrono::Utc::now(), "started_at": chrono::Utc::now() },
+        "input": {},
+    }))
+    .expect("run")
+}
+
+#[tokio::test]
+async fn recover_runs_requeues_restarts_or_fails_orphans() {
+    let stores: [Arc<dyn RunStore>; 2] = [
+        Arc::new(InMemoryRunStore::new()),
+        Arc::new(SqliteRunStore::open_in_memory().expect("open sqlite store")),
+    ];
+    for store in stores {
+        let queued = orphaned_run("echo", Some("0.1.0"), "queued");
+        let idempotent = orphaned_run("versioned", Some("1.0.0"), "running");
+        let running = orphaned_run("approval", None, "running");
+        let waiting = orphaned_run("approval", None, "waiting_human");
+        let unknown = orphaned_run("retired", None, "queued");
+        for run in [&queued, &idempotent, &running, &waiting, &unknown] {
+            store.insert_run(run.clone()).await.expect("insert run");
+        }
+
+        let runtime = Arc::new(InMemoryRuntime::with_store(store));
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let options = WorkflowOptions {
+            idempotent: true,
+            ..WorkflowOptions::default()
+        };
+        runtime
+            .register_workflow_with_options(
+                Arc::new(VersionedWorkflow("1.0.0")),
+                None,
+                None,
+                options,
+            )
+            .await;
+
+        let mut report = runtime.recover_runs().await.expect("recover");
+        report.failed.sort();
+        let mut failed = vec![
+            running.run_id.clone(),
+            waiting.run_id.clone(),
+            unknown.run_id.clone(),
+        ];
+        failed.sort();
+        assert_eq!(report.requeued, [queued.run_id.as_str()]);
+        assert_eq!(report.restarted, [idempotent.run_id.as_str()]);
+        assert_eq!(report.failed, failed);
+
+        wait_for_status(&runtime, &queued.run_id, RunStatus::Succeeded).await;
+        let run = wait_for_status(&runtime, &idempotent.run_id, RunStatus::Succeeded).await;
+        assert_eq!(run.output, Some(json!({ "version": "1.0.0" })));
+        for (run_id, code) in [
+            (&running.run_id, "process_restarted"),
+            (&waiting.run_id, "process_restarted"),
+            (&unknown.run_id, "workflow_not_found"),
+        ] {
+            let run = runtime.get_run(run_id).await.expect("run");
+            assert_eq!(run.status, RunStatus::Failed);
+            assert_eq!(run.error.expect("error").code, code);
+        }
+
+        for (run_id, previous, action) in [
+            (&queued.run_id, "queued", "requeued"),
+            (&idempotent.run_id, "running", "restarted"),
+            (&waiting.run_id, "waiting_human", "failed"),
+        ] {
+            let events = runtime.list_events(run_id).await.expect("events");
+            assert_eq!(events[0].event_type, EventType::RunRecovered);
+            assert_eq!(events[0].payload["reason"], "process_restarted");
+            assert_eq!(events[0].payload["previous_status"], previous);
+            assert_eq!(events[0].payload["action"], action);
+        }
+        let events = runtime.list_events(&running.run_id).await.expect("events");
+        let types: Vec<EventType> = events.into_iter().map(|event| event.event_type).collect();
+        assert_eq!(types, [EventType::RunRecovered, EventType::RunFailed]);
+    }
+}
+
+fn auth_config() -> AuthConfig {
+    AuthConfig {
+        keys: vec![
+            ApiKey::new("reader", "reader-token", [Scope::RunsRead, Scope::WorkflowsRead]),
+            ApiKey::new("echo-only", "echo-token", [Scope::RunsCreate, Scope::WorkflowsRead])
+                .with_workflows(["echo"]),
+        ],
+    }
+}
+
+async fn send_as(
+    app: &axum::Router,
+    token: Option<&str>,
+    request: axum::http::request::Builder,
+    body: Body,
+) -> axum::http::Response<Body> {
+    let request = match token {
+        Some(token) => request.header("authorization", format!("Bearer {}", token)),
+        None => request,
+    };
+    app.clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .expect("response")
+}
+
+async fn post_run_as(
+    app: &axum::Router,
+    token: &str,
+    payload: &serde_json::Value,
+) -> axum::http::Response<Body> {
+    let request = axum::http::Request::post("/v1/runs").header("content-type", "application/json");
+    send_as(app, Some(token), request, Body::from(payload.to_string())).await
+}
+
+#[tokio::test]
+async fn requests_without_valid_key_return_401() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router_with_auth(runtime, auth_config());
+
+        let attempts = [(None, "missing bearer token"), (Some("nope"), "invalid API key")];
+        for (token, message) in attempts {
+            let request = axum::http::Request::get("/v1/workflows");
+            let response = send_as(&app, token, request, Body::empty()).await;
+            assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+            assert_eq!(response.headers()["www-authenticate"], "Bearer");
+            let body: serde_json::Value =
+                serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+            assert_eq!(body["code"], "unauthorized");
+            assert_eq!(body["message"], message);
+        }
+
+        let response = app
+            .oneshot(axum::http::Request::get("/metrics").body(Body::empty()).unwrap())
+            .await
+            .expect("metrics response");
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn key_without_scope_returns_403() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router_with_auth(runtime, auth_config());
+
+        let payload = json!({ "workflow": { "name": "echo" }, "input": {} });
+        let response = post_run_as(&app, "reader-token", &payload).await;
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+        let body: serde_json::Value =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+        assert_eq!(body["code"], "forbidden");
+        assert_eq!(body["details"]["required_scope"], "runs:create");
+
+        let request = axum::http::Request::get("/v1/runs");
+        let response = send_as(&app, Some("echo-token"), request, Body::empty()).await;
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+    }
+}
+
+#[tokio::test]
+async fn keys_are_limited_to_their_workflows() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        runtime.register_workflow(Arc::new(SlowWorkflow)).await;
+        let app = router_with_auth(runtime.clone(), auth_config());
+
+        let request = axum::http::Request::get("/v1/workflows");
+        let response = send_as(&app, Some("echo-token"), request, Body::empty()).await;
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let list: WorkflowListResponse =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+        let names: Vec<String> = list.data.into_iter().map(|workflow| workflow.name).collect();
+        assert_eq!(names, ["echo"]);
+
+        let request = axum::http::Request::get("/v1/workflows/slow");
+        let response = send_as(&app, Some("echo-token"), request, Body::empty()).await;
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+
+        let payload = json!({ "workflow": { "name": "slow" }, "input": {} });
+        let response = post_run_as(&app, "echo-token", &payload).await;
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+        let body: serde_json::Value =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+        assert_eq!(body["details"]["workflow"], "slow");
+
+        let payload = json!({ "workflow": { "name": "echo" }, "input": {} });
+        let response = post_run_as(&app, "echo-token", &payload).await;
+        assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+        let created: RunCreateResponse =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+        assert_eq!(created.run.created_by.as_deref(), Some("echo-only"));
+        let run = runtime.get_run(&created.run.run_id).await.expect("run");
+        assert_eq!(run.created_by.as_deref(), Some("echo-only"));
+    }
+}
+
+#[tokio::test]
+async fn keys_only_read_runs_of_their_workflows() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        runtime.register_workflow(Arc::new(StagedWorkflow)).await;
+        let config = AuthConfig {
+            keys: vec![
+                ApiKey::new("reader", "reader-token", [Scope::RunsRead]),
+                ApiKey::new("echo-reader", "echo-reader-token", [Scope::RunsRead])
+                    .with_workflows(["echo"]),
+            ],
+        };
+        let app = router_with_auth(runtime.clone(), config);
+
+        let mut runs = Vec::new();
+        for name in ["echo", "staged"] {
+            let run = runtime
+                .create_run(RunCreateRequest {
+                    workflow: WorkflowRef {
+                        name: name.to_string(),
+                        version: None,
+                    },
+                    input: json!({}),
+                    context: None,
+                    metadata: None,
+                    labels: None,
+                })
+                .await
+                .expect("create run");
+            runs.push(wait_for_status(&runtime, &run.run_id, RunStatus::Succeeded).await);
+        }
+        let uris = |run: &Run| {
+            [
+                format!("/v1/runs/{}", run.run_id),
+                format!("/v1/runs/{}/events", run.run_id),
+                format!("/v1/artifacts/{}", run.artifacts[0].artifact_id),
+            ]
+        };
+
+        for uri in uris(&runs[1]) {
+            let (status, body) = get_as(&app, "echo-reader-token", &uri).await;
+            assert_eq!(status, axum::http::StatusCode::FORBIDDEN, "{}", uri);
+            assert_eq!(body["details"]["workflow"], "staged");
+            let (status, _) = get_as(&app, "reader-token", &uri).await;
+            assert_eq!(status, axum::http::StatusCode::OK, "{}", uri);
+        }
+        for uri in uris(&runs[0]) {
+            let (status, _) = get_as(&app, "echo-reader-token", &uri).await;
+            assert_eq!(status, axum::http::StatusCode::OK, "{}", uri);
+        }
+        let events_uri = format!("/v1/runs/{}/events", runs[1].run_id);
+        let request = axum::http::Request::get(&events_uri).header("accept", "text/event-stream");
+        let response = send_as(&app, Some("echo-reader-token"), request, Body::empty()).await;
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+
+        let (status, page) = get_as(&app, "echo-reader-token", "/v1/runs").await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!(run_ids(&page), [runs[0].run_id.clone()]);
+        let (status, page) = get_as(&app, "reader-token", "/v1/runs").await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!(run_ids(&page).len(), 2);
+        let uri = "/v1/runs?workflow_name=staged";
+        let (status, body) = get_as(&app, "echo-reader-token", uri).await;
+        assert_eq!(status, axum::http::StatusCode::FORBIDDEN);
+        assert_eq!(body["code"], "forbidden");
+    }
+}
+
+fn tenant_auth_config() -> AuthConfig {
+    let all = [Scope::RunsCreate, Scope::RunsRead, Scope::RunsCancel, Scope::WorkflowsRead];
+    AuthConfig {
+        keys: vec![
+            ApiKey::new("shanghai-ops", "shanghai-token", all).with_tenant("shanghai"),
+            ApiKey::new("suzhou-ops", "suzhou-token", all).with_tenant("suzhou"),
+            ApiKey::new("operator", "operator-token", all),
+        ],
+    }
+}
+
+async fn get_as(
+    app: &axum::Router,
+    token: &str,
+    uri: &str,
+) -> (axum::http::StatusCode, serde_json::Value) {
+    let response = send_as(app, Some(token), axum::http::Request::get(uri), Body::empty()).await;
+    let status = response.status();
+    let body = read_body_bytes(response.into_body()).await;
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn tenants_cannot_see_each_others_runs() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router_with_auth(runtime.clone(), tenant_auth_config());
+
+        let payload = json!({ "workflow": { "name": "echo" }, "input": {} });
+        let response = post_run_as(&app, "shanghai-token", &payload).await;
+        assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+        let created: RunCreateResponse =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+        assert_eq!(created.run.tenant_id.as_deref(), Some("shanghai"));
+        let run_id = created.run.run_id;
+        let run = wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+        let artifact_id = &run.artifacts[0].artifact_id;
+        let events = runtime.list_events(&run_id).await.expect("events");
+        assert!(events.iter().all(|event| event.tenant_id.as_deref() == Some("shanghai")));
+
+        let run_uri = format!("/v1/runs/{}", run_id);
+        let events_uri = format!("/v1/runs/{}/events", run_id);
+        let artifact_uri = format!("/v1/artifacts/{}", artifact_id);
+        for uri in [&run_uri, &events_uri, &artifact_uri] {
+            let (status, body) = get_as(&app, "suzhou-token", uri).await;
+            assert_eq!(status, axum::http::StatusCode::NOT_FOUND, "{}", uri);
+            assert_eq!(body["code"], "not_found");
+            let (status, _) = get_as(&app, "shanghai-token", uri).await;
+            assert_eq!(status, axum::http::StatusCode::OK, "{}", uri);
+            let (status, _) = get_as(&app, "operator-token", uri).await;
+            assert_eq!(status, axum::http::StatusCode::OK, "{}", uri);
+        }
+        let (status, artifact) = get_as(&app, "shanghai-token", &artifact_uri).await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!(artifact["tenant_id"], "shanghai");
+
+        let request = axum::http::Request::get(&events_uri).header("accept", "text/event-stream");
+        let response = send_as(&app, Some("suzhou-token"), request, Body::empty()).await;
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+        let request = axum::http::Request::post(format!("/v1/runs/{}/cancel", run_id));
+        let response = send_as(&app, Some("suzhou-token"), request, Body::empty()).await;
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+
+        let (_, page) = get_as(&app, "suzhou-token", "/v1/runs").await;
+        assert_eq!(page["data"], json!([]));
+        let (_, page) = get_as(&app, "shanghai-token", "/v1/runs").await;
+        assert_eq!(page["data"][0]["run_id"], run_id);
+        let (status, _) = get_as(&app, "suzhou-token", "/v1/runs?tenant_id=shanghai").await;
+        assert_eq!(status, axum::http::StatusCode::FORBIDDEN);
+
+        let payload = json!({
+            "workflow": { "name": "echo" },
+            "input": {},
+            "metadata": { "tenant_id": "shanghai" },
+        });
+        let response = post_run_as(&app, "suzhou-token", &payload).await;
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+    }
+}
+
+#[tokio::test]
+async fn idempotency_keys_are_scoped_to_the_tenant() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = router_with_auth(runtime.clone(), tenant_auth_config());
+
+        let payload = json!({ "workflow": { "name": "echo" }, "input": {} });
+        let mut run_ids = Vec::new();
+        for token in ["shanghai-token", "suzhou-token"] {
+            let request = axum::http::Request::post("/v1/runs")
+                .header("content-type", "application/json")
+                .header("idempotency-key", "daily-sync");
+            let body = Body::from(payload.to_string());
+            let response = send_as(&app, Some(token), request, body).await;
+            assert_eq!(response.status(), axum::http::StatusCode::CREATED);
+            assert!(response.headers().get("idempotent-replayed").is_none());
+            let created: RunCreateResponse =
+                serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+            run_ids.push(created.run.run_id);
+        }
+        assert_ne!(run_ids[0], run_ids[1]);
+    }
+}
+
+#[tokio::test]
+async fn workflows_can_be_registered_for_a_tenant() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(VersionedWorkflow("1.0.0"))).await;
+        let options = WorkflowOptions {
+            tenants: Some(vec!["shanghai".to_string()]),
+            ..WorkflowOptions::default()
+        };
+        let variant = Arc::new(VersionedWorkflow("2.0.0"));
+        runtime
+            .register_workflow_with_options(variant, None, None, options)
+            .await;
+        let app = router_with_auth(runtime.clone(), tenant_auth_config());
+
+        let (_, workflow) = get_as(&app, "shanghai-token", "/v1/workflows/versioned").await;
+        assert_eq!(workflow["version"], "2.0.0");
+        let (_, workflow) = get_as(&app, "suzhou-token", "/v1/workflows/versioned").await;
+        assert_eq!(workflow["version"], "1.0.0");
+        assert_eq!(workflow["versions"].as_array().map(Vec::len), Some(1));
+        let (_, list) = get_as(&app, "operator-token", "/v1/workflows").await;
+        assert_eq!(list["data"].as_array().map(Vec::len), Some(2));
+
+        for (token, version) in [("shanghai-token", "2.0.0"), ("suzhou-token", "1.0.0")] {
+            let payload = json!({ "workflow": { "name": "versioned" }, "input": {} });
+            let response = post_run_as(&app, token, &payload).await;
+            let created: RunCreateResponse =
+                serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+            let run = wait_for_status(&runtime, &created.run.run_id, RunStatus::Succeeded).await;
+            assert_eq!(run.output, Some(json!({ "version": version })));
+        }
+
+        let workflow = json!({ "name": "versioned", "version": "2.0.0" });
+        let payload = json!({ "workflow": workflow, "input": {} });
+        let response = post_run_as(&app, "suzhou-token", &payload).await;
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        let body: serde_json::Value =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+        assert_eq!(body["code"], "workflow_version_not_found");
+    }
+}
+
+#[tokio::test]
+async fn tenant_quotas_bound_each_tenant() {
+    let limits = ConcurrencyLimits {
+        per_tenant: TenantQuota {
+            max_running: Some(1),
+            max_queued: Some(1),
+        },
+        ..ConcurrencyLimits::default()
+    };
+    for runtime in backends_with(|runtime| runtime.with_concurrency_limits(limits.clone())) {
+        runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+        let app = router(runtime.clone());
+
+        let first = post_run(&app, approval_run("shanghai")).await;
+        wait_for_status(&runtime, &first, RunStatus::WaitingHuman).await;
+        let second = post_run(&app, approval_run("shanghai")).await;
+        // Another tenant is not held up by the first one's quota.
+        let other = post_run(&app, approval_run("suzhou")).await;
+        wait_for_status(&runtime, &other, RunStatus::WaitingHuman).await;
+        let (_, run) = get_json(&app, &format!("/v1/runs/{}", second)).await;
+        assert_eq!(run["status"], "queued");
+
+        let response = app
+            .clone()
+            .oneshot(
+                axum::http::Request::post("/v1/runs")
+                    .header("content-type", "application/json")
+                    .body(Body::from(approval_run("shanghai").to_string()))
+                    .unwrap(),
+            )
+            .await
+            .expect("create run response");
+        assert_eq!(response.status(), axum::http::StatusCode::TOO_MANY_REQUESTS);
+        let body: serde_json::Value =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+        assert_eq!(body["code"], "tenant_quota_exceeded");
+        assert_eq!(body["details"]["tenant_id"], "shanghai");
+
+        approve_first_checkpoint(&app, &runtime, &first).await;
+        wait_for_status(&runtime, &second, RunStatus::WaitingHuman).await;
+    }
+}
+
+#[tokio::test]
+async fn router_builder_mounts_api_under_prefix() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = RouterBuilder::new(runtime)
+            .prefix("/agent-runtime/")
+            .auth(auth_config())
+            .build();
+
+        let request = axum::http::Request::get("/agent-runtime/v1/workflows");
+        let response = send_as(&app, Some("reader-token"), request, Body::empty()).await;
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+
+        // Scopes are still looked up by the route below the prefix.
+        let payload = json!({ "workflow": { "name": "echo" }, "input": {} });
+        let request = axum::http::Request::post("/agent-runtime/v1/runs")
+            .header("content-type", "application/json");
+        let response =
+            send_as(&app, Some("reader-token"), request, Body::from(payload.to_string())).await;
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+        let body: serde_json::Value =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+        assert_eq!(body["details"]["required_scope"], "runs:create");
+
+        for uri in ["/agent-runtime/metrics", "/agent-runtime/openapi.json"] {
+            let response = send_as(&app, None, axum::http::Request::get(uri), Body::empty()).await;
+            assert_eq!(response.status(), axum::http::StatusCode::OK, "{}", uri);
+        }
+        let request = axum::http::Request::get("/v1/workflows");
+        let response = send_as(&app, Some("reader-token"), request, Body::empty()).await;
+        assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    }
+}
+
+async fn whoami(
+    axum::extract::State(state): axum::extract::State<AppState>,
+    axum::Extension(principal): axum::Extension<Principal>,
+) -> axum::Json<serde_json::Value> {
+    let workflows = state.runtime.list_workflows().await.len();
+    axum::Json(json!({ "key_id": principal.key_id, "workflows": workflows }))
+}
+
+#[tokio::test]
+async fn router_builder_adds_routes_and_layers() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = RouterBuilder::new(runtime)
+            .auth(auth_config())
+            .routes(axum::Router::new().route("/v1/whoami", axum::routing::get(whoami)))
+            .layer(axum::middleware::from_fn(
+                |request: axum::extract::Request, next: axum::middleware::Next| async move {
+                    let mut response = next.run(request).await;
+                    response
+                        .headers_mut()
+                        .insert("x-request-id", axum::http::HeaderValue::from_static("req-1"));
+                    response
+                },
+            ))
+            .build();
+
+        // App routes need a valid key, but no particular scope.
+        let request = axum::http::Request::get("/v1/whoami");
+        let response = send_as(&app, None, request, Body::empty()).await;
+        assert_eq!(response.status(), axum::http::StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()["x-request-id"], "req-1");
+        let request = axum::http::Request::get("/v1/whoami");
+        let response = send_as(&app, Some("echo-token"), request, Body::empty()).await;
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        assert_eq!(response.headers()["x-request-id"], "req-1");
+        let body: serde_json::Value =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+        assert_eq!(body, json!({ "key_id": "echo-only", "workflows": 1 }));
+
+        let request = axum::http::Request::get("/metrics");
+        let response = send_as(&app, None, request, Body::empty()).await;
+        assert_eq!(response.headers()["x-request-id"], "req-1");
+    }
+}
+
+#[tokio::test]
+async fn router_builder_runs_create_run_hooks() {
+    for runtime in backends() {
+        runtime.register_workflow(Arc::new(TestWorkflow)).await;
+        let app = RouterBuilder::new(runtime.clone())
+            .on_create_run(|req, principal| {
+                if req.input.get("blocked").is_some() {
+                    return Err(agent_runtime::types::ErrorResponse {
+                        code: "invalid_request".to_string(),
+                        message: "blocked input".to_string(),
+                        retryable: false,
+                        details: None,
+                    });
+                }
+                let caller = principal.map_or("anonymous", |principal| principal.key_id.as_str());
+                let metadata = req.metadata.get_or_insert_with(Default::default);
+                metadata.insert("source".to_string(), json!("builder"));
+                metadata.insert("caller".to_string(), json!(caller));
+                Ok(())
+            })
+            .build();
+
+        let run_id = post_run(&app, json!({ "workflow": { "name": "echo" }, "input": {} })).await;
+        let run = runtime.get_run(&run_id).await.expect("run exists");
+        let metadata = run.metadata.expect("metadata");
+        assert_eq!(metadata["source"], "builder");
+        assert_eq!(metadata["caller"], "anonymous");
+
+        let payload = json!({ "workflow": { "name": "echo" }, "input": { "blocked": true } });
+        let request =
+            axum::http::Request::post("/v1/runs").header("content-type", "application/json");
+        let response = send_as(&app, None, request, Body::from(payload.to_string())).await;
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        let body: serde_json::Value =
+            serde_json::from_slice(&read_body_bytes(response.into_body()).await).unwrap();
+        assert_eq!(body["code"], "invalid_request");
+    }
 }
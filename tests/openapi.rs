@@ -0,0 +1,514 @@
+use std::sync::Arc;
+
+use agent_runtime::auth::{ApiKey, AuthConfig, Scope};
+use agent_runtime::runtime::{
+    AgentError, CheckpointRequest, ConcurrencyLimits, InMemoryRuntime, RunContext,
+    WorkflowOutput, WorkflowRunner,
+};
+use agent_runtime::server::{router, router_with_auth, OPENAPI_YAML};
+use agent_runtime::store::SqliteRunStore;
+use agent_runtime::types::{Artifact, ArtifactType, Cost, RunStatus};
+use axum::body::Body;
+use axum::http::{Method, StatusCode};
+use http_body_util::BodyExt;
+use jsonschema::JSONSchema;
+use serde_json::{json, Value};
+use tokio::time::{sleep, Duration};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// Reports a step, a tool call, model usage and an artifact, then echoes its
+/// input.
+struct EchoWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for EchoWorkflow {
+    fn name(&self) -> &'static str {
+        "echo"
+    }
+
+    fn version(&self) -> Option<&'static str> {
+        Some("0.1.0")
+    }
+
+    async fn run(&self, input: Value, ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
+        ctx.step("lookup", async {
+            ctx.tool_called("lookup", "mysql.query", json!({ "sql": "select 1" }))
+                .await;
+            ctx.tool_result("lookup", "mysql.query", json!({ "rows": 1 }))
+                .await;
+            Ok(())
+        })
+        .await?;
+        ctx.llm_request("summarize", json!({ "model": "test" })).await;
+        ctx.llm_response("summarize", json!({ "ok": true })).await;
+        ctx.add_cost(Cost {
+            total_tokens: 30,
+            prompt_tokens: 20,
+            completion_tokens: 10,
+            total_usd: 0.002,
+        })
+        .await;
+        ctx.add_artifact(Artifact {
+            artifact_id: format!("art_{}", Uuid::new_v4()),
+            r#type: ArtifactType::Record,
+            name: Some("echo".to_string()),
+            created_at: chrono::Utc::now(),
+            mime_type: Some("application/json".to_string()),
+            data: Some(json!({ "echo": input })),
+            file: None,
+            tenant_id: None,
+            run_id: None,
+        })
+        .await;
+        Ok(WorkflowOutput {
+            output: json!({ "echo": input }),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+/// Waits for a human to approve or provide a note.
+struct ApprovalWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for ApprovalWorkflow {
+    fn name(&self) -> &'static str {
+        "approval"
+    }
+
+    async fn run(&self, _input: Value, ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
+        let checkpoint = ctx
+            .request_checkpoint(CheckpointRequest {
+                title: Some("Send the prebrief?".to_string()),
+                instructions: Some("Add a note for the store manager".to_string()),
+                required_schema: Some(json!({ "type": "object" })),
+                expires_in: Some(Duration::from_secs(60)),
+            })
+            .await?;
+        Ok(WorkflowOutput {
+            output: json!({ "status": checkpoint.status }),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+struct FailingWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for FailingWorkflow {
+    fn name(&self) -> &'static str {
+        "failing"
+    }
+
+    async fn run(&self, _input: Value, _ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
+        Err(AgentError::fatal("bad input"))
+    }
+}
+
+fn backends() -> Vec<Arc<InMemoryRuntime>> {
+    let sqlite = SqliteRunStore::open_in_memory().expect("open sqlite store");
+    vec![
+        Arc::new(InMemoryRuntime::new()),
+        Arc::new(InMemoryRuntime::with_store(Arc::new(sqlite))),
+    ]
+}
+
+async fn register_workflows(runtime: &InMemoryRuntime) {
+    runtime
+        .register_workflow_with_schemas(
+            Arc::new(EchoWorkflow),
+            Some(json!({
+                "type": "object",
+                "properties": { "text": { "type": "string" } },
+                "required": ["text"]
+            })),
+            Some(json!({ "type": "object" })),
+        )
+        .await;
+    runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+    runtime.register_workflow(Arc::new(FailingWorkflow)).await;
+}
+
+async fn wait_for_status(runtime: &InMemoryRuntime, run_id: &str, status: RunStatus) {
+    for _ in 0..100 {
+        let run = runtime.get_run(run_id).await.expect("run exists");
+        if run.status == status {
+            return;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    panic!("run {} never reached {:?}", run_id, status);
+}
+
+/// Sends requests to a router and checks each request and response body, and
+/// each status code, against the operation the spec declares for the route.
+struct Contract {
+    app: axum::Router,
+    spec: Value,
+    token: Option<&'static str>,
+}
+
+impl Contract {
+    /// Loads the spec the router itself serves.
+    async fn new(app: axum::Router) -> Self {
+        let response = app
+            .clone()
+            .oneshot(axum::http::Request::get("/openapi.json").body(Body::empty()).unwrap())
+            .await
+            .expect("openapi response");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let mut spec: Value = serde_json::from_slice(&body).expect("openapi json");
+        forbid_undeclared_properties(&mut spec["components"]["schemas"]);
+        Self {
+            app,
+            spec,
+            token: None,
+        }
+    }
+
+    fn with_token(mut self, token: &'static str) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    fn operation(&self, method: &Method, route: &str) -> &Value {
+        let operation = &self.spec["paths"][route][method.as_str().to_lowercase()];
+        assert!(operation.is_object(), "{} {} is not in the spec", method, route);
+        operation
+    }
+
+    /// Calls `method uri`, where `uri` fills in the spec's `route`.
+    async fn call(
+        &self,
+        method: Method,
+        route: &str,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        if let Some(body) = &body {
+            let operation = self.operation(&method, route);
+            let schema = &operation["requestBody"]["content"]["application/json"]["schema"];
+            assert!(schema.is_object(), "{} {} takes no JSON body", method, route);
+            self.validate(schema, body, &format!("{} {} request", method, route));
+        }
+        self.send(method, route, uri, body).await
+    }
+
+    /// Like [`Self::call`], but sends `body` even if the spec would reject it.
+    async fn send(
+        &self,
+        method: Method,
+        route: &str,
+        uri: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let operation = self.operation(&method, route);
+        let mut request = axum::http::Request::builder().method(method.clone()).uri(uri);
+        if let Some(token) = self.token {
+            request = request.header("authorization", format!("Bearer {}", token));
+        }
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        };
+        let response = self.app.clone().oneshot(request.unwrap()).await.expect("response");
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap_or_else(|err| {
+            panic!("{} {} returned {} with a non-JSON body: {}", method, uri, status, err)
+        });
+
+        let responses = &operation["responses"];
+        let declared = &responses[status.as_str()];
+        let response = if declared.is_object() {
+            declared
+        } else {
+            // Errors may fall back to the default response; successes must be listed.
+            assert!(
+                status.is_client_error() || status.is_server_error(),
+                "{} {} returned {}, which the spec does not declare",
+                method,
+                route,
+                status
+            );
+            &responses["default"]
+        };
+        let response = self.resolve(response);
+        let schema = &response["content"]["application/json"]["schema"];
+        assert!(schema.is_object(), "{} {} {} has no JSON schema", method, route, status);
+        self.validate(schema, &body, &format!("{} {} {} response", method, route, status));
+        (status, body)
+    }
+
+    fn resolve<'a>(&'a self, value: &'a Value) -> &'a Value {
+        match value["$ref"].as_str() {
+            Some(reference) => {
+                let pointer = reference.trim_start_matches('#');
+                self.spec.pointer(pointer).expect("reference resolves")
+            }
+            None => value,
+        }
+    }
+
+    fn validate(&self, schema: &Value, instance: &Value, what: &str) {
+        // References point into the spec's components, so carry them along.
+        let schema = json!({
+            "allOf": [schema],
+            "components": self.spec["components"],
+        });
+        let compiled = JSONSchema::compile(&schema).expect("compile schema");
+        if let Err(errors) = compiled.validate(instance) {
+            let errors: Vec<String> = errors
+                .map(|error| format!("{} at {}", error, error.instance_path))
+                .collect();
+            panic!("{} does not match the spec:\n{}\n{:#}", what, errors.join("\n"), instance);
+        }
+    }
+}
+
+/// Closes every object schema that lists its properties, so a field the
+/// server sends but the spec does not declare fails validation.
+fn forbid_undeclared_properties(schema: &mut Value) {
+    match schema {
+        Value::Object(object) => {
+            if object.contains_key("properties") && !object.contains_key("additionalProperties") {
+                object.insert("additionalProperties".to_string(), json!(false));
+            }
+            object.values_mut().for_each(forbid_undeclared_properties);
+        }
+        Value::Array(items) => items.iter_mut().for_each(forbid_undeclared_properties),
+        _ => {}
+    }
+}
+
+#[tokio::test]
+async fn serves_openapi_document() {
+    let auth = AuthConfig {
+        keys: vec![ApiKey::new("dev", "dev-token", [Scope::RunsRead])],
+    };
+    let app = router_with_auth(Arc::new(InMemoryRuntime::new()), auth);
+
+    let response = app
+        .clone()
+        .oneshot(axum::http::Request::get("/openapi.yaml").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/yaml");
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(body, OPENAPI_YAML.as_bytes());
+
+    // Served without a key, and as the same document.
+    let contract = Contract::new(app).await;
+    assert_eq!(contract.spec["openapi"], "3.1.0");
+    assert_eq!(contract.spec["paths"]["/openapi.json"]["get"]["security"], json!([]));
+}
+
+#[tokio::test]
+async fn runs_events_and_artifacts_match_spec() {
+    for runtime in backends() {
+        register_workflows(&runtime).await;
+        let api = Contract::new(router(runtime.clone())).await;
+
+        let create = json!({
+            "workflow": { "name": "echo", "version": "0.1.0" },
+            "input": { "text": "hi" },
+            "context": { "channel": "wecom" },
+            "metadata": { "tenant_id": "shanghai", "timeout_ms": 5000 },
+            "labels": { "store_id": "sh01" },
+        });
+        let (status, created) = api.call(Method::POST, "/v1/runs", "/v1/runs", Some(create)).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let run_id = created["run"]["run_id"].as_str().unwrap().to_string();
+        wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+
+        let uri = format!("/v1/runs/{}", run_id);
+        let (status, run) = api.call(Method::GET, "/v1/runs/{run_id}", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(run["cost"]["total_tokens"], 30);
+        let (status, _) = api
+            .call(Method::GET, "/v1/runs", "/v1/runs?label_selector=store_id=sh01&limit=1", None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let uri = format!("/v1/runs/{}/events", run_id);
+        let (status, events) = api.call(Method::GET, "/v1/runs/{run_id}/events", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        let types: Vec<&str> = events["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| event["type"].as_str().unwrap())
+            .collect();
+        for expected in ["tool.called", "llm.response", "artifact.created", "run.completed"] {
+            assert!(types.contains(&expected), "missing {}", expected);
+        }
+
+        let artifact_id = run["artifacts"][0]["artifact_id"].as_str().unwrap();
+        let uri = format!("/v1/artifacts/{}", artifact_id);
+        let (status, _) = api.call(Method::GET, "/v1/artifacts/{artifact_id}", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let create = json!({ "workflow": { "name": "failing" }, "input": {} });
+        let (_, created) = api.call(Method::POST, "/v1/runs", "/v1/runs", Some(create)).await;
+        let run_id = created["run"]["run_id"].as_str().unwrap().to_string();
+        wait_for_status(&runtime, &run_id, RunStatus::Failed).await;
+        let uri = format!("/v1/runs/{}", run_id);
+        let (_, run) = api.call(Method::GET, "/v1/runs/{run_id}", &uri, None).await;
+        assert_eq!(run["error"]["message"], "bad input");
+    }
+}
+
+#[tokio::test]
+async fn checkpoints_and_cancellation_match_spec() {
+    for runtime in backends() {
+        register_workflows(&runtime).await;
+        let api = Contract::new(router(runtime.clone())).await;
+        let approval = json!({ "workflow": { "name": "approval" }, "input": {} });
+
+        let mut runs = Vec::new();
+        for _ in 0..3 {
+            let (_, created) = api
+                .call(Method::POST, "/v1/runs", "/v1/runs", Some(approval.clone()))
+                .await;
+            let run_id = created["run"]["run_id"].as_str().unwrap().to_string();
+            wait_for_status(&runtime, &run_id, RunStatus::WaitingHuman).await;
+            let run = runtime.get_run(&run_id).await.unwrap();
+            runs.push((run_id, run.checkpoints[0].checkpoint_id.clone()));
+        }
+
+        let route = "/v1/runs/{run_id}/human/{checkpoint_id}/approve";
+        let uri = format!("/v1/runs/{}/human/{}/approve", runs[0].0, runs[0].1);
+        let (status, _) = api.call(Method::POST, route, &uri, Some(json!({ "note": "ok" }))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, error) = api.call(Method::POST, route, &uri, None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(error["code"], "checkpoint_already_resolved");
+
+        let route = "/v1/runs/{run_id}/human/{checkpoint_id}/reject";
+        let uri = format!("/v1/runs/{}/human/{}/reject", runs[1].0, runs[1].1);
+        let (status, _) = api.call(Method::POST, route, &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let route = "/v1/runs/{run_id}/human/{checkpoint_id}/provide_input";
+        let uri = format!("/v1/runs/{}/human/{}/provide_input", runs[2].0, runs[2].1);
+        let (status, error) = api.send(Method::POST, route, &uri, Some(json!({ "note": 1 }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "invalid_request");
+        let input = json!({ "input": { "note": "call first" }, "note": "done" });
+        let (status, checkpoint) = api.call(Method::POST, route, &uri, Some(input)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(checkpoint["status"], "input_provided");
+
+        let (_, created) = api
+            .call(Method::POST, "/v1/runs", "/v1/runs", Some(approval.clone()))
+            .await;
+        let run_id = created["run"]["run_id"].as_str().unwrap().to_string();
+        wait_for_status(&runtime, &run_id, RunStatus::WaitingHuman).await;
+        let uri = format!("/v1/runs/{}/cancel", run_id);
+        let (status, _) = api.call(Method::POST, "/v1/runs/{run_id}/cancel", &uri, None).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let uri = format!("/v1/runs/{}", run_id);
+        wait_for_status(&runtime, &run_id, RunStatus::Canceled).await;
+        let (status, error) = api.call(Method::DELETE, "/v1/runs/{run_id}", &uri, None).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(error["code"], "run_not_cancelable");
+    }
+}
+
+#[tokio::test]
+async fn workflows_match_spec() {
+    for runtime in backends() {
+        register_workflows(&runtime).await;
+        let api = Contract::new(router(runtime.clone())).await;
+
+        let (status, _) = api.call(Method::GET, "/v1/workflows", "/v1/workflows", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = api
+            .call(Method::GET, "/v1/workflows/{name}", "/v1/workflows/echo?version=^0.1", None)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        let route = "/v1/workflows/{name}/schemas";
+        let (status, _) = api.call(Method::GET, route, "/v1/workflows/echo/schemas", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = api.call(Method::GET, route, "/v1/workflows/missing/schemas", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn errors_match_spec() {
+    for runtime in backends() {
+        register_workflows(&runtime).await;
+        let limits = ConcurrencyLimits {
+            max_running: Some(1),
+            max_queued: Some(1),
+            ..ConcurrencyLimits::default()
+        };
+        let runtime = Arc::new(
+            Arc::try_unwrap(runtime)
+                .unwrap_or_else(|_| panic!("runtime is shared"))
+                .with_concurrency_limits(limits),
+        );
+        let api = Contract::new(router(runtime.clone())).await;
+
+        let invalid = json!({ "workflow": { "name": "echo" }, "input": { "text": 1 } });
+        let (status, error) = api.call(Method::POST, "/v1/runs", "/v1/runs", Some(invalid)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "input_validation_failed");
+        let unknown = json!({ "workflow": { "name": "missing" }, "input": {} });
+        let (status, _) = api.call(Method::POST, "/v1/runs", "/v1/runs", Some(unknown)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, error) = api.call(Method::GET, "/v1/runs", "/v1/runs?limit=x", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error["code"], "invalid_query");
+
+        let (status, _) = api
+            .call(Method::GET, "/v1/runs/{run_id}", "/v1/runs/run_missing", None)
+            .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let route = "/v1/artifacts/{artifact_id}";
+        let (status, _) = api.call(Method::GET, route, "/v1/artifacts/art_missing", None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // One run holds the only slot, one waits, and the next one is refused.
+        let approval = json!({ "workflow": { "name": "approval" }, "input": {} });
+        let (_, first) = api
+            .call(Method::POST, "/v1/runs", "/v1/runs", Some(approval.clone()))
+            .await;
+        let first = first["run"]["run_id"].as_str().unwrap().to_string();
+        wait_for_status(&runtime, &first, RunStatus::WaitingHuman).await;
+        let (_, second) = api
+            .call(Method::POST, "/v1/runs", "/v1/runs", Some(approval.clone()))
+            .await;
+        let uri = format!("/v1/runs/{}", second["run"]["run_id"].as_str().unwrap());
+        let (_, queued) = api.call(Method::GET, "/v1/runs/{run_id}", &uri, None).await;
+        assert_eq!(queued["queue_position"], 1);
+        let (status, error) = api.call(Method::POST, "/v1/runs", "/v1/runs", Some(approval)).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error["code"], "queue_full");
+    }
+}
+
+#[tokio::test]
+async fn auth_errors_match_spec() {
+    let runtime = Arc::new(InMemoryRuntime::new());
+    register_workflows(&runtime).await;
+    let auth = AuthConfig {
+        keys: vec![ApiKey::new("reader", "reader-token", [Scope::RunsRead])],
+    };
+    let app = router_with_auth(runtime, auth);
+
+    let api = Contract::new(app.clone()).await;
+    let (status, _) = api.call(Method::GET, "/v1/workflows", "/v1/workflows", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let api = Contract::new(app).await.with_token("reader-token");
+    let (status, error) = api.call(Method::GET, "/v1/workflows", "/v1/workflows", None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(error["details"]["required_scope"], "workflows:read");
+}
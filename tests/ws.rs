@@ -0,0 +1,346 @@
+use std::sync::Arc;
+
+use agent_runtime::auth::{ApiKey, AuthConfig, Scope};
+use agent_runtime::runtime::{
+    AgentError, CheckpointRequest, InMemoryRuntime, RunContext, WorkflowOutput, WorkflowRunner,
+};
+use agent_runtime::server::{router, router_with_auth};
+use agent_runtime::store::SqliteRunStore;
+use agent_runtime::types::{
+    Event, RunCreateRequest, RunStatus, WorkflowRef, WsClientMessage, WsServerMessage,
+};
+use axum::body::Body;
+use futures::{SinkExt, StreamExt};
+use http_body_util::BodyExt;
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+use tokio::time::{sleep, timeout, Duration};
+use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tower::ServiceExt;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+struct EchoWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for EchoWorkflow {
+    fn name(&self) -> &'static str {
+        "echo"
+    }
+
+    async fn run(&self, input: Value, ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
+        ctx.step("echo", async { Ok(()) }).await?;
+        Ok(WorkflowOutput {
+            output: json!({ "echo": input }),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+/// Waits on a human checkpoint, so the run stays open until it is canceled.
+struct ApprovalWorkflow;
+
+#[async_trait::async_trait]
+impl WorkflowRunner for ApprovalWorkflow {
+    fn name(&self) -> &'static str {
+        "approval"
+    }
+
+    async fn run(&self, _input: Value, ctx: &RunContext) -> Result<WorkflowOutput, AgentError> {
+        ctx.request_checkpoint(CheckpointRequest::default()).await?;
+        Ok(WorkflowOutput {
+            output: json!({}),
+            artifacts: Vec::new(),
+        })
+    }
+}
+
+fn backends() -> Vec<Arc<InMemoryRuntime>> {
+    let sqlite = SqliteRunStore::open_in_memory().expect("open sqlite store");
+    vec![
+        Arc::new(InMemoryRuntime::new()),
+        Arc::new(InMemoryRuntime::with_store(Arc::new(sqlite))),
+    ]
+}
+
+async fn register_workflows(runtime: &InMemoryRuntime) {
+    runtime.register_workflow(Arc::new(EchoWorkflow)).await;
+    runtime.register_workflow(Arc::new(ApprovalWorkflow)).await;
+}
+
+/// Serves `app` on an ephemeral port and returns its `ws://` base URL.
+async fn serve(app: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let addr = listener.local_addr().expect("local addr");
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("ws://{}", addr)
+}
+
+async fn connect(url: &str, token: Option<&str>) -> Result<Socket, tungstenite::Error> {
+    let mut request = url.into_client_request().expect("request");
+    if let Some(token) = token {
+        let value = format!("Bearer {}", token).parse().expect("header value");
+        request.headers_mut().insert("authorization", value);
+    }
+    tokio_tungstenite::connect_async(request).await.map(|(socket, _)| socket)
+}
+
+async fn create_run(runtime: &InMemoryRuntime, workflow: &str, tenant_id: Option<&str>) -> String {
+    let metadata = tenant_id.map(|tenant_id| {
+        let mut metadata = serde_json::Map::new();
+        metadata.insert("tenant_id".to_string(), json!(tenant_id));
+        metadata
+    });
+    let run = runtime
+        .create_run(RunCreateRequest {
+            workflow: WorkflowRef {
+                name: workflow.to_string(),
+                version: None,
+            },
+            input: json!({ "hello": "ws" }),
+            context: None,
+            metadata,
+            labels: None,
+        })
+        .await
+        .expect("create run");
+    run.run_id
+}
+
+async fn wait_for_status(runtime: &InMemoryRuntime, run_id: &str, status: RunStatus) {
+    for _ in 0..100 {
+        let run = runtime.get_run(run_id).await.expect("run exists");
+        if run.status == status {
+            return;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    panic!("run {} never reached {:?}", run_id, status);
+}
+
+async fn send(socket: &mut Socket, message: &WsClientMessage) {
+    let text = serde_json::to_string(message).expect("serialize");
+    socket.send(Message::Text(text)).await.expect("send");
+}
+
+/// The next server message, or `None` once the server closes the socket.
+async fn next_message(socket: &mut Socket) -> Option<WsServerMessage> {
+    loop {
+        let message = timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("message in time")?
+            .expect("websocket message");
+        match message {
+            Message::Text(text) => return Some(serde_json::from_str(&text).expect("parse")),
+            Message::Close(_) => return None,
+            _ => continue,
+        }
+    }
+}
+
+/// Reads messages up to and including the first one `done` accepts.
+async fn read_until(
+    socket: &mut Socket,
+    done: impl Fn(&WsServerMessage) -> bool,
+) -> Vec<WsServerMessage> {
+    let mut messages = Vec::new();
+    loop {
+        let message = next_message(socket).await.expect("socket open");
+        let finished = done(&message);
+        messages.push(message);
+        if finished {
+            return messages;
+        }
+    }
+}
+
+fn event_ids(messages: &[WsServerMessage]) -> Vec<String> {
+    messages
+        .iter()
+        .filter_map(|message| match message {
+            WsServerMessage::Event { event } => Some(event.event_id.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn stored_ids(events: Vec<Event>) -> Vec<String> {
+    events.into_iter().map(|event| event.event_id).collect()
+}
+
+#[tokio::test]
+async fn run_socket_streams_events_until_the_run_ends() {
+    for runtime in backends() {
+        register_workflows(&runtime).await;
+        let base_url = serve(router(runtime.clone())).await;
+        let run_id = create_run(&runtime, "echo", None).await;
+        wait_for_status(&runtime, &run_id, RunStatus::Succeeded).await;
+
+        let url = format!("{}/v1/runs/{}/ws", base_url, run_id);
+        let mut socket = connect(&url, None).await.expect("connect");
+        let mut messages = Vec::new();
+        while let Some(message) = next_message(&mut socket).await {
+            messages.push(message);
+        }
+        assert!(matches!(
+            &messages[0],
+            WsServerMessage::Subscribed { run_id: id } if *id == run_id
+        ));
+        assert!(matches!(
+            messages.last(),
+            Some(WsServerMessage::Unsubscribed { run_id: id }) if *id == run_id
+        ));
+        let stored = stored_ids(runtime.list_events(&run_id).await.expect("events"));
+        assert_eq!(event_ids(&messages), stored);
+
+        // Resuming skips the events up to `after`.
+        let url = format!("{}/v1/runs/{}/ws?after={}", base_url, run_id, stored[0]);
+        let mut socket = connect(&url, None).await.expect("connect");
+        let mut messages = Vec::new();
+        while let Some(message) = next_message(&mut socket).await {
+            messages.push(message);
+        }
+        assert_eq!(event_ids(&messages), stored[1..]);
+    }
+}
+
+#[tokio::test]
+async fn multiplexed_socket_follows_subscribed_runs() {
+    for runtime in backends() {
+        register_workflows(&runtime).await;
+        let base_url = serve(router(runtime.clone())).await;
+        let first = create_run(&runtime, "approval", None).await;
+        let second = create_run(&runtime, "approval", None).await;
+        wait_for_status(&runtime, &first, RunStatus::WaitingHuman).await;
+        wait_for_status(&runtime, &second, RunStatus::WaitingHuman).await;
+
+        let mut socket = connect(&format!("{}/v1/ws", base_url), None).await.expect("connect");
+        for run_id in [&first, &second, &"run_missing".to_string()] {
+            let subscribe = WsClientMessage::Subscribe {
+                run_id: run_id.clone(),
+                after: None,
+            };
+            send(&mut socket, &subscribe).await;
+        }
+        socket.send(Message::Text("{\"type\":\"nope\"}".to_string())).await.expect("send");
+        send(&mut socket, &WsClientMessage::Ping).await;
+        let messages = read_until(&mut socket, |message| {
+            matches!(message, WsServerMessage::Pong)
+        })
+        .await;
+        let errors: Vec<_> = messages
+            .iter()
+            .filter_map(|message| match message {
+                WsServerMessage::Error { run_id, error } => Some((run_id.clone(), &error.code)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (Some("run_missing".to_string()), &"not_found".to_string()),
+                (None, &"invalid_request".to_string()),
+            ]
+        );
+        let subscribed = messages
+            .iter()
+            .filter(|message| matches!(message, WsServerMessage::Subscribed { .. }))
+            .count();
+        assert_eq!(subscribed, 2);
+        let mut seen = event_ids(&messages);
+
+        send(&mut socket, &WsClientMessage::Unsubscribe { run_id: second.clone() }).await;
+        let messages = read_until(&mut socket, |message| {
+            matches!(message, WsServerMessage::Unsubscribed { run_id } if *run_id == second)
+        })
+        .await;
+        seen.extend(event_ids(&messages));
+
+        runtime.cancel_run(&first).await.expect("cancel first");
+        runtime.cancel_run(&second).await.expect("cancel second");
+        let messages = read_until(&mut socket, |message| {
+            matches!(message, WsServerMessage::Unsubscribed { run_id } if *run_id == first)
+        })
+        .await;
+        for message in &messages {
+            if let WsServerMessage::Event { event } = message {
+                assert_eq!(event.run_id, first, "events of an unsubscribed run");
+            }
+        }
+        seen.extend(event_ids(&messages));
+        // The socket outlives its subscriptions.
+        send(&mut socket, &WsClientMessage::Ping).await;
+        assert!(matches!(next_message(&mut socket).await, Some(WsServerMessage::Pong)));
+
+        let stored = stored_ids(runtime.list_events(&first).await.expect("events"));
+        let seen_first: Vec<_> = seen.into_iter().filter(|id| stored.contains(id)).collect();
+        assert_eq!(seen_first, stored);
+    }
+}
+
+#[tokio::test]
+async fn sockets_require_runs_read_and_respect_tenants() {
+    let all = [Scope::RunsCreate, Scope::RunsRead];
+    let auth = AuthConfig {
+        keys: vec![
+            ApiKey::new("shanghai-ops", "shanghai-token", all).with_tenant("shanghai"),
+            ApiKey::new("creator", "create-token", [Scope::RunsCreate]),
+        ],
+    };
+    for runtime in backends() {
+        register_workflows(&runtime).await;
+        let app = router_with_auth(runtime.clone(), auth.clone());
+        let base_url = serve(app.clone()).await;
+        let own = create_run(&runtime, "echo", Some("shanghai")).await;
+        let other = create_run(&runtime, "echo", Some("suzhou")).await;
+
+        let url = format!("{}/v1/ws", base_url);
+        for (token, status) in [(None, 401), (Some("create-token"), 403)] {
+            match connect(&url, token).await {
+                Err(tungstenite::Error::Http(response)) => {
+                    assert_eq!(response.status().as_u16(), status);
+                }
+                other => panic!("expected HTTP {}, got {:?}", status, other.map(|_| ())),
+            }
+        }
+        let url = format!("{}/v1/runs/{}/ws", base_url, other);
+        match connect(&url, Some("shanghai-token")).await {
+            Err(tungstenite::Error::Http(response)) => {
+                assert_eq!(response.status().as_u16(), 404);
+            }
+            other => panic!("expected HTTP 404, got {:?}", other.map(|_| ())),
+        }
+
+        let url = format!("{}/v1/ws", base_url);
+        let mut socket = connect(&url, Some("shanghai-token")).await.expect("connect");
+        for run_id in [&other, &own] {
+            let subscribe = WsClientMessage::Subscribe {
+                run_id: run_id.clone(),
+                after: None,
+            };
+            send(&mut socket, &subscribe).await;
+        }
+        let reply = next_message(&mut socket).await.expect("reply");
+        assert!(matches!(
+            reply,
+            WsServerMessage::Error { run_id: Some(id), error }
+                if id == other && error.code == "not_found"
+        ));
+        assert!(matches!(
+            next_message(&mut socket).await,
+            Some(WsServerMessage::Subscribed { run_id }) if run_id == own
+        ));
+
+        // A plain GET is not an upgrade.
+        let request = axum::http::Request::get("/v1/ws")
+            .header("authorization", "Bearer shanghai-token")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.expect("response");
+        assert!(response.status().is_client_error());
+        let body = response.into_body().collect().await.expect("body").to_bytes();
+        let body: Value = serde_json::from_slice(&body).expect("error body");
+        assert_eq!(body["code"], "invalid_request");
+    }
+}